@@ -1,5 +1,7 @@
 const questionForm = "questionForm";
 let questionTable;
+let questionPage = 1;
+let trashTable;
 
 async function fillForms() {
     setUpForms();
@@ -8,6 +10,11 @@ async function fillForms() {
         .then(json => json.categories);
     fillCategories(categories);
     await fillTable(categories);
+    await fillTrash();
+    await fillReports();
+    await fillAnalytics();
+    await fillDuplicates();
+    await fillTokens();
 }
 
 function fillCategories(categories) {
@@ -24,13 +31,203 @@ function fillCategories(categories) {
 
 async function fillTable(categories) {
     questionTable = document.getElementById("questions");
-    await fetch("/admin/all_questions", { credentials: "include" })
+    const filter = document.getElementById("questionFilter");
+    const categoryFilter = document.getElementById("filterCategory");
+    categories.forEach(cat => {
+        const option = document.createElement("option");
+        option.value = cat.id;
+        option.innerText = cat.name;
+        categoryFilter.add(option);
+    });
+
+    filter.onsubmit = async (e) => {
+        e.preventDefault();
+        await loadQuestions(filter, categories, 1);
+    };
+    document.getElementById("previousPage").onclick =
+        async () => await loadQuestions(filter, categories, questionPage - 1);
+    document.getElementById("nextPage").onclick =
+        async () => await loadQuestions(filter, categories, questionPage + 1);
+
+    await loadQuestions(filter, categories, 1);
+}
+
+async function loadQuestions(filter, categories, page) {
+    const params = new URLSearchParams(new FormData(filter));
+    params.set("page", page);
+
+    await fetch(`/admin/questions?${params}`, { credentials: "include" })
+        .then(async resp => await resp.json())
+        .then(resp => {
+            // the first row holds the headers
+            while (questionTable.rows.length > 1) {
+                questionTable.deleteRow(-1);
+            }
+            resp.questions.forEach(q =>
+                questionTable.appendChild(tableRow(q, categories))
+            );
+
+            questionPage = resp.page;
+            document.getElementById("questionPage").innerText =
+                `${messages.page} ${resp.page} / ${Math.max(resp.pages, 1)} (${resp.total})`;
+            document.getElementById("previousPage").disabled = resp.page <= 1;
+            document.getElementById("nextPage").disabled = resp.page >= resp.pages;
+        });
+}
+
+async function fillTrash() {
+    trashTable = document.getElementById("trash");
+    await fetch("/admin/trashed_questions", { credentials: "include" })
         .then(async resp => await resp.json())
         .then(resp => resp.questions.forEach(q =>
-            questionTable.appendChild(tableRow(q, categories))
+            trashTable.appendChild(trashRow(q))
+        ));
+}
+
+async function fillReports() {
+    const table = document.getElementById("reports");
+    await fetch("/admin/open_reports", { credentials: "include" })
+        .then(async resp => await resp.json())
+        .then(resp => resp.questions.forEach(({ question, reports }) =>
+            table.appendChild(reportRow(question, reports))
         ));
 }
 
+function reportRow(question, reports) {
+    const row = document.createElement("tr");
+    const addButton = (text, onclick) => {
+        const btn = document.createElement("button");
+        btn.innerText = text;
+        btn.onclick = onclick;
+        row.insertCell(-1).appendChild(btn);
+    };
+
+    row.insertCell(-1).innerText = question.id;
+    row.insertCell(-1).innerText = question.string;
+    row.insertCell(-1).appendChild(statusLabel(question.status));
+    row.insertCell(-1).innerText = reports.length;
+    const comments = document.createElement("ul");
+    reports.forEach(report => {
+        const item = document.createElement("li");
+        item.innerText = messages[`report_reason_${report.reason}`]
+            + (report.comment ? `: ${report.comment}` : "");
+        comments.appendChild(item);
+    });
+    row.insertCell(-1).appendChild(comments);
+    addButton(messages.resolve, async () => await resolveReports(question, row));
+    addButton(messages.edit, () => editReported(question));
+    addButton(messages.retire, async () => await retireReported(question, row));
+
+    return row;
+}
+
+async function resolveReports(question, row) {
+    let resp = await fetch(
+        `/admin/resolve_reports/${question.id}`,
+        { method: "post", credentials: "include"}
+    );
+
+    if (resp.ok) {
+        row.parentNode.removeChild(row);
+    }
+}
+
+function editReported(question) {
+    const row = document.getElementById(`question-${question.id}`);
+    if (row) {
+        row.scrollIntoView();
+        row.querySelector("input[name=string]").focus();
+    }
+}
+
+async function retireReported(question, row) {
+    let resp = await fetch(
+        `/admin/retire_question/${question.id}`,
+        { method: "post", credentials: "include"}
+    );
+
+    if (resp.ok) {
+        const retired = await resp.json();
+        const questionRow = document.getElementById(`question-${question.id}`);
+        if (questionRow) {
+            questionRow.querySelector(".status").innerText = messages[`status_${retired.status}`];
+        }
+        await resolveReports(question, row);
+    }
+}
+
+async function fillAnalytics() {
+    const categoryTable = document.getElementById("categoryAnalytics");
+    const analyticsTable = document.getElementById("questionAnalytics");
+    const percent = (rate) => `${Math.round(rate * 100)}%`;
+    const secs = (secs) => `${secs.toFixed(1)}s`;
+    const addRow = (table, cells) => {
+        const row = document.createElement("tr");
+        cells.forEach(text => row.insertCell(-1).innerText = text);
+        table.appendChild(row);
+    };
+
+    await fetch("/admin/analytics", { credentials: "include" })
+        .then(async resp => await resp.json())
+        .then(resp => {
+            resp.categories.forEach(cat => addRow(categoryTable, [
+                cat.name, cat.answers, percent(cat.correct_rate), secs(cat.avg_secs), cat.joker_uses
+            ]));
+            resp.questions.forEach(q => addRow(analyticsTable, [
+                q.question,
+                q.string,
+                q.answers,
+                percent(q.correct_rate),
+                q.picks.join(" / "),
+                secs(q.avg_secs),
+                q.joker_uses,
+                q.flags.map(flagLabel).join(", ")
+            ]));
+        });
+}
+
+function flagLabel(flag) {
+    if (typeof flag === "string") {
+        return messages[`flag_${flag}`];
+    }
+    return `${messages.flag_unpicked_distractor} (${flag.unpicked_distractor})`;
+}
+
+async function fillDuplicates() {
+    const table = document.getElementById("duplicates");
+    await fetch("/admin/duplicate_questions", { credentials: "include" })
+        .then(async resp => await resp.json())
+        .then(resp => resp.clusters.forEach((cluster, i) =>
+            cluster.forEach(q => {
+                const row = document.createElement("tr");
+                row.insertCell(-1).innerText = i + 1;
+                row.insertCell(-1).innerText = q.id;
+                row.insertCell(-1).innerText = q.string;
+                row.insertCell(-1).appendChild(statusLabel(q.status));
+                table.appendChild(row);
+            })
+        ));
+}
+
+function trashRow(question) {
+    const row = document.createElement("tr");
+    const addTextCell = (text) => row.insertCell(-1).innerText = text;
+    const addButton = (text, onclick) => {
+        const btn = document.createElement("button");
+        btn.innerText = text;
+        btn.onclick = onclick;
+        row.insertCell(-1).appendChild(btn);
+    };
+
+    addTextCell(question.id);
+    addTextCell(question.string);
+    addTextCell(question.correct);
+    addButton(messages.restore, async () => await restoreQ(question, row));
+    addButton(messages.purge, async () => await purgeQ(question, row));
+
+    return row;
+}
+
 function setUpForms() {
     const editQuestionForm = document.getElementById(questionForm);
     editQuestionForm.onsubmit = async (e) => await submit(e, editQuestionForm);
@@ -38,6 +235,7 @@ function setUpForms() {
 
 function tableRow(question, categories) {
     const row = document.createElement("tr");
+    row.id = `question-${question.id}`;
     const addCell = (elm) => row.insertCell(-1).appendChild(elm);
     const addTextCell = (name, val) => addCell(textField(name, val));
 
@@ -49,8 +247,13 @@ function tableRow(question, categories) {
     addTextCell("incorrect1", question.incorrect[0]);
     addTextCell("incorrect2", question.incorrect[1]);
     addTextCell("incorrect3", question.incorrect[2]);
+    addTextCell("tags", question.tags.join(", "));
+    addTextCell("locale", question.locale);
+    addCell(mediaUpload(question));
+    addCell(statusLabel(question.status));
     addCell(editButton());
     addCell(deleteButton());
+    statusActions.forEach(action => addCell(button(action, messages[action])));
 
     return row;
 }
@@ -64,9 +267,18 @@ function textField(name, val) {
     return elm;
 }
 
-const editButton = () => button("edit", "Bearbeiten");
+const editButton = () => button("edit", messages.edit);
 
-const deleteButton = () => button("delete", "Löschen");
+const deleteButton = () => button("delete", messages.delete);
+
+const statusActions = ["submit", "approve", "reject", "retire", "reopen"];
+
+function statusLabel(status) {
+    const label = document.createElement("span");
+    label.classList.add("status");
+    label.innerText = messages[`status_${status}`];
+    return label;
+}
 
 function button(action, text) {
     const btn = document.createElement("button");
@@ -94,6 +306,49 @@ function categorySelect(question, categories) {
     return select;
 }
 
+const mediaTargets = () => [
+    messages.question,
+    messages.correct_answer,
+    `${messages.incorrect_answer} 1`,
+    `${messages.incorrect_answer} 2`,
+    `${messages.incorrect_answer} 3`
+];
+
+function mediaUpload(question) {
+    const container = document.createElement("div");
+
+    const target = document.createElement("select");
+    mediaTargets().forEach((text, i) => {
+        const option = document.createElement("option");
+        // the question itself has no answer index
+        option.value = i - 1;
+        option.innerText = text;
+        target.add(option);
+    });
+
+    const file = document.createElement("input");
+    file.type = "file";
+    file.accept = "image/*,audio/*";
+    file.onchange = async () => await uploadMedia(question.id, target.value, file);
+
+    container.append(target, file);
+    return container;
+}
+
+async function uploadMedia(id, target, input) {
+    const file = input.files[0];
+    const query = target >= 0 ? `?answer=${target}` : "";
+
+    let resp = await fetch(
+        `/admin/upload_media/${id}${query}`,
+        { credentials: "include", method: "post", headers: { "Content-Type": file.type }, body: file }
+    );
+
+    if (resp.ok) {
+        input.value = "";
+    }
+}
+
 async function submit(event, form) {
     event.preventDefault();
     const action = event
@@ -117,27 +372,40 @@ async function submit(event, form) {
 
     switch (action) {
         case "edit":
-            await editQ(values);
+            await editQ(values, row);
             break;
         case "delete":
             await deleteQ(values, row);
             break;
+        default:
+            if (statusActions.includes(action)) {
+                await changeStatus(values, action, row);
+            }
     }
 }
 
-async function editQ(values) {
+async function editQ(values, row) {
     let obj = {
         id: parseInt(values.id),
         category_id: parseInt(values.category),
         string: values.string,
         correct: values.correct,
-        incorrect: [values.incorrect1, values.incorrect2, values.incorrect3]
+        incorrect: [values.incorrect1, values.incorrect2, values.incorrect3],
+        tags: values.tags
+            .split(",")
+            .map(tag => tag.trim())
+            .filter(tag => tag.length > 0),
+        locale: values.locale
     };
 
-    await fetch(
+    let resp = await fetch(
         "/admin/edit_question",
         { credentials: "include", method: "put", body: JSON.stringify(obj)}
     );
+
+    if (resp.status === 409) {
+        row.insertCell(-1).innerText = messages.edit_duplicate;
+    }
 }
 
 async function deleteQ(values, row) {
@@ -147,8 +415,123 @@ async function deleteQ(values, row) {
     );
 
     if (resp.ok) {
-        questionTable.removeChild(row)
+        questionTable.removeChild(row);
+        const question = await fetch("/admin/trashed_questions", { credentials: "include" })
+            .then(resp => resp.json())
+            .then(json => json.questions.find(q => q.id === parseInt(values.id)));
+        if (question) {
+            trashTable.appendChild(trashRow(question));
+        }
     }
 }
 
+async function changeStatus(values, action, row) {
+    let resp = await fetch(
+        `/admin/${action}_question/${values.id}`,
+        { method: "post", credentials: "include"}
+    );
 
+    if (resp.ok) {
+        const question = await resp.json();
+        const label = row.querySelector(".status");
+        label.innerText = messages[`status_${question.status}`];
+    }
+}
+
+async function restoreQ(question, row) {
+    let resp = await fetch(
+        `/admin/restore_question/${question.id}`,
+        { method: "put", credentials: "include"}
+    );
+
+    if (resp.ok) {
+        trashTable.removeChild(row);
+        question.deleted_at = null;
+        const categories = await fetch("/admin/all_categories", { credentials: "include" })
+            .then(resp => resp.json())
+            .then(json => json.categories);
+        questionTable.appendChild(tableRow(question, categories));
+    }
+}
+
+async function purgeQ(question, row) {
+    let resp = await fetch(
+        `/admin/purge_question/${question.id}`,
+        { method: "delete", credentials: "include"}
+    );
+
+    if (resp.ok) {
+        trashTable.removeChild(row);
+    } else if (resp.status === 409) {
+        row.insertCell(-1).innerText = messages.purge_answered;
+    }
+}
+
+async function fillTokens() {
+    const table = document.getElementById("tokens");
+    const form = document.getElementById("tokenForm");
+    form.onsubmit = async (e) => {
+        e.preventDefault();
+        await createToken(form, table);
+    };
+
+    await fetch("/admin/api_tokens", { credentials: "include" })
+        .then(async resp => await resp.json())
+        .then(resp => resp.tokens.forEach(token =>
+            table.appendChild(tokenRow(token))
+        ));
+}
+
+function tokenRow(token) {
+    const row = document.createElement("tr");
+    const addTextCell = (text) => row.insertCell(-1).innerText = text;
+
+    addTextCell(token.id);
+    addTextCell(token.name);
+    addTextCell(token.scopes.map(scope => messages[`scope_${scope}`]).join(", "));
+    addTextCell(token.last_used_at
+        ? new Date(token.last_used_at.secs_since_epoch * 1000).toLocaleString()
+        : ""
+    );
+    if (token.revoked_at) {
+        addTextCell(messages.token_revoked);
+    } else {
+        const btn = document.createElement("button");
+        btn.innerText = messages.revoke;
+        btn.onclick = async () => await revokeToken(token, row);
+        row.insertCell(-1).appendChild(btn);
+    }
+
+    return row;
+}
+
+async function createToken(form, table) {
+    const data = new FormData(form);
+    let resp = await fetch(
+        "/admin/api_tokens",
+        {
+            method: "post",
+            credentials: "include",
+            body: JSON.stringify({ name: data.get("name"), scopes: data.getAll("scopes") })
+        }
+    );
+
+    if (resp.ok) {
+        const created = await resp.json();
+        document.getElementById("createdToken").innerText = `${messages.token_created} ${created.token}`;
+        table.appendChild(tokenRow(created.details));
+        form.reset();
+    }
+}
+
+async function revokeToken(token, row) {
+    let resp = await fetch(
+        `/admin/api_tokens/${token.id}`,
+        { method: "delete", credentials: "include"}
+    );
+
+    if (resp.ok) {
+        row.deleteCell(-1);
+        row.insertCell(-1).innerText = messages.token_revoked;
+    }
+}
\ No newline at end of file
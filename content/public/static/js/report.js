@@ -0,0 +1,17 @@
+function sendReport(form) {
+    fetch("/play/report", {
+        credentials: "include",
+        method: "post",
+        headers: { "Content-Type": "application/x-www-form-urlencoded" },
+        body: new URLSearchParams(new FormData(form))
+    }).then(resp => {
+        const status = form.querySelector(".reportStatus");
+        if (resp.ok) {
+            status.innerText = form.dataset.sent;
+            form.querySelector("button").disabled = true;
+        } else if (resp.status === 429) {
+            status.innerText = form.dataset.limited;
+        }
+    });
+    return false;
+}
@@ -0,0 +1,90 @@
+let roomCode;
+let isHost;
+
+function openRoom(code, host) {
+    roomCode = code;
+    isHost = host;
+    const events = new EventSource(`/rooms/${code}/events`, { withCredentials: true });
+    events.addEventListener("state", event => showRoom(JSON.parse(event.data)));
+    // the server counts down, so every player sees the same time
+    events.addEventListener("time_remaining", event =>
+        showRemaining(JSON.parse(event.data).remaining_secs));
+}
+
+function showRoom(room) {
+    const phase = document.getElementById("phase");
+    phase.innerText = phase.dataset[room.phase] || "";
+    document.getElementById("round").innerText = `${room.round} / ${room.rounds}`;
+
+    const question = document.getElementById("question");
+    question.hidden = room.question === null || room.phase !== "question";
+    if (room.question !== null) {
+        showQuestion(room.question);
+    }
+
+    const correct = document.getElementById("correct");
+    correct.innerText = room.correct === null
+        ? ""
+        : `${correct.dataset.prefix} ${room.correct}`;
+
+    showStandings(room.standings);
+
+    if (isHost) {
+        document.getElementById("advance").disabled = room.phase === "finished";
+    }
+}
+
+function showQuestion(question) {
+    document.getElementById("questionText").innerText = question.string;
+    document.getElementById("answered").innerText = question.answered;
+
+    const answers = document.getElementById("answers");
+    // answering is only up to the players, the host just watches
+    if (answers.dataset.question !== question.string) {
+        answers.dataset.question = question.string;
+        answers.innerHTML = "";
+        question.answers.forEach(text => {
+            const button = document.createElement("button");
+            button.innerText = text;
+            button.disabled = isHost;
+            button.onclick = () => sendAnswer(text, answers);
+            answers.appendChild(button);
+        });
+        showRemaining(question.remaining_secs);
+    }
+}
+
+function showRemaining(secs) {
+    document.getElementById("remaining").innerText = secs;
+}
+
+function showStandings(standings) {
+    const table = document.getElementById("standings");
+    while (table.rows.length > 1) {
+        table.deleteRow(1);
+    }
+    standings.forEach(standing => {
+        const row = table.insertRow();
+        row.insertCell().innerText = standing.name;
+        row.insertCell().innerText = standing.points;
+    });
+}
+
+function sendAnswer(answer, answers) {
+    answers
+        .querySelectorAll("button")
+        .forEach(button => button.disabled = true);
+    fetch(`/rooms/${roomCode}/answer`, {
+        credentials: "include",
+        method: "post",
+        headers: { "Content-Type": "application/x-www-form-urlencoded" },
+        body: new URLSearchParams({ answer })
+    });
+}
+
+function advanceRoom() {
+    fetch(`/rooms/${roomCode}/advance`, {
+        credentials: "include",
+        method: "post"
+    });
+}
\ No newline at end of file
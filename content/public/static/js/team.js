@@ -0,0 +1,17 @@
+function followTeam(code) {
+    const events = new EventSource(`/teams/${code}/events`, { withCredentials: true });
+    // whoever answers moves the team on, everyone else follows
+    events.addEventListener("answer_revealed", () => window.location.reload());
+    events.addEventListener("question_started", () => window.location.reload());
+}
+
+// members that left don't get to hold up a majority vote past the answer window
+function settleTeam(code, remainingSecs) {
+    setTimeout(async () => {
+        await fetch(`/teams/${code}/settle`, {
+            credentials: "include",
+            method: "post"
+        });
+        window.location.reload();
+    }, remainingSecs * 1000);
+}
\ No newline at end of file
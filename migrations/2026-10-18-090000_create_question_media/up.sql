@@ -0,0 +1,8 @@
+CREATE TABLE question_media
+(
+    id           SERIAL PRIMARY KEY,
+    question_id  INTEGER NOT NULL REFERENCES questions (id),
+    answer       SMALLINT,
+    file_name    TEXT    NOT NULL,
+    content_type TEXT    NOT NULL
+);
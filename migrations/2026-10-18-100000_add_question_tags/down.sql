@@ -0,0 +1,2 @@
+ALTER TABLE questions
+DROP COLUMN IF EXISTS tags;
\ No newline at end of file
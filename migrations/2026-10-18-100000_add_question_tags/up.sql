@@ -0,0 +1,2 @@
+ALTER TABLE questions
+ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
\ No newline at end of file
@@ -0,0 +1,4 @@
+DROP TABLE IF EXISTS question_translations;
+
+ALTER TABLE questions
+DROP COLUMN IF EXISTS locale;
\ No newline at end of file
@@ -0,0 +1,13 @@
+ALTER TABLE questions
+ADD COLUMN locale TEXT NOT NULL DEFAULT 'de';
+
+CREATE TABLE question_translations
+(
+    id          SERIAL PRIMARY KEY,
+    question_id INTEGER NOT NULL REFERENCES questions (id),
+    locale      TEXT    NOT NULL,
+    string      TEXT    NOT NULL,
+    correct     TEXT    NOT NULL,
+    incorrect   TEXT[3] NOT NULL,
+    UNIQUE (question_id, locale)
+);
@@ -0,0 +1,18 @@
+CREATE TABLE question_revisions
+(
+    id          SERIAL PRIMARY KEY,
+    question_id INTEGER   NOT NULL REFERENCES questions (id),
+    category_id INTEGER   NOT NULL,
+    string      TEXT      NOT NULL,
+    correct     TEXT      NOT NULL,
+    incorrect   TEXT[3]   NOT NULL,
+    tags        TEXT[]    NOT NULL,
+    locale      TEXT      NOT NULL,
+    editor      TEXT,
+    revised_at  TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- the authors of existing questions are unknown
+INSERT INTO question_revisions (question_id, category_id, string, correct, incorrect, tags, locale)
+SELECT id, category_id, string, correct, incorrect, tags, locale
+FROM questions;
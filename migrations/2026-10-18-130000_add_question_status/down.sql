@@ -0,0 +1,4 @@
+ALTER TABLE questions
+DROP COLUMN IF EXISTS status,
+DROP COLUMN IF EXISTS submitted_by,
+DROP COLUMN IF EXISTS approved_by;
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- 0 = draft, 1 = in review, 2 = published, 3 = retired
+ALTER TABLE questions
+ADD COLUMN status       SMALLINT NOT NULL DEFAULT 0,
+ADD COLUMN submitted_by TEXT,
+ADD COLUMN approved_by  TEXT;
+
+-- everything that existed before was live already
+UPDATE questions
+SET status = 2;
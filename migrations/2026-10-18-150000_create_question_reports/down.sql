@@ -0,0 +1 @@
+DROP TABLE IF EXISTS question_reports;
\ No newline at end of file
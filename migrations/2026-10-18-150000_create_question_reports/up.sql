@@ -0,0 +1,12 @@
+CREATE TABLE question_reports
+(
+    id          SERIAL PRIMARY KEY,
+    question_id INTEGER   NOT NULL REFERENCES questions (id),
+    reason      SMALLINT  NOT NULL,
+    comment     TEXT,
+    reported_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    resolved_at TIMESTAMP,
+    resolved_by TEXT
+);
+
+CREATE INDEX question_reports_open ON question_reports (question_id) WHERE resolved_at IS NULL;
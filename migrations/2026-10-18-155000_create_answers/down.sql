@@ -0,0 +1 @@
+DROP TABLE IF EXISTS answers;
\ No newline at end of file
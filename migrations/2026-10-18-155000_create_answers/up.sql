@@ -0,0 +1,14 @@
+-- every single answer given in a game, `chosen` is 0 for the correct answer,
+-- 1 to 3 for the incorrect ones and NULL if the answer didn't match any of them
+CREATE TABLE answers
+(
+    id            SERIAL PRIMARY KEY,
+    question_id   INTEGER   NOT NULL REFERENCES questions (id),
+    chosen        SMALLINT,
+    time_taken_ms INTEGER   NOT NULL,
+    joker_used    BOOLEAN   NOT NULL,
+    answered_at   TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    correct       BOOLEAN   NOT NULL
+);
+
+CREATE INDEX answers_question_id ON answers (question_id);
@@ -0,0 +1,4 @@
+ALTER TABLE answers
+    DROP COLUMN game_id;
+
+DROP TABLE IF EXISTS games;
\ No newline at end of file
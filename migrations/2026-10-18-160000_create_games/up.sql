@@ -0,0 +1,12 @@
+CREATE TABLE games
+(
+    id         SERIAL PRIMARY KEY,
+    player     TEXT      NOT NULL,
+    started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- answers from before games were stored don't belong to one
+ALTER TABLE answers
+    ADD COLUMN game_id INTEGER REFERENCES games (id);
+
+CREATE INDEX answers_game_id ON answers (game_id);
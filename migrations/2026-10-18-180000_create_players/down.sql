@@ -0,0 +1,7 @@
+ALTER TABLE games
+    DROP COLUMN player_id;
+
+ALTER TABLE scores
+    DROP COLUMN player_id;
+
+DROP TABLE IF EXISTS players;
\ No newline at end of file
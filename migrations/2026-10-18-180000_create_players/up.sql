@@ -0,0 +1,17 @@
+CREATE TABLE players
+(
+    id            SERIAL PRIMARY KEY,
+    name          TEXT      NOT NULL UNIQUE,
+    password      TEXT      NOT NULL,
+    registered_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- playing without an account stays possible, so both are optional
+ALTER TABLE scores
+    ADD COLUMN player_id INTEGER REFERENCES players (id);
+
+ALTER TABLE games
+    ADD COLUMN player_id INTEGER REFERENCES players (id);
+
+CREATE INDEX scores_player_id ON scores (player_id);
+CREATE INDEX games_player_id ON games (player_id);
@@ -0,0 +1,2 @@
+ALTER TABLE games
+    DROP COLUMN visitor;
\ No newline at end of file
@@ -0,0 +1,6 @@
+-- an anonymous id kept in a long lived cookie, so returning players
+-- without an account can be recognised as well
+ALTER TABLE games
+    ADD COLUMN visitor TEXT;
+
+CREATE INDEX games_visitor ON games (visitor);
\ No newline at end of file
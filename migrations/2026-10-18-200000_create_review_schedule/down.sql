@@ -0,0 +1 @@
+DROP TABLE IF EXISTS review_schedule;
\ No newline at end of file
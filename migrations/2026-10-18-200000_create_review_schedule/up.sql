@@ -0,0 +1,13 @@
+-- when every player should see a question again in practice mode,
+-- following the leitner system
+CREATE TABLE review_schedule
+(
+    player_id   INTEGER   NOT NULL REFERENCES players (id),
+    question_id INTEGER   NOT NULL REFERENCES questions (id),
+    leitner_box SMALLINT  NOT NULL,
+    due_at      TIMESTAMP NOT NULL,
+    reviewed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (player_id, question_id)
+);
+
+CREATE INDEX review_schedule_due_at ON review_schedule (player_id, due_at);
\ No newline at end of file
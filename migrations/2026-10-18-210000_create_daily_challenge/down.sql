@@ -0,0 +1,4 @@
+ALTER TABLE scores
+    DROP COLUMN daily;
+
+DROP TABLE IF EXISTS daily_plays;
\ No newline at end of file
@@ -0,0 +1,15 @@
+-- everyone gets one attempt at the daily challenge, players are
+-- identified by their account or the visitor cookie
+CREATE TABLE daily_plays
+(
+    day        DATE      NOT NULL,
+    challenger TEXT      NOT NULL,
+    started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (day, challenger)
+);
+
+-- scores of daily challenges have their own leaderboard
+ALTER TABLE scores
+    ADD COLUMN daily DATE;
+
+CREATE INDEX scores_daily ON scores (daily);
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- every game draws its randomness from this seed, so it can be replayed.
+-- games from before don't have one
+ALTER TABLE games
+    ADD COLUMN seed BIGINT;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS team_scores;
\ No newline at end of file
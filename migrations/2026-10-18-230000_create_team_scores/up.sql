@@ -0,0 +1,13 @@
+-- teams play one game together, so their scores are kept apart
+-- from the ones of single players
+CREATE TABLE team_scores
+(
+    id              SERIAL PRIMARY KEY,
+    team            TEXT      NOT NULL,
+    members         TEXT[]    NOT NULL,
+    points          INTEGER   NOT NULL,
+    weighted_points INTEGER   NOT NULL,
+    played_on       TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    duration        BIGINT    NOT NULL,
+    categories      INTEGER[] NOT NULL
+);
@@ -0,0 +1 @@
+DROP TABLE IF EXISTS duels;
\ No newline at end of file
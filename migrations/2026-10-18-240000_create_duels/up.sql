@@ -0,0 +1,13 @@
+-- a challenge to play the same questions as someone else's game,
+-- both games are compared through their answers
+CREATE TABLE duels
+(
+    id              SERIAL PRIMARY KEY,
+    code            TEXT      NOT NULL UNIQUE,
+    seed            BIGINT    NOT NULL,
+    questions       INTEGER[] NOT NULL,
+    challenger_game INTEGER   NOT NULL REFERENCES games (id),
+    opponent_game   INTEGER REFERENCES games (id),
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    finished_at     TIMESTAMP
+);
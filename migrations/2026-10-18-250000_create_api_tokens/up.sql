@@ -0,0 +1,12 @@
+-- tokens for scripts using the admin api, only the hash of the secret is kept
+CREATE TABLE api_tokens
+(
+    id           SERIAL PRIMARY KEY,
+    admin_id     INTEGER   NOT NULL REFERENCES admins (id) ON DELETE CASCADE,
+    name         TEXT      NOT NULL,
+    secret       TEXT      NOT NULL,
+    scopes       TEXT[]    NOT NULL,
+    created_at   TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    last_used_at TIMESTAMP,
+    revoked_at   TIMESTAMP
+);
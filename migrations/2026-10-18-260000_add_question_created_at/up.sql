@@ -0,0 +1,12 @@
+ALTER TABLE questions
+    ADD COLUMN created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP;
+
+-- older questions were created with their first revision
+UPDATE questions
+SET created_at = first_revisions.revised_at
+FROM (
+    SELECT question_id, MIN(revised_at) AS revised_at
+    FROM question_revisions
+    GROUP BY question_id
+) AS first_revisions
+WHERE questions.id = first_revisions.question_id;
@@ -0,0 +1 @@
+DROP TABLE daily_challenges;
\ No newline at end of file
@@ -0,0 +1,7 @@
+-- the questions of a day are picked once and kept, so changes
+-- to the question bank during the day don't change the challenge
+CREATE TABLE daily_challenges
+(
+    day       DATE      PRIMARY KEY,
+    questions INTEGER[] NOT NULL
+);
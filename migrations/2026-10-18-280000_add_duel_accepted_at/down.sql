@@ -0,0 +1,2 @@
+ALTER TABLE duels
+    DROP COLUMN accepted_at;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- when the opponent took the challenge, so one who gave up on it
+-- doesn't keep everyone else from taking it
+ALTER TABLE duels
+    ADD COLUMN accepted_at TIMESTAMP;
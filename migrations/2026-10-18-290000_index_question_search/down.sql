@@ -0,0 +1,2 @@
+DROP INDEX questions_search;
+DROP FUNCTION question_search_text;
\ No newline at end of file
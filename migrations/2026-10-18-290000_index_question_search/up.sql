@@ -0,0 +1,12 @@
+-- array_to_string is only stable because of other element types, for text it never changes.
+-- Declaring that lets the search text be indexed
+CREATE FUNCTION question_search_text(question TEXT, correct TEXT, incorrect TEXT[]) RETURNS TEXT
+    LANGUAGE sql IMMUTABLE
+AS
+$$
+SELECT question || ' ' || correct || ' ' || array_to_string(incorrect, ' ')
+$$;
+
+-- has to be the exact expression the admin search filters with, or it isn't used
+CREATE INDEX questions_search ON questions
+    USING GIN (to_tsvector('simple', question_search_text(string, correct, incorrect)));
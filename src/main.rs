@@ -21,12 +21,13 @@ fn main() {
 }
 
 fn rocket() -> rocket::Rocket {
-    rocket::ignite()
+    let rocket = rocket::ignite()
         .mount("/", routes![
             routing::index,
-            routing::static_page,
             routing::static_content,
+            routing::media,
             routing::favicon,
+            routing::api::openapi::openapi,
             routing::pages::start::settings,
             routing::pages::play::new_game,
             routing::pages::play::answer,
@@ -35,23 +36,101 @@ fn rocket() -> rocket::Rocket {
             routing::pages::play::use_joker,
             routing::pages::play::resume,
             routing::pages::play::failed,
+            routing::pages::play::missed,
+            routing::pages::play::events,
+            routing::pages::play::report,
+            routing::pages::leaderboard::leaderboard,
+            routing::pages::daily::daily,
+            routing::pages::daily::play,
+            routing::pages::duels::duel,
+            routing::pages::duels::accept,
+            routing::pages::rooms::rooms,
+            routing::pages::rooms::create,
+            routing::pages::rooms::host,
+            routing::pages::rooms::join,
+            routing::pages::rooms::room,
+            routing::pages::rooms::advance,
+            routing::pages::rooms::answer,
+            routing::pages::rooms::events,
+            routing::pages::teams::teams,
+            routing::pages::teams::create,
+            routing::pages::teams::join,
+            routing::pages::teams::team,
+            routing::pages::teams::answer,
+            routing::pages::teams::settle_overdue,
+            routing::pages::teams::events,
+            routing::pages::teams::leaderboard,
+            routing::pages::player::player,
+            routing::pages::player::register,
+            routing::pages::player::login,
+            routing::pages::player::logout,
+            routing::pages::player::profile,
+            routing::pages::player::public_profile,
             routing::pages::admin::admin,
+            routing::pages::admin::login,
             routing::pages::admin::verify,
             routing::pages::admin::register,
             routing::pages::admin::add_question,
             routing::pages::admin::add_category,
             routing::pages::admin::api::delete_question,
+            routing::pages::admin::api::trashed_questions,
+            routing::pages::admin::api::restore_question,
+            routing::pages::admin::api::purge_question,
             routing::pages::admin::api::edit_question,
             routing::pages::admin::api::all_questions,
-            routing::pages::admin::api::all_categories
+            routing::pages::admin::api::search_questions,
+            routing::pages::admin::api::duplicate_questions,
+            routing::pages::admin::api::question_analytics,
+            routing::pages::admin::api::open_reports,
+            routing::pages::admin::api::resolve_reports,
+            routing::pages::admin::api::all_categories,
+            routing::pages::admin::api::all_tags,
+            routing::pages::admin::api::tagged_questions,
+            routing::pages::admin::api::upload_media,
+            routing::pages::admin::api::question_media,
+            routing::pages::admin::api::delete_media,
+            routing::pages::admin::api::question_translations,
+            routing::pages::admin::api::translate_question,
+            routing::pages::admin::api::delete_translation,
+            routing::pages::admin::api::question_revisions,
+            routing::pages::admin::api::revision_diff,
+            routing::pages::admin::api::restore_revision,
+            routing::pages::admin::api::submit_question,
+            routing::pages::admin::api::approve_question,
+            routing::pages::admin::api::reject_question,
+            routing::pages::admin::api::retire_question,
+            routing::pages::admin::api::reopen_question,
+            routing::pages::admin::api::api_tokens,
+            routing::pages::admin::api::create_api_token,
+            routing::pages::admin::api::revoke_api_token
+        ])
+        .mount("/api/v1", routes![
+            routing::api::v1::new_game,
+            routing::api::v1::game,
+            routing::api::v1::question,
+            routing::api::v1::next,
+            routing::api::v1::answer,
+            routing::api::v1::joker,
+            routing::api::v1::pause,
+            routing::api::v1::resume,
+            routing::api::v1::end_game
         ])
         .register(catchers![
             routing::catchers::unauthorized
         ])
         .attach(templates())
         .attach(models::db::DbConn::fairing())
+        .attach(models::media::fairing())
+        .attach(models::daily::fairing())
         .manage(models::web::init_game_states())
+        .manage(models::web::init_report_limits())
         .manage(models::web::init_admin_sessions())
+        .manage(models::web::init_rooms())
+        .manage(models::web::init_teams());
+
+    // everything is mounted by now, so the description covers every route
+    let document = routing::api::openapi::document(rocket.routes());
+    rocket.manage(document)
 }
 
 fn templates() -> impl Fairing {
@@ -65,7 +144,14 @@ mod test {
     use {
         std::sync::Mutex,
         once_cell::sync::Lazy,
-        diesel::{PgConnection, Connection}
+        diesel::{PgConnection, Connection, QueryResult},
+        crate::models::{
+            i18n::Locale,
+            db::{
+                StatusError,
+                models::{Category, NewCategory, Question, NewQuestion}
+            }
+        }
     };
 
     pub static CONN: Lazy<Mutex<PgConnection>> = Lazy::new(|| {
@@ -75,6 +161,33 @@ mod test {
         )
     });
 
+    /// A new category with a published question for every text,
+    /// each of them has "0" as its correct answer and "1", "2" and "3" as incorrect ones.
+    pub fn insert_questions(category: &str, strings: &[&str], conn: &PgConnection) -> QueryResult<(Category, Vec<Question>)> {
+        let cat = Category::insert(&NewCategory { name: category }, conn)?;
+        let incorrect = ["1".to_string(), "2".into(), "3".into()];
+
+        let questions = strings
+            .iter()
+            .map(|string| {
+                let question = Question::insert(
+                    &NewQuestion::with_category(&cat, string, "0", &incorrect, &[], Locale::En),
+                    "Tom",
+                    conn
+                )?;
+
+                Question::submit_for_review(question.id(), "Tom", conn)
+                    .and_then(|_| Question::approve(question.id(), "Ann", conn))
+                    .map_err(|e| match e {
+                        StatusError::Query(e) => e,
+                        e => panic!("couldn't publish a new question: {:?}", e)
+                    })
+            })
+            .collect::<QueryResult<_>>()?;
+
+        Ok((cat, questions))
+    }
+
     // re-export the rocket initialiser so tests (and only tests) can use it
     pub fn rocket() -> rocket::Rocket {
         super::rocket()
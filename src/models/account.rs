@@ -1,13 +1,18 @@
 use {
     rocket::FromForm,
+    serde::{Serialize, Deserialize},
     std::num::NonZeroU8,
     once_cell::sync::Lazy,
-    rand::{thread_rng, Rng},
+    rand::{thread_rng, Rng, distributions::Alphanumeric},
     argon2::{Config, Variant},
+    sha2::{Sha256, Digest},
+    constant_time_eq::constant_time_eq,
     crate::models::db::{
         AdminError,
+        PlayerError,
         Connection,
-        models::{Admin, NewAdmin}
+        ApiTokenId,
+        models::{Admin, NewAdmin, Player, NewPlayer, ApiToken, NewApiToken}
     }
 };
 
@@ -25,7 +30,9 @@ fn config() -> Config<'static> {
 #[derive(Debug)]
 pub enum Error {
     Hash(argon2::Error),
-    Insert(AdminError)
+    Insert(AdminError),
+    InsertPlayer(PlayerError),
+    Query(diesel::result::Error)
 }
 
 #[derive(FromForm, Debug)]
@@ -35,7 +42,7 @@ pub struct Credentials {
 }
 
 pub fn register(cred: &Credentials, conn: &Connection) -> Result<(), Error> {
-    hash(&cred.password, &salt())
+    hash(&cred.password, &salt(&mut thread_rng()))
         .map_err(Error::Hash)
         .and_then(|hash| Admin::insert(
             &NewAdmin {
@@ -60,6 +67,100 @@ pub fn verify(cred: &Credentials, conn: &Connection) -> Result<bool, Error> {
         )?
 }
 
+/// Players use the same hashing as admins, but their accounts are completely separate.
+pub fn register_player(cred: &Credentials, conn: &Connection) -> Result<Player, Error> {
+    hash(&cred.password, &salt(&mut thread_rng()))
+        .map_err(Error::Hash)
+        .and_then(|hash| Player::insert(
+            &NewPlayer {
+                name: &cred.name,
+                password: &hash
+            },
+            conn
+        ).map_err(Error::InsertPlayer))
+}
+
+pub fn verify_player(cred: &Credentials, conn: &Connection) -> Result<Option<Player>, Error> {
+    let player = Player::named(&cred.name, conn)
+        .map_err(Error::Query)?;
+
+    match player {
+        Some(player) => verify_password(&cred.password, &player.password)
+            .map_err(Error::Hash)
+            .map(|verified| if verified { Some(player) } else { None }),
+        None => Ok(None)
+    }
+}
+
+/// What an api token may be used for.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Read,
+    Write
+}
+
+impl Scope {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write"
+        }
+    }
+}
+
+const TOKEN_SECRET_LENGTH: usize = 40;
+
+/// Returns the token itself along with what's stored of it,
+/// it can't be recovered later on.
+pub fn create_token(admin: &Admin, name: &str, scopes: &[Scope], conn: &Connection) -> Result<(ApiToken, String), Error> {
+    let secret = thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(TOKEN_SECRET_LENGTH)
+        .collect::<String>();
+    let hash = token_hash(&secret);
+    let scopes = scopes
+        .iter()
+        .map(|scope| scope.as_str().to_owned())
+        .collect::<Vec<_>>();
+
+    let stored = ApiToken::insert(
+        &NewApiToken {
+            admin_id: *admin.id(),
+            name,
+            secret: &hash,
+            scopes: &scopes
+        },
+        conn
+    ).map_err(Error::Query)?;
+    let token = format!("{}.{}", *stored.id(), secret);
+
+    Ok((stored, token))
+}
+
+/// Tokens look like `<id>.<secret>`, the id saves hashing the secret against every stored one.
+/// Returns the admin the token belongs to, if it's valid for the scope.
+pub fn verify_token(token: &str, scope: Scope, conn: &Connection) -> Result<Option<Admin>, Error> {
+    let mut parts = token.splitn(2, '.');
+    let (id, secret) = match (parts.next().map(str::parse::<ApiTokenId>), parts.next()) {
+        (Some(Ok(id)), Some(secret)) => (id, secret),
+        _ => return Ok(None)
+    };
+
+    let stored = match ApiToken::with_id(id, conn).map_err(Error::Query)? {
+        Some(stored) if stored.revoked_at.is_none() && stored.scopes.iter().any(|s| s == scope.as_str()) => stored,
+        _ => return Ok(None)
+    };
+    if !constant_time_eq(token_hash(secret).as_bytes(), stored.secret.as_bytes()) {
+        return Ok(None)
+    }
+
+    ApiToken::mark_used(id, conn)
+        .and_then(|_| stored.admin(conn))
+        .map(Some)
+        .map_err(Error::Query)
+}
+
 fn verify_password(password: &str, hash: &str) -> argon2::Result<bool> {
     argon2::verify_encoded(
         hash,
@@ -75,8 +176,16 @@ fn hash(password: &str, salt: &str) -> argon2::Result<String> {
     )
 }
 
-fn salt() -> String {
-    let mut rng = thread_rng();
+// unlike passwords, the secrets are long and random, so a plain hash is enough
+// and checking a token doesn't take as long as checking a password
+fn token_hash(secret: &str) -> String {
+    Sha256::digest(secret.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+fn salt<R: Rng>(rng: &mut R) -> String {
     (0..128)
         .map(|_| rng.gen())
         .map(NonZeroU8::get)
@@ -110,4 +219,90 @@ mod test {
 
         assert!(verified)
     }
+
+    #[test]
+    fn salt_depends_only_on_rng() {
+        use rand::{SeedableRng, rngs::StdRng};
+
+        let salt_with = |seed| salt(&mut StdRng::seed_from_u64(seed));
+
+        assert_eq!(salt_with(7), salt_with(7));
+        assert_ne!(salt_with(7), salt_with(8));
+        assert_eq!(salt_with(7).chars().count(), 128)
+    }
+
+    #[test]
+    fn tokens_need_their_scope() {
+        let credentials = Credentials {
+            name: "Gerald".to_string(),
+            password: "automate everything".to_string()
+        };
+
+        let conn = CONN
+            .lock()
+            .unwrap();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            register(&credentials, &conn)?;
+            let admin = Admin::named(&credentials.name, &conn)
+                .map_err(Error::Query)?
+                .unwrap();
+            let (stored, token) = create_token(&admin, "import", &[Scope::Read], &conn)?;
+
+            let verified = verify_token(&token, Scope::Read, &conn)?;
+            assert_eq!(verified.map(|admin| admin.name), Some(credentials.name.clone()));
+            assert!(verify_token(&token, Scope::Write, &conn)?.is_none());
+
+            // using it again right away doesn't record the use again
+            let last_used = || ApiToken::with_id(stored.id(), &conn)
+                .map(|stored| stored.and_then(|stored| stored.last_used_at))
+                .map_err(Error::Query);
+            let first_use = last_used()?;
+            assert!(first_use.is_some());
+            verify_token(&token, Scope::Read, &conn)?;
+            assert_eq!(last_used()?, first_use);
+
+            let guessed = format!("{}.{}", *stored.id(), "x".repeat(TOKEN_SECRET_LENGTH));
+            assert!(verify_token(&guessed, Scope::Read, &conn)?.is_none());
+            assert!(verify_token("not a token", Scope::Read, &conn)?.is_none());
+
+            ApiToken::revoke(stored.id(), admin.id(), &conn)
+                .map_err(Error::Query)?;
+            assert!(verify_token(&token, Scope::Read, &conn)?.is_none());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn player_registration() {
+        let credentials = Credentials {
+            name: "Georgina".to_string(),
+            password: "hunter2".to_string()
+        };
+
+        let conn = CONN
+            .lock()
+            .unwrap();
+
+        conn.test_transaction::<_, Error, _>(|| {
+            let registered = register_player(&credentials, &conn)?;
+            let verified = verify_player(&credentials, &conn)?;
+            assert_eq!(verified.map(|player| player.id()), Some(registered.id()));
+
+            let wrong = Credentials {
+                password: "hunter3".to_string(),
+                ..credentials
+            };
+            assert!(verify_player(&wrong, &conn)?.is_none());
+
+            // the failed insert aborts the transaction, so this has to come last
+            match register_player(&wrong, &conn) {
+                Err(Error::InsertPlayer(PlayerError::NameInUse)) => { /* good */ },
+                _ => panic!()
+            };
+
+            Ok(())
+        })
+    }
 }
\ No newline at end of file
@@ -0,0 +1,200 @@
+use {
+    serde::Serialize,
+    diesel::QueryResult,
+    std::collections::HashMap,
+    crate::models::db::{
+        Connection,
+        QuestionId,
+        CategoryId,
+        ops::AnswerTally,
+        models::{Answer, Question, Category}
+    }
+};
+
+// below this many answers the numbers are too random to flag anything
+const MIN_ANSWERS_FOR_FLAGS: i64 = 20;
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Flag {
+    AlwaysCorrect,
+    NeverCorrect,
+    /// Contains the index of the incorrect answer, starting at 1.
+    UnpickedDistractor(i16)
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    answers: i64,
+    correct: i64,
+    total_time_ms: i64,
+    joker_uses: i64
+}
+
+impl Totals {
+    fn add(&mut self, tally: &AnswerTally) {
+        self.answers += tally.count;
+        self.total_time_ms += tally.total_time_ms.unwrap_or_default();
+        if tally.chosen == Some(0) {
+            self.correct += tally.count;
+        }
+        if tally.joker_used {
+            self.joker_uses += tally.count;
+        }
+    }
+
+    fn correct_rate(&self) -> f32 {
+        self.correct as f32 / self.answers.max(1) as f32
+    }
+
+    fn avg_secs(&self) -> f32 {
+        self.total_time_ms as f32 / self.answers.max(1) as f32 / 1000.
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct QuestionAnalytics {
+    question: QuestionId,
+    category: CategoryId,
+    string: String,
+    answers: i64,
+    correct_rate: f32,
+    /// How often each answer was picked, the correct one first.
+    picks: [i64; 4],
+    avg_secs: f32,
+    joker_uses: i64,
+    flags: Vec<Flag>
+}
+
+#[derive(Serialize, Debug)]
+pub struct CategoryAnalytics {
+    category: CategoryId,
+    name: String,
+    answers: i64,
+    correct_rate: f32,
+    avg_secs: f32,
+    joker_uses: i64
+}
+
+#[derive(Serialize, Debug)]
+pub struct Analytics {
+    questions: Vec<QuestionAnalytics>,
+    categories: Vec<CategoryAnalytics>
+}
+
+pub fn flags(answers: i64, picks: &[i64; 4]) -> Vec<Flag> {
+    if answers < MIN_ANSWERS_FOR_FLAGS {
+        return vec![]
+    }
+
+    let mut flags = vec![];
+    if picks[0] == answers {
+        flags.push(Flag::AlwaysCorrect);
+    } else if picks[0] == 0 {
+        flags.push(Flag::NeverCorrect);
+    }
+
+    flags.extend((1..4)
+        .filter(|&i| picks[i] == 0)
+        .map(|i| Flag::UnpickedDistractor(i as i16))
+    );
+
+    flags
+}
+
+/// Only takes answers into account that were given since they are recorded one by one,
+/// so the numbers can differ from the totals in the question stats.
+pub fn load(conn: &Connection) -> QueryResult<Analytics> {
+    let tallies = Answer::tallies(conn)?;
+    let categories = Category::load_all(conn)?;
+
+    let mut per_question = HashMap::<_, (Totals, [i64; 4])>::new();
+    for tally in &tallies {
+        let (totals, picks) = per_question
+            .entry(tally.question_id())
+            .or_default();
+        totals.add(tally);
+        if let Some(chosen) = tally.chosen.filter(|&chosen| chosen >= 0 && chosen < 4) {
+            picks[chosen as usize] += tally.count;
+        }
+    }
+
+    let mut per_category = HashMap::<CategoryId, Totals>::new();
+    let mut questions = Question::load_all(conn)?
+        .into_iter()
+        .map(|question| {
+            let (totals, picks) = per_question
+                .get(&question.id())
+                .copied()
+                .unwrap_or_default();
+
+            let cat_totals = per_category
+                .entry(question.category_id())
+                .or_default();
+            cat_totals.answers += totals.answers;
+            cat_totals.correct += totals.correct;
+            cat_totals.total_time_ms += totals.total_time_ms;
+            cat_totals.joker_uses += totals.joker_uses;
+
+            QuestionAnalytics {
+                question: question.id(),
+                category: question.category_id(),
+                flags: flags(totals.answers, &picks),
+                string: question.string,
+                answers: totals.answers,
+                correct_rate: totals.correct_rate(),
+                picks,
+                avg_secs: totals.avg_secs(),
+                joker_uses: totals.joker_uses
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // flagged questions are the interesting ones, so they come first
+    questions.sort_by_key(|q| (q.flags.is_empty(), q.question));
+
+    let categories = categories
+        .into_iter()
+        .map(|cat| {
+            let totals = per_category
+                .get(&cat.id())
+                .copied()
+                .unwrap_or_default();
+
+            CategoryAnalytics {
+                category: cat.id(),
+                name: cat.name,
+                answers: totals.answers,
+                correct_rate: totals.correct_rate(),
+                avg_secs: totals.avg_secs(),
+                joker_uses: totals.joker_uses
+            }
+        })
+        .collect();
+
+    Ok(Analytics {
+        questions,
+        categories
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flags_suspicious_questions() {
+        assert!(flags(10, &[10, 0, 0, 0]).is_empty());
+        assert_eq!(
+            flags(30, &[30, 0, 0, 0]),
+            vec![
+                Flag::AlwaysCorrect,
+                Flag::UnpickedDistractor(1),
+                Flag::UnpickedDistractor(2),
+                Flag::UnpickedDistractor(3)
+            ]
+        );
+        assert_eq!(flags(30, &[0, 10, 10, 10]), vec![Flag::NeverCorrect]);
+        assert_eq!(flags(30, &[15, 10, 0, 5]), vec![Flag::UnpickedDistractor(2)])
+    }
+}
@@ -0,0 +1,134 @@
+use {
+    diesel::QueryResult,
+    chrono::{Datelike, FixedOffset, NaiveDate, Utc},
+    rand::{
+        SeedableRng,
+        seq::SliceRandom
+    },
+    rand_chacha::ChaCha8Rng,
+    rocket::{
+        config::ConfigError,
+        fairing::{Fairing, AdHoc}
+    },
+    crate::models::{
+        i18n::Locale,
+        db::{
+            Connection,
+            QuestionId,
+            Returning,
+            models::{Category, DailyChallenge, NewDailyChallenge, Question}
+        }
+    }
+};
+
+pub const QUESTIONS: usize = 10;
+
+/// The timezone whose midnight starts a new daily challenge,
+/// configured in minutes east of UTC as `daily_utc_offset`.
+#[derive(Debug, Clone, Copy)]
+pub struct DailyOffset(FixedOffset);
+
+impl DailyOffset {
+    pub fn today(&self) -> NaiveDate {
+        Utc::now()
+            .with_timezone(&self.0)
+            .date()
+            .naive_local()
+    }
+}
+
+/// The categories the daily challenge asks questions from, configured
+/// by name as `daily_categories`. Without any, every category takes part.
+#[derive(Debug, Clone, Default)]
+pub struct DailyCategories(Vec<String>);
+
+impl DailyCategories {
+    fn includes(&self, category: &Category) -> bool {
+        self.0.is_empty() || self.0.contains(&category.name)
+    }
+}
+
+pub fn fairing() -> impl Fairing {
+    AdHoc::on_attach("Daily challenge", |rocket| {
+        let minutes = rocket
+            .config()
+            .get_int("daily_utc_offset")
+            .unwrap_or(0);
+        let categories = match rocket.config().get_slice("daily_categories") {
+            Ok(names) => names
+                .iter()
+                .map(|name| name.as_str().map(String::from))
+                .collect::<Option<Vec<_>>>(),
+            Err(ConfigError::Missing(_)) => Some(Vec::new()),
+            Err(_) => None
+        };
+
+        match (FixedOffset::east_opt(minutes as i32 * 60), categories) {
+            (Some(offset), Some(categories)) => Ok(rocket
+                .manage(DailyOffset(offset))
+                .manage(DailyCategories(categories))
+            ),
+            _ => Err(rocket)
+        }
+    })
+}
+
+/// Picks the same items for everyone on the same day.
+pub fn pick<T: Copy>(day: NaiveDate, from: &[T], amount: usize) -> Vec<T> {
+    let mut rng = ChaCha8Rng::seed_from_u64(day.num_days_from_ce() as u64);
+    from.choose_multiple(&mut rng, amount)
+        .copied()
+        .collect()
+}
+
+/// The day's questions are picked by the first request of the day and stored,
+/// later requests get the same ones even if the question bank changed in between.
+pub fn question_ids(day: NaiveDate, categories: &DailyCategories, conn: &Connection) -> QueryResult<Vec<QuestionId>> {
+    if let Some(challenge) = DailyChallenge::of_day(day, conn)? {
+        return Ok(challenge.questions())
+    }
+
+    let selected = Category::load_all(conn)?
+        .iter()
+        .filter(|cat| categories.includes(cat))
+        .map(Category::id)
+        .collect::<Vec<_>>();
+    let picked = pick(day, &Question::published_ids(&selected, conn)?, QUESTIONS);
+
+    // an empty bank shouldn't leave the whole day without a challenge
+    if picked.is_empty() {
+        return Ok(picked)
+    }
+
+    DailyChallenge::insert(&NewDailyChallenge { day, questions: picked }, conn)
+        .map(|challenge| challenge.questions())
+}
+
+pub fn questions(day: NaiveDate, categories: &DailyCategories, locale: Locale, conn: &Connection) -> QueryResult<Vec<Question>> {
+    Question::load_translated(&question_ids(day, categories, conn)?, locale, conn)
+}
+
+/// Identifies someone taking the challenge, so they can only do it once a day.
+pub fn challenger(returning: Returning) -> String {
+    match returning {
+        Returning::Player(player) => format!("player:{}", *player),
+        Returning::Visitor(visitor) => format!("visitor:{}", visitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn same_day_same_questions() {
+        let ids = (0..100).collect::<Vec<_>>();
+        let day = NaiveDate::from_ymd(2020, 2, 13);
+
+        let picked = pick(day, &ids, QUESTIONS);
+        assert_eq!(picked.len(), QUESTIONS);
+        assert_eq!(picked, pick(day, &ids, QUESTIONS));
+        assert_ne!(picked, pick(day.succ(), &ids, QUESTIONS));
+        assert_eq!(pick(day, &ids[..3], QUESTIONS).len(), 3)
+    }
+}
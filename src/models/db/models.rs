@@ -1,10 +1,33 @@
 use {
     serde::{Serialize, Deserialize},
+    chrono::NaiveDate,
     std::time::{Duration, SystemTime},
     diesel::{Identifiable, Queryable, Associations},
+    crate::models::i18n::Locale,
     super::{
         CategoryId,
-        schema::{questions, question_stats, categories, scores, admins}
+        QuestionId,
+        QuestionStatus,
+        ReportReason,
+        schema::{
+            answers,
+            games,
+            daily_plays,
+            duels,
+            players,
+            questions,
+            question_stats,
+            question_media,
+            question_translations,
+            question_revisions,
+            question_reports,
+            categories,
+            scores,
+            team_scores,
+            admins,
+            api_tokens,
+            review_schedule
+        }
     }
 };
 
@@ -20,9 +43,114 @@ pub struct Question {
     pub string: String,
     pub correct: String,
     #[diesel(deserialize_as = "Incorrect")]
+    pub incorrect: [String; NUM_INCORRECT],
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default = "default_locale")]
+    pub locale: String,
+    #[serde(default)]
+    pub status: QuestionStatus,
+    #[serde(default)]
+    pub submitted_by: Option<String>,
+    #[serde(default)]
+    pub approved_by: Option<String>,
+    #[serde(default)]
+    pub deleted_at: Option<SystemTime>,
+    /// Edits don't send it, it never changes anyway.
+    #[serde(default = "SystemTime::now")]
+    pub created_at: SystemTime
+}
+
+fn default_locale() -> String {
+    Locale::default()
+        .code()
+        .into()
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, PartialEq, Debug, Clone)]
+#[belongs_to(Question)]
+#[table_name = "question_translations"]
+pub struct QuestionTranslation {
+    pub(super) id: i32,
+    pub(super) question_id: i32,
+    pub locale: String,
+    pub string: String,
+    pub correct: String,
+    #[diesel(deserialize_as = "Incorrect")]
     pub incorrect: [String; NUM_INCORRECT]
 }
 
+#[derive(Identifiable, Queryable, Associations, Serialize, PartialEq, Debug, Clone)]
+#[belongs_to(Question)]
+#[table_name = "question_revisions"]
+pub struct QuestionRevision {
+    pub(super) id: i32,
+    pub(super) question_id: i32,
+    pub(super) category_id: i32,
+    pub string: String,
+    pub correct: String,
+    #[diesel(deserialize_as = "Incorrect")]
+    pub incorrect: [String; NUM_INCORRECT],
+    pub tags: Vec<String>,
+    pub locale: String,
+    pub editor: Option<String>,
+    pub revised_at: SystemTime
+}
+
+#[derive(Identifiable, Queryable, Associations, Serialize, PartialEq, Debug, Clone)]
+#[belongs_to(Question)]
+#[table_name = "question_reports"]
+pub struct QuestionReport {
+    pub(super) id: i32,
+    pub(super) question_id: i32,
+    pub reason: ReportReason,
+    pub comment: Option<String>,
+    pub reported_at: SystemTime,
+    pub resolved_at: Option<SystemTime>,
+    pub resolved_by: Option<String>
+}
+
+#[derive(Identifiable, Queryable, Associations, PartialEq, Debug, Clone)]
+#[belongs_to(Question)]
+#[belongs_to(Game)]
+#[table_name = "answers"]
+pub struct Answer {
+    pub(super) id: i32,
+    pub(super) question_id: i32,
+    pub chosen: Option<i16>,
+    pub time_taken_ms: i32,
+    pub joker_used: bool,
+    pub answered_at: SystemTime,
+    pub(super) game_id: Option<i32>,
+    pub correct: bool
+}
+
+#[derive(Identifiable, Queryable, PartialEq, Debug, Clone)]
+#[table_name = "games"]
+pub struct Game {
+    pub(super) id: i32,
+    pub player: String,
+    pub started_at: SystemTime,
+    pub(super) player_id: Option<i32>,
+    pub visitor: Option<String>,
+    pub seed: Option<i64>
+}
+
+#[derive(Identifiable, Queryable, PartialEq, Debug, Clone)]
+#[table_name = "duels"]
+pub struct Duel {
+    pub(super) id: i32,
+    pub code: String,
+    pub seed: i64,
+    pub(super) questions: Vec<i32>,
+    pub(super) challenger_game: i32,
+    pub(super) opponent_game: Option<i32>,
+    pub created_at: SystemTime,
+    /// Set once the opponent's game is over, their answers aren't shown before.
+    pub finished_at: Option<SystemTime>,
+    pub accepted_at: Option<SystemTime>
+}
+
 #[derive(Identifiable, Queryable, Associations, PartialEq, Debug, Clone)]
 #[belongs_to(Question)]
 #[table_name = "question_stats"]
@@ -33,6 +161,17 @@ pub struct QuestionStats {
     pub num_incorrect: i32
 }
 
+#[derive(Identifiable, Queryable, Associations, Serialize, PartialEq, Debug, Clone)]
+#[belongs_to(Question)]
+#[table_name = "question_media"]
+pub struct QuestionMedia {
+    pub(super) id: i32,
+    pub(super) question_id: i32,
+    pub answer: Option<i16>,
+    pub file_name: String,
+    pub content_type: String
+}
+
 #[derive(Identifiable, Queryable, Serialize, PartialEq, Eq, Hash, Debug, Clone)]
 #[table_name = "categories"]
 pub struct Category {
@@ -51,9 +190,64 @@ pub struct Score {
     #[diesel(deserialize_as = "DurationSecs")]
     pub duration: Duration,
     #[diesel(deserialize_as = "ScoreCategories")]
+    pub categories: Vec<CategoryId>,
+    pub(super) player_id: Option<i32>,
+    /// The day of the daily challenge the score was achieved in.
+    #[serde(skip)]
+    pub daily: Option<NaiveDate>
+}
+
+#[derive(Identifiable, Queryable, Serialize, PartialEq, Debug, Clone)]
+#[table_name = "team_scores"]
+pub struct TeamScore {
+    pub(super) id: i32,
+    pub team: String,
+    pub members: Vec<String>,
+    pub points: i32,
+    pub weighted_points: i32,
+    pub played_on: SystemTime,
+    #[diesel(deserialize_as = "DurationSecs")]
+    pub duration: Duration,
+    #[diesel(deserialize_as = "ScoreCategories")]
     pub categories: Vec<CategoryId>
 }
 
+#[derive(Identifiable, Queryable, PartialEq, Debug, Clone)]
+#[table_name = "players"]
+pub struct Player {
+    pub(super) id: i32,
+    pub name: String,
+    pub password: String,
+    pub registered_at: SystemTime
+}
+
+#[derive(Queryable, Associations, PartialEq, Debug, Clone)]
+#[belongs_to(Player)]
+#[belongs_to(Question)]
+#[table_name = "review_schedule"]
+pub struct ReviewEntry {
+    pub(super) player_id: i32,
+    pub(super) question_id: i32,
+    pub leitner_box: i16,
+    pub due_at: SystemTime,
+    pub reviewed_at: SystemTime
+}
+
+#[derive(Queryable, PartialEq, Debug, Clone)]
+#[table_name = "daily_challenges"]
+pub struct DailyChallenge {
+    pub day: NaiveDate,
+    pub(super) questions: Vec<i32>
+}
+
+#[derive(Queryable, PartialEq, Debug, Clone)]
+#[table_name = "daily_plays"]
+pub struct DailyPlay {
+    pub day: NaiveDate,
+    pub challenger: String,
+    pub started_at: SystemTime
+}
+
 #[derive(Identifiable, Queryable, PartialEq, Debug, Clone)]
 #[table_name = "admins"]
 pub struct Admin {
@@ -62,6 +256,22 @@ pub struct Admin {
     pub password: String
 }
 
+#[derive(Identifiable, Queryable, Serialize, PartialEq, Debug, Clone)]
+#[table_name = "api_tokens"]
+pub struct ApiToken {
+    pub(super) id: i32,
+    #[serde(skip)]
+    pub(super) admin_id: i32,
+    pub name: String,
+    /// Only the SHA-256 hash, the secret itself is shown once when creating the token.
+    #[serde(skip)]
+    pub secret: String,
+    pub scopes: Vec<String>,
+    pub created_at: SystemTime,
+    pub last_used_at: Option<SystemTime>,
+    pub revoked_at: Option<SystemTime>
+}
+
 pub use {
     load::*,
     insert::*
@@ -169,7 +379,86 @@ mod insert {
         pub category_id: i32,
         pub string: &'a str,
         pub correct: &'a str,
-        pub incorrect: &'a [String]
+        pub incorrect: &'a [String],
+        pub tags: &'a [String],
+        pub locale: &'a str
+    }
+
+    #[derive(Insertable, AsChangeset, Deserialize, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "question_translations"]
+    pub struct NewQuestionTranslation {
+        pub question_id: i32,
+        pub locale: String,
+        pub string: String,
+        pub correct: String,
+        pub incorrect: Vec<String>
+    }
+
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "question_revisions"]
+    pub struct NewQuestionRevision<'a> {
+        pub question_id: i32,
+        pub category_id: i32,
+        pub string: &'a str,
+        pub correct: &'a str,
+        pub incorrect: &'a [String],
+        pub tags: &'a [String],
+        pub locale: &'a str,
+        pub editor: Option<&'a str>
+    }
+
+    #[derive(Insertable, Debug, PartialEq, Clone)]
+    #[table_name = "question_reports"]
+    pub struct NewQuestionReport<'a> {
+        pub question_id: i32,
+        pub reason: ReportReason,
+        pub comment: Option<&'a str>
+    }
+
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "answers"]
+    pub struct NewAnswer {
+        pub question_id: i32,
+        pub chosen: Option<i16>,
+        pub time_taken_ms: i32,
+        pub joker_used: bool,
+        pub game_id: Option<i32>,
+        pub correct: bool
+    }
+
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "games"]
+    pub struct NewGame<'a> {
+        pub player: &'a str,
+        pub player_id: Option<i32>,
+        pub visitor: Option<&'a str>,
+        pub seed: i64
+    }
+
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "duels"]
+    pub struct NewDuel<'a> {
+        pub code: &'a str,
+        pub seed: i64,
+        pub questions: Vec<QuestionId>,
+        pub challenger_game: i32
+    }
+
+    #[derive(Insertable, AsChangeset, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "review_schedule"]
+    pub struct NewReviewEntry {
+        pub player_id: i32,
+        pub question_id: i32,
+        pub leitner_box: i16,
+        pub due_at: SystemTime,
+        pub reviewed_at: SystemTime
+    }
+
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "players"]
+    pub struct NewPlayer<'a> {
+        pub name: &'a str,
+        pub password: &'a str
     }
 
     #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
@@ -178,6 +467,15 @@ mod insert {
         pub question_id: i32,
     }
 
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "question_media"]
+    pub struct NewQuestionMedia<'a> {
+        pub question_id: i32,
+        pub answer: Option<i16>,
+        pub file_name: &'a str,
+        pub content_type: &'a str
+    }
+
     #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
     #[table_name = "categories"]
     pub struct NewCategory<'a> {
@@ -193,13 +491,51 @@ mod insert {
         pub played_on: SystemTime,
         #[column_name = "duration"]
         pub duration_secs: i64,
+        pub categories: Vec<CategoryId>,
+        pub player_id: Option<i32>,
+        pub daily: Option<NaiveDate>
+    }
+
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "team_scores"]
+    pub struct NewTeamScore<'a> {
+        pub team: &'a str,
+        pub members: &'a [String],
+        pub points: i32,
+        pub weighted_points: i32,
+        pub played_on: SystemTime,
+        #[column_name = "duration"]
+        pub duration_secs: i64,
         pub categories: Vec<CategoryId>
     }
 
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "daily_challenges"]
+    pub struct NewDailyChallenge {
+        pub day: NaiveDate,
+        pub questions: Vec<QuestionId>
+    }
+
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "daily_plays"]
+    pub struct NewDailyPlay<'a> {
+        pub day: NaiveDate,
+        pub challenger: &'a str
+    }
+
     #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
     #[table_name = "admins"]
     pub struct NewAdmin<'a> {
         pub name: &'a str,
         pub password: &'a str
     }
+
+    #[derive(Insertable, Debug, PartialEq, PartialOrd, Clone)]
+    #[table_name = "api_tokens"]
+    pub struct NewApiToken<'a> {
+        pub admin_id: i32,
+        pub name: &'a str,
+        pub secret: &'a str,
+        pub scopes: &'a [String]
+    }
 }
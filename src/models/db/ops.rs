@@ -1,17 +1,28 @@
 use {
     derive_more::Deref,
     serde::{Serialize, Deserialize},
+    serde_repr::{Serialize_repr, Deserialize_repr},
+    chrono::NaiveDate,
     std::{
         io::Write,
+        time::{Duration, SystemTime},
         ops::{Add, Div, Mul}
     },
-    crate::models::db::{
-        schema,
-        models::*,
-        conn::Connection
+    crate::models::{
+        i18n::Locale,
+        similarity::{self, Duplicates},
+        db::{
+            schema,
+            models::*,
+            conn::Connection
+        }
     },
     rocket::{
-        http::RawStr,
+        http::{
+            RawStr,
+            impl_from_uri_param_identity,
+            uri::{Query, UriDisplay, Formatter}
+        },
         request::{
             FromParam,
             FromFormValue
@@ -26,8 +37,9 @@ use {
         prelude::*,
         pg::Pg,
         backend::Backend,
-        sql_types::Integer,
+        sql_types::{Integer, SmallInt},
         result::QueryResult,
+        deserialize::{self, FromSql},
         expression::dsl::{any, all},
         serialize::{ToSql, Output},
         query_builder::{AsChangeset, QueryFragment}
@@ -61,6 +73,30 @@ macro_rules! impl_from_form_value_for_id {
     };
 }
 
+macro_rules! impl_from_param_for_id {
+    ($item:ident) => {
+        impl <'r> FromParam<'r> for $item {
+            type Error = <i32 as FromParam<'r>>::Error;
+
+            fn from_param(param: &'r RawStr) -> Result<Self, Self::Error> {
+                i32::from_param(param).map($item)
+            }
+        }
+    };
+}
+
+macro_rules! impl_uri_display_for_id {
+    ($item:ident) => {
+        impl UriDisplay<Query> for $item {
+            fn fmt(&self, f: &mut Formatter<Query>) -> std::fmt::Result {
+                f.write_value(self.0.to_string())
+            }
+        }
+
+        impl_from_uri_param_identity!([Query] $item);
+    };
+}
+
 #[derive(AsExpression, Serialize, Deserialize, Deref, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
 #[sql_type = "diesel::sql_types::Integer"]
 pub struct CategoryId(pub(super) i32);
@@ -94,12 +130,231 @@ pub struct QuestionId(pub(super) i32);
 
 impl_to_sql_for_id!(QuestionId);
 impl_from_form_value_for_id!(QuestionId);
+impl_from_param_for_id!(QuestionId);
+impl_uri_display_for_id!(QuestionId);
+
+#[derive(AsExpression, FromSqlRow, Serialize_repr, Deserialize_repr, Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::SmallInt"]
+#[repr(i16)]
+pub enum QuestionStatus {
+    Draft = 0,
+    InReview = 1,
+    Published = 2,
+    Retired = 3
+}
+
+impl Default for QuestionStatus {
+    fn default() -> Self {
+        QuestionStatus::Draft
+    }
+}
+
+impl ToSql<SmallInt, Pg> for QuestionStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> diesel::serialize::Result {
+        (*self as i16).to_sql(out)
+    }
+}
+
+impl QuestionStatus {
+    fn from_i16(int: i16) -> Option<QuestionStatus> {
+        match int {
+            0 => Some(QuestionStatus::Draft),
+            1 => Some(QuestionStatus::InReview),
+            2 => Some(QuestionStatus::Published),
+            3 => Some(QuestionStatus::Retired),
+            _ => None
+        }
+    }
+}
+
+impl FromSql<SmallInt, Pg> for QuestionStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let int = i16::from_sql(bytes)?;
+        QuestionStatus::from_i16(int)
+            .ok_or_else(|| format!("invalid question status {}", int).into())
+    }
+}
+
+impl<'v> FromFormValue<'v> for QuestionStatus {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        i16::from_form_value(form_value)
+            .ok()
+            .and_then(QuestionStatus::from_i16)
+            .ok_or(form_value)
+    }
+}
+
+#[derive(Debug)]
+pub enum StatusError {
+    Query(diesel::result::Error),
+    NotFound,
+    InvalidTransition,
+    SameReviewer
+}
+
+impl From<diesel::result::Error> for StatusError {
+    fn from(e: diesel::result::Error) -> Self {
+        StatusError::Query(e)
+    }
+}
+
+#[derive(Debug)]
+pub enum PurgeError {
+    Query(diesel::result::Error),
+    Answered
+}
+
+impl From<diesel::result::Error> for PurgeError {
+    fn from(e: diesel::result::Error) -> Self {
+        PurgeError::Query(e)
+    }
+}
+
+#[derive(AsExpression, FromSqlRow, Serialize_repr, Deserialize_repr, Debug, Eq, PartialEq, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::SmallInt"]
+#[repr(i16)]
+pub enum ReportReason {
+    WrongAnswer = 0,
+    Typo = 1,
+    Unclear = 2,
+    Offensive = 3,
+    Other = 4
+}
+
+impl ReportReason {
+    fn from_i16(int: i16) -> Option<ReportReason> {
+        match int {
+            0 => Some(ReportReason::WrongAnswer),
+            1 => Some(ReportReason::Typo),
+            2 => Some(ReportReason::Unclear),
+            3 => Some(ReportReason::Offensive),
+            4 => Some(ReportReason::Other),
+            _ => None
+        }
+    }
+}
+
+impl ToSql<SmallInt, Pg> for ReportReason {
+    fn to_sql<W: Write>(&self, out: &mut Output<W, Pg>) -> diesel::serialize::Result {
+        (*self as i16).to_sql(out)
+    }
+}
+
+impl FromSql<SmallInt, Pg> for ReportReason {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        let int = i16::from_sql(bytes)?;
+        ReportReason::from_i16(int)
+            .ok_or_else(|| format!("invalid report reason {}", int).into())
+    }
+}
+
+impl<'v> FromFormValue<'v> for ReportReason {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        i16::from_form_value(form_value)
+            .ok()
+            .and_then(ReportReason::from_i16)
+            .ok_or(form_value)
+    }
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestionOrder {
+    Id,
+    CreatedAt,
+    CorrectRate,
+    Answers
+}
+
+impl Default for QuestionOrder {
+    fn default() -> Self {
+        QuestionOrder::Id
+    }
+}
+
+impl<'v> FromFormValue<'v> for QuestionOrder {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "id" => Ok(QuestionOrder::Id),
+            "created_at" => Ok(QuestionOrder::CreatedAt),
+            "correct_rate" => Ok(QuestionOrder::CorrectRate),
+            "answers" => Ok(QuestionOrder::Answers),
+            _ => Err(form_value)
+        }
+    }
+}
+
+/// Which questions the admin question list shows, trashed ones never are.
+#[derive(Debug, Clone, Default)]
+pub struct QuestionFilter {
+    /// Words that all have to appear in the question or its answers.
+    pub search: Option<String>,
+    pub category: Option<CategoryId>,
+    pub status: Option<QuestionStatus>,
+    /// Inclusive bounds of the correct rate in percent.
+    pub min_correct_rate: Option<i32>,
+    pub max_correct_rate: Option<i32>
+}
 
-impl <'r> FromParam<'r> for QuestionId {
-    type Error = <i32 as FromParam<'r>>::Error;
+type BoxedQuestions<'a> = schema::questions::BoxedQuery<'a, Pg>;
+
+/// The correct answers in percent, questions nobody answered yet have 0.
+const CORRECT_RATE: &str = "COALESCE((\
+    SELECT num_correct * 100 / NULLIF(num_correct + num_incorrect, 0) \
+    FROM question_stats WHERE question_stats.question_id = questions.id\
+), 0)";
+
+const ANSWERS: &str = "COALESCE((\
+    SELECT num_correct + num_incorrect \
+    FROM question_stats WHERE question_stats.question_id = questions.id\
+), 0)";
+
+/// The 'simple' configuration doesn't stem, so it works for every locale alike.
+/// The `questions_search` index is on exactly this `to_tsvector` expression, keep them the same.
+const SEARCH_MATCHES: &str = "to_tsvector('simple', \
+    question_search_text(questions.string, questions.correct, questions.incorrect)\
+) @@ plainto_tsquery('simple', ";
+
+impl QuestionFilter {
+    fn apply<'a>(&self) -> BoxedQuestions<'a> {
+        use {
+            schema::questions::dsl::*,
+            diesel::{dsl::sql, sql_types::{Bool, Text}}
+        };
+
+        let mut query = questions
+            .filter(deleted_at.is_null())
+            .into_boxed();
+        if let Some(words) = &self.search {
+            query = query.filter(sql::<Bool>(SEARCH_MATCHES)
+                .bind::<Text, _>(words.clone())
+                .sql(")")
+            );
+        }
+        if let Some(cat) = self.category {
+            query = query.filter(category_id.eq(cat));
+        }
+        if let Some(stat) = self.status {
+            query = query.filter(status.eq(stat));
+        }
+        if let Some(min) = self.min_correct_rate {
+            query = query.filter(sql::<Bool>(&format!("{} >= ", CORRECT_RATE))
+                .bind::<Integer, _>(min)
+            );
+        }
+        if let Some(max) = self.max_correct_rate {
+            query = query.filter(sql::<Bool>(&format!("{} <= ", CORRECT_RATE))
+                .bind::<Integer, _>(max)
+            );
+        }
 
-    fn from_param(param: &'r RawStr) -> Result<Self, Self::Error> {
-        i32::from_param(param).map(QuestionId)
+        query
     }
 }
 
@@ -114,73 +369,524 @@ impl Question {
         CategoryId(self.category_id)
     }
 
-    pub fn insert(new: &NewQuestion, conn: &Connection) -> QueryResult<Question> {
+    pub fn insert(new: &NewQuestion, editor: &str, conn: &Connection) -> QueryResult<Question> {
         use schema::questions::dsl::*;
 
-        let res: Question = insert_into(questions)
-            .values(new)
-            .get_result(conn)?;
+        conn.transaction(|| {
+            let res: Question = insert_into(questions)
+                .values(new)
+                .get_result(conn)?;
 
-        QuestionStats::insert(
-            &NewQuestionStats { question_id: res.id },
-            conn
-        )?;
+            QuestionStats::insert(
+                &NewQuestionStats { question_id: res.id },
+                conn
+            )?;
+
+            QuestionRevision::insert(
+                &NewQuestionRevision::of(&res, editor),
+                conn
+            )?;
 
-        Ok(res)
+            Ok(res)
+        })
     }
 
     pub fn is_of_category(&self, cat: &Category) -> bool {
         self.category_id == cat.id
     }
 
-    pub(in crate::models) fn load_set(categories: &[Category], answered: &[QuestionId], conn: &Connection) -> QueryResult<Vec<Question>> {
+    /// The index media and recorded answers refer to,
+    /// with 0 being the correct answer and 1 to 3 the incorrect ones.
+    pub fn answer_index(&self, answer: &str) -> Option<i16> {
+        if answer == self.correct {
+            Some(0)
+        } else {
+            self.incorrect
+                .iter()
+                .position(|inc| inc == answer)
+                .map(|pos| pos as i16 + 1)
+        }
+    }
+
+    /// All questions that can currently be played, ordered by id.
+    pub fn published_ids(of: &[CategoryId], conn: &Connection) -> QueryResult<Vec<QuestionId>> {
+        use schema::questions::dsl::*;
+
+        questions
+            .filter(category_id.eq(any(of)))
+            .filter(status.eq(QuestionStatus::Published))
+            .filter(deleted_at.is_null())
+            .select(id)
+            .order(id)
+            .load::<i32>(conn)
+            .map(|ids| ids
+                .into_iter()
+                .map(QuestionId)
+                .collect()
+            )
+    }
+
+    /// Loads the questions in the order of the ids, translated where possible.
+    pub fn load_translated(ids: &[QuestionId], into: Locale, conn: &Connection) -> QueryResult<Vec<Question>> {
+        use schema::questions::dsl::*;
+
+        let mut loaded = questions
+            .filter(id.eq(any(ids)))
+            .load::<Question>(conn)?;
+        loaded.sort_by_key(|q| ids
+            .iter()
+            .position(|&qid| qid == q.id())
+        );
+        Question::translate(&mut loaded, into, conn)?;
+
+        Ok(loaded)
+    }
+
+    pub(in crate::models) fn load_set(
+        categories: &[Category],
+        selected_tags: &[String],
+        answered: &[QuestionId],
+        seen: &[QuestionId],
+        in_locale: Locale,
+        conn: &Connection
+    ) -> QueryResult<Vec<Question>> {
         use schema::questions::dsl::*;
 
-        let per_category = (Self::PER_SET / categories.len()) as i64;
+        let translated = || {
+            use schema::question_translations::dsl::*;
+
+            question_translations
+                .filter(locale.eq(in_locale.code()))
+                .select(question_id)
+                .into_boxed()
+        };
+
+        // the selected tags are treated as one additional source
+        // of questions next to the categories
+        let sources = categories.len() + !selected_tags.is_empty() as usize;
+        let per_source = (Self::PER_SET / sources.max(1)) as i64;
         let mut result = Vec::with_capacity(Self::PER_SET);
 
         // this might not always give us the full PER_SET, since
         // a category might not have enough unanswered questions
-        // remaining, but it's whatever
+        // remaining, but it's whatever. questions the player has seen
+        // in earlier games only fill up what's left (false sorts first),
+        // the id keeps the set the same for the same seed
         categories
             .iter()
             .map(|cat| Question::belonging_to(cat)
+                .filter(status.eq(QuestionStatus::Published))
+                .filter(deleted_at.is_null())
                 .filter(id.ne(all(answered)))
-                .limit(per_category)
+                .filter(locale.eq(in_locale.code()).or(id.eq_any(translated())))
+                .order(id.eq(any(seen)))
+                .then_order_by(id)
+                .limit(per_source)
                 .load(conn)
                 .map(|qs| result.extend(qs))
             ).collect::<Result<_, _>>()?;
 
+        if !selected_tags.is_empty() {
+            let category_ids = categories
+                .iter()
+                .map(|cat| cat.id)
+                .collect::<Vec<_>>();
+
+            // questions from the selected categories have already been loaded above
+            questions
+                .filter(tags.overlaps_with(selected_tags))
+                .filter(category_id.ne(all(category_ids)))
+                .filter(status.eq(QuestionStatus::Published))
+                .filter(deleted_at.is_null())
+                .filter(id.ne(all(answered)))
+                .filter(locale.eq(in_locale.code()).or(id.eq_any(translated())))
+                .order(id.eq(any(seen)))
+                .then_order_by(id)
+                .limit(per_source)
+                .load(conn)
+                .map(|qs| result.extend(qs))?;
+        }
+
+        Question::translate(&mut result, in_locale, conn)?;
+
         Ok(result)
     }
 
+    fn translate(qs: &mut [Question], into: Locale, conn: &Connection) -> QueryResult<()> {
+        use schema::question_translations::dsl::*;
+
+        let ids = qs
+            .iter()
+            .filter(|q| q.locale != into.code())
+            .map(|q| q.id)
+            .collect::<Vec<_>>();
+
+        let translations = question_translations
+            .filter(question_id.eq(any(ids)))
+            .filter(locale.eq(into.code()))
+            .load::<QuestionTranslation>(conn)?;
+
+        for tr in translations {
+            if let Some(q) = qs.iter_mut().find(|q| q.id == tr.question_id) {
+                q.string = tr.string;
+                q.correct = tr.correct;
+                q.incorrect = tr.incorrect;
+                q.locale = tr.locale;
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn load_all(conn: &Connection) -> QueryResult<Vec<Question>> {
         use schema::questions::dsl::*;
 
-        questions.load(conn)
+        questions
+            .filter(deleted_at.is_null())
+            .load(conn)
+    }
+
+    /// Loads one page of the questions matching the filter,
+    /// equal ones are ordered by id.
+    pub fn search(
+        filter: &QuestionFilter,
+        order: QuestionOrder,
+        descending: bool,
+        offset: i64,
+        limit: i64,
+        conn: &Connection
+    ) -> QueryResult<Vec<Question>> {
+        use {
+            schema::questions::dsl::*,
+            diesel::dsl::sql
+        };
+
+        let query = filter.apply();
+        match (order, descending) {
+            (QuestionOrder::Id, false) => query.order(id),
+            (QuestionOrder::Id, true) => query.order(id.desc()),
+            (QuestionOrder::CreatedAt, false) => query.order((created_at, id)),
+            (QuestionOrder::CreatedAt, true) => query.order((created_at.desc(), id)),
+            (QuestionOrder::CorrectRate, false) => query.order((sql::<Integer>(CORRECT_RATE), id)),
+            (QuestionOrder::CorrectRate, true) => query.order((sql::<Integer>(CORRECT_RATE).desc(), id)),
+            (QuestionOrder::Answers, false) => query.order((sql::<Integer>(ANSWERS), id)),
+            (QuestionOrder::Answers, true) => query.order((sql::<Integer>(ANSWERS).desc(), id))
+        }
+        .offset(offset)
+        .limit(limit)
+        .load(conn)
     }
 
-    pub fn delete(qid: QuestionId, conn: &Connection) -> QueryResult<()> {
+    pub fn count(filter: &QuestionFilter, conn: &Connection) -> QueryResult<i64> {
+        filter.apply()
+            .count()
+            .get_result(conn)
+    }
+
+    pub fn load_tagged(tag: &str, conn: &Connection) -> QueryResult<Vec<Question>> {
         use schema::questions::dsl::*;
 
-        {
-            use schema::question_stats::dsl::*;
+        questions
+            .filter(tags.contains(vec![tag]))
+            .filter(deleted_at.is_null())
+            .load(conn)
+    }
 
-            delete(question_stats.filter(question_id.eq(qid)))
-                .execute(conn)?;
-        }
+    /// Loads only id and text of every question that's not in the trash.
+    pub fn load_texts(conn: &Connection) -> QueryResult<Vec<(QuestionId, String)>> {
+        use schema::questions::dsl::*;
+
+        questions
+            .filter(deleted_at.is_null())
+            .select((id, string))
+            .load::<(i32, String)>(conn)
+            .map(|texts| texts
+                .into_iter()
+                .map(|(qid, text)| (QuestionId(qid), text))
+                .collect()
+            )
+    }
+
+    /// Compares a question text against the rest of the bank. A question
+    /// that's being edited is left out, it would always match itself.
+    pub fn duplicates(text: &str, except: Option<QuestionId>, conn: &Connection) -> QueryResult<Duplicates> {
+        Question::load_texts(conn)
+            .map(|mut existing| {
+                existing.retain(|(qid, _)| Some(*qid) != except);
+                similarity::check(text, &existing)
+            })
+    }
+
+    pub fn all_tags(conn: &Connection) -> QueryResult<Vec<String>> {
+        use {
+            schema::questions::dsl::*,
+            diesel::{dsl::sql, sql_types::Text}
+        };
+
+        let mut all_tags = questions
+            .filter(deleted_at.is_null())
+            .select(sql::<Text>("unnest(tags)"))
+            .distinct()
+            .load::<String>(conn)?;
+        all_tags.sort_unstable();
+
+        Ok(all_tags)
+    }
+
+    pub fn load(qid: QuestionId, conn: &Connection) -> QueryResult<Option<Question>> {
+        use schema::questions::dsl::*;
 
-        delete(questions.filter(id.eq(qid)))
+        questions
+            .filter(id.eq(qid))
+            .first(conn)
+            .optional()
+    }
+
+    /// Loads a question only if players can currently be asked it.
+    pub fn load_published(qid: QuestionId, conn: &Connection) -> QueryResult<Option<Question>> {
+        use schema::questions::dsl::*;
+
+        questions
+            .filter(id.eq(qid))
+            .filter(status.eq(QuestionStatus::Published))
+            .filter(deleted_at.is_null())
+            .first(conn)
+            .optional()
+    }
+
+    /// Moves a question to the trash, where it's kept
+    /// along with its stats and history until it's purged.
+    pub fn delete(qid: QuestionId, conn: &Connection) -> QueryResult<()> {
+        use schema::questions::dsl::*;
+
+        update(questions
+            .filter(id.eq(qid))
+            .filter(deleted_at.is_null())
+        ).set(deleted_at.eq(SystemTime::now()))
             .execute(conn)
             .map(drop)
     }
 
-    pub fn update(qid: QuestionId, new: NewQuestion, conn: &Connection) -> QueryResult<Question> {
+    /// Returns false if there is no such question in the trash.
+    pub fn restore(qid: QuestionId, conn: &Connection) -> QueryResult<bool> {
         use schema::questions::dsl::*;
 
-        update(questions.filter(id.eq(qid)))
-            .set(new)
+        update(questions
+            .filter(id.eq(qid))
+            .filter(deleted_at.is_not_null())
+        ).set(deleted_at.eq(None::<SystemTime>))
+            .execute(conn)
+            .map(|updated| updated > 0)
+    }
+
+    pub fn load_trashed(conn: &Connection) -> QueryResult<Vec<Question>> {
+        use schema::questions::dsl::*;
+
+        questions
+            .filter(deleted_at.is_not_null())
+            .order(deleted_at.desc())
+            .load(conn)
+    }
+
+    /// Irrevocably deletes a question and everything that belongs to it.
+    /// Answers are history that analytics rely on, so answered questions can't be purged.
+    pub fn purge(qid: QuestionId, conn: &Connection) -> Result<(), PurgeError> {
+        use schema::questions::dsl::*;
+
+        conn.transaction(|| {
+            let answered = {
+                use schema::answers::dsl::*;
+
+                answers
+                    .filter(question_id.eq(qid))
+                    .count()
+                    .get_result::<i64>(conn)?
+            };
+            if answered > 0 {
+                return Err(PurgeError::Answered)
+            }
+
+            {
+                use schema::question_stats::dsl::*;
+
+                delete(question_stats.filter(question_id.eq(qid)))
+                    .execute(conn)?;
+            }
+
+            {
+                use schema::question_media::dsl::*;
+
+                delete(question_media.filter(question_id.eq(qid)))
+                    .execute(conn)?;
+            }
+
+            {
+                use schema::question_translations::dsl::*;
+
+                delete(question_translations.filter(question_id.eq(qid)))
+                    .execute(conn)?;
+            }
+
+            {
+                use schema::question_revisions::dsl::*;
+
+                delete(question_revisions.filter(question_id.eq(qid)))
+                    .execute(conn)?;
+            }
+
+            {
+                use schema::question_reports::dsl::*;
+
+                delete(question_reports.filter(question_id.eq(qid)))
+                    .execute(conn)?;
+            }
+
+            {
+                use schema::review_schedule::dsl::*;
+
+                delete(review_schedule.filter(question_id.eq(qid)))
+                    .execute(conn)?;
+            }
+
+            delete(questions.filter(id.eq(qid)))
+                .execute(conn)?;
+            Ok(())
+        })
+    }
+
+    /// Changing a published question takes it out of the game
+    /// until it has been reviewed again.
+    pub fn update(qid: QuestionId, new: NewQuestion, editor: &str, conn: &Connection) -> QueryResult<Question> {
+        use schema::questions::dsl::*;
+
+        conn.transaction(|| {
+            let old: Question = questions
+                .filter(id.eq(qid))
+                .for_update()
+                .first(conn)?;
+            let changed = old.category_id != new.category_id
+                || old.string != new.string
+                || old.correct != new.correct
+                || old.incorrect[..] != new.incorrect[..]
+                || old.tags[..] != new.tags[..]
+                || old.locale != new.locale;
+
+            let res: Question = if changed && old.status == QuestionStatus::Published {
+                update(questions.filter(id.eq(qid)))
+                    .set((
+                        new,
+                        status.eq(QuestionStatus::InReview),
+                        submitted_by.eq(editor),
+                        approved_by.eq(None::<String>)
+                    ))
+                    .get_result(conn)?
+            } else {
+                update(questions.filter(id.eq(qid)))
+                    .set(new)
+                    .get_result(conn)?
+            };
+
+            QuestionRevision::insert(
+                &NewQuestionRevision::of(&res, editor),
+                conn
+            )?;
+
+            Ok(res)
+        })
+    }
+
+    pub fn submit_for_review(qid: QuestionId, editor: &str, conn: &Connection) -> Result<Question, StatusError> {
+        use schema::questions::dsl::*;
+
+        Question::transition(qid, &[QuestionStatus::Draft], conn, |_| Ok(()), |q| update(q)
+            .set((status.eq(QuestionStatus::InReview), submitted_by.eq(editor)))
+            .get_result(conn)
+        )
+    }
+
+    /// Publishes a question in review, which has to
+    /// be done by someone other than its submitter.
+    pub fn approve(qid: QuestionId, approver: &str, conn: &Connection) -> Result<Question, StatusError> {
+        use schema::questions::dsl::*;
+
+        let check_reviewer = |q: &Question| match &q.submitted_by {
+            Some(submitter) if submitter == approver => Err(StatusError::SameReviewer),
+            _ => Ok(())
+        };
+
+        Question::transition(qid, &[QuestionStatus::InReview], conn, check_reviewer, |q| update(q)
+            .set((status.eq(QuestionStatus::Published), approved_by.eq(approver)))
+            .get_result(conn)
+        )
+    }
+
+    pub fn reject(qid: QuestionId, conn: &Connection) -> Result<Question, StatusError> {
+        use schema::questions::dsl::*;
+
+        Question::transition(qid, &[QuestionStatus::InReview], conn, |_| Ok(()), |q| update(q)
+            .set(status.eq(QuestionStatus::Draft))
+            .get_result(conn)
+        )
+    }
+
+    pub fn retire(qid: QuestionId, conn: &Connection) -> Result<Question, StatusError> {
+        use schema::questions::dsl::*;
+
+        let retirable = [QuestionStatus::Draft, QuestionStatus::InReview, QuestionStatus::Published];
+        Question::transition(qid, &retirable, conn, |_| Ok(()), |q| update(q)
+            .set(status.eq(QuestionStatus::Retired))
+            .get_result(conn)
+        )
+    }
+
+    /// Brings a retired question back as a draft, to be reviewed again before it's asked.
+    pub fn reopen(qid: QuestionId, conn: &Connection) -> Result<Question, StatusError> {
+        use schema::questions::dsl::*;
+
+        Question::transition(qid, &[QuestionStatus::Retired], conn, |_| Ok(()), |q| update(q)
+            .set((status.eq(QuestionStatus::Draft), submitted_by.eq(None::<String>), approved_by.eq(None::<String>)))
             .get_result(conn)
+        )
+    }
+
+    fn transition<C, U>(
+        qid: QuestionId,
+        from: &[QuestionStatus],
+        conn: &Connection,
+        check: C,
+        apply: U
+    ) -> Result<Question, StatusError>
+    where
+        C: FnOnce(&Question) -> Result<(), StatusError>,
+        U: FnOnce(&Question) -> QueryResult<Question>
+    {
+        use schema::questions::dsl::*;
+
+        conn.transaction(|| {
+            let question: Question = questions
+                .filter(id.eq(qid))
+                .for_update()
+                .first(conn)
+                .optional()?
+                .ok_or(StatusError::NotFound)?;
+
+            if !from.contains(&question.status) {
+                return Err(StatusError::InvalidTransition)
+            }
+
+            check(&question)?;
+            Ok(apply(&question)?)
+        })
+    }
+
+    pub fn media(&self, conn: &Connection) -> QueryResult<Vec<QuestionMedia>> {
+        QuestionMedia::belonging_to(self)
+            .load(conn)
+    }
+
+    pub fn translations(&self, conn: &Connection) -> QueryResult<Vec<QuestionTranslation>> {
+        QuestionTranslation::belonging_to(self)
+            .load(conn)
     }
 
     pub fn stats(&self) -> Stats {
@@ -218,6 +924,31 @@ impl <'a> Stats<'a> {
         )
     }
 
+    /// Counts the recorded answers instead of reading the stored totals.
+    /// Both are the same for questions that were added after answers started being recorded.
+    pub fn from_answers(&self, conn: &Connection) -> QueryResult<QuestionStats> {
+        use {
+            schema::answers::dsl::*,
+            diesel::dsl::count_star
+        };
+
+        let counts = Answer::belonging_to(self.question)
+            .group_by(correct)
+            .select((correct, count_star()))
+            .load::<(bool, i64)>(conn)?;
+
+        let count_of = |is_correct| counts
+            .iter()
+            .find(|(cor, _)| *cor == is_correct)
+            .map_or(0, |(_, count)| *count as i32);
+
+        Ok(QuestionStats {
+            num_correct: count_of(true),
+            num_incorrect: count_of(false),
+            ..self.load(conn)?
+        })
+    }
+
     fn update_stat<V>(&self, expr: V, conn: &Connection) -> QueryResult<()>
     where
         V: AsChangeset<Target = schema::question_stats::table>,
@@ -230,7 +961,240 @@ impl <'a> Stats<'a> {
     }
 }
 
-impl QuestionStats {
+impl QuestionReport {
+    pub fn question_id(&self) -> QuestionId {
+        QuestionId(self.question_id)
+    }
+
+    /// Loads all unresolved reports grouped by the question they're about,
+    /// with the most reported questions first.
+    pub fn open(conn: &Connection) -> QueryResult<Vec<(Question, Vec<QuestionReport>)>> {
+        use schema::question_reports::dsl::*;
+
+        let reports = question_reports
+            .filter(resolved_at.is_null())
+            .order(reported_at)
+            .load::<QuestionReport>(conn)?;
+
+        let ids = reports
+            .iter()
+            .map(|report| report.question_id)
+            .collect::<Vec<_>>();
+
+        let reported = schema::questions::table
+            .filter(schema::questions::id.eq(any(ids)))
+            .load::<Question>(conn)?;
+
+        let grouped = reports.grouped_by(&reported);
+        let mut open = reported
+            .into_iter()
+            .zip(grouped)
+            .collect::<Vec<_>>();
+        open.sort_by_key(|(_, reports)| std::cmp::Reverse(reports.len()));
+
+        Ok(open)
+    }
+
+    /// Marks all open reports about a question as resolved.
+    pub fn resolve(qid: QuestionId, admin: &str, conn: &Connection) -> QueryResult<usize> {
+        use schema::question_reports::dsl::*;
+
+        update(question_reports
+            .filter(question_id.eq(qid))
+            .filter(resolved_at.is_null())
+        ).set((
+            resolved_at.eq(SystemTime::now()),
+            resolved_by.eq(admin)
+        )).execute(conn)
+    }
+}
+
+#[derive(AsExpression, Serialize, Deserialize, Deref, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::Integer"]
+pub struct GameId(pub(super) i32);
+
+impl_to_sql_for_id!(GameId);
+
+impl Game {
+    pub fn id(&self) -> GameId {
+        GameId(self.id)
+    }
+
+    pub fn with_id(gid: GameId, conn: &Connection) -> QueryResult<Option<Game>> {
+        use schema::games::dsl::*;
+
+        games
+            .filter(id.eq(gid))
+            .first(conn)
+            .optional()
+    }
+}
+
+#[derive(AsExpression, Serialize, Deserialize, Deref, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::Integer"]
+pub struct DuelId(pub(super) i32);
+
+impl_to_sql_for_id!(DuelId);
+
+impl Duel {
+    /// An opponent who hasn't finished after this long gave up, someone else may take the challenge.
+    pub const ABANDONED_AFTER: Duration = Duration::from_secs(6 * 60 * 60);
+
+    pub fn id(&self) -> DuelId {
+        DuelId(self.id)
+    }
+
+    /// Someone took the challenge and either finished it or may still be playing.
+    pub fn is_taken(&self, now: SystemTime) -> bool {
+        self.finished_at.is_some() || self.accepted_at.map_or(false, |at| at > Self::abandoned_before(now))
+    }
+
+    fn abandoned_before(now: SystemTime) -> SystemTime {
+        now
+            .checked_sub(Self::ABANDONED_AFTER)
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+    }
+
+    /// The questions in the order the challenger got them.
+    pub fn questions(&self) -> Vec<QuestionId> {
+        self.questions
+            .iter()
+            .copied()
+            .map(QuestionId)
+            .collect()
+    }
+
+    pub fn challenger_game(&self) -> GameId {
+        GameId(self.challenger_game)
+    }
+
+    pub fn opponent_game(&self) -> Option<GameId> {
+        self.opponent_game.map(GameId)
+    }
+
+    pub fn with_code(with: &str, conn: &Connection) -> QueryResult<Option<Duel>> {
+        use schema::duels::dsl::*;
+
+        duels
+            .filter(code.eq(with))
+            .first(conn)
+            .optional()
+    }
+
+    /// Makes the game the one answering the challenge, returns false
+    /// if someone else took it and hasn't given up on it.
+    pub fn accept(duel: DuelId, game: GameId, now: SystemTime, conn: &Connection) -> QueryResult<bool> {
+        use schema::duels::dsl::*;
+
+        update(duels
+            .filter(id.eq(duel))
+            .filter(finished_at.is_null())
+            .filter(accepted_at.is_null().or(accepted_at.le(Self::abandoned_before(now))))
+        ).set((opponent_game.eq(game), accepted_at.eq(now)))
+            .execute(conn)
+            .map(|updated| updated > 0)
+    }
+
+    /// None if the game isn't the one answering the challenge anymore,
+    /// because someone else took it after the game was abandoned.
+    pub fn finish(duel: DuelId, game: GameId, conn: &Connection) -> QueryResult<Option<Duel>> {
+        use schema::duels::dsl::*;
+
+        update(duels
+            .filter(id.eq(duel))
+            .filter(opponent_game.eq(game))
+            .filter(finished_at.is_null())
+        ).set(finished_at.eq(SystemTime::now()))
+            .get_result(conn)
+            .optional()
+    }
+}
+
+/// Number of times a question got answered in the same way.
+#[derive(Queryable, Debug, Clone)]
+pub struct AnswerTally {
+    question_id: i32,
+    pub chosen: Option<i16>,
+    pub joker_used: bool,
+    pub count: i64,
+    pub total_time_ms: Option<i64>
+}
+
+impl AnswerTally {
+    pub fn question_id(&self) -> QuestionId {
+        QuestionId(self.question_id)
+    }
+}
+
+/// Someone who may have played before, either with an account
+/// or recognised by the visitor cookie.
+#[derive(Debug, Clone, Copy)]
+pub enum Returning<'a> {
+    Player(PlayerId),
+    Visitor(&'a str)
+}
+
+impl Answer {
+    // answering incorrectly this long ago, the question can be asked again
+    const FORGOTTEN_AFTER: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+    /// The questions a returning player should preferably not get again.
+    pub fn seen_by(returning: Returning, conn: &Connection) -> QueryResult<Vec<QuestionId>> {
+        use schema::{answers, games};
+
+        let query = answers::table
+            .inner_join(games::table)
+            .select((answers::question_id, answers::correct, answers::answered_at))
+            .order(answers::answered_at.desc())
+            .into_boxed();
+
+        let history = match returning {
+            Returning::Player(player) => query.filter(games::player_id.eq(player)),
+            Returning::Visitor(visitor) => query.filter(games::visitor.eq(visitor))
+        }.load::<(i32, bool, SystemTime)>(conn)?;
+
+        let forgotten = SystemTime::now()
+            .checked_sub(Self::FORGOTTEN_AFTER)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        // only the latest answer to every question counts
+        let mut latest = std::collections::HashSet::new();
+        Ok(history
+            .into_iter()
+            .filter(|&(question, _, _)| latest.insert(question))
+            .filter(|&(_, correct, answered_at)| correct || answered_at > forgotten)
+            .map(|(question, _, _)| QuestionId(question))
+            .collect())
+    }
+
+    pub fn question_id(&self) -> QuestionId {
+        QuestionId(self.question_id)
+    }
+
+    /// All answers given in a game, in order.
+    pub fn of_game(game: GameId, conn: &Connection) -> QueryResult<Vec<Answer>> {
+        use schema::answers::dsl::*;
+
+        answers
+            .filter(game_id.eq(game))
+            .order(id)
+            .load(conn)
+    }
+
+    pub fn tallies(conn: &Connection) -> QueryResult<Vec<AnswerTally>> {
+        use {
+            schema::answers::dsl::*,
+            diesel::dsl::{count_star, sum}
+        };
+
+        answers
+            .group_by((question_id, chosen, joker_used))
+            .select((question_id, chosen, joker_used, count_star(), sum(time_taken_ms)))
+            .load(conn)
+    }
+}
+
+impl QuestionStats {
     pub fn correct_ratio(&self) -> u8 {
         (self.num_correct as f32)
             .div(self.num_incorrect
@@ -242,36 +1206,335 @@ impl QuestionStats {
     }
 }
 
+#[derive(AsExpression, Serialize, Deserialize, Deref, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::Integer"]
+pub struct MediaId(pub(super) i32);
+
+impl_to_sql_for_id!(MediaId);
+impl_from_param_for_id!(MediaId);
+
+impl QuestionMedia {
+    pub fn id(&self) -> MediaId {
+        MediaId(self.id)
+    }
+
+    pub fn question_id(&self) -> QuestionId {
+        QuestionId(self.question_id)
+    }
+
+    pub fn with_id(mid: MediaId, conn: &Connection) -> QueryResult<Option<QuestionMedia>> {
+        use schema::question_media::dsl::*;
+
+        question_media
+            .filter(id.eq(mid))
+            .first(conn)
+            .optional()
+    }
+
+    pub fn of_question(qid: QuestionId, conn: &Connection) -> QueryResult<Vec<QuestionMedia>> {
+        use schema::question_media::dsl::*;
+
+        question_media
+            .filter(question_id.eq(qid))
+            .order(id)
+            .load(conn)
+    }
+
+    pub fn delete(mid: MediaId, conn: &Connection) -> QueryResult<Option<QuestionMedia>> {
+        use schema::question_media::dsl::*;
+
+        delete(question_media.filter(id.eq(mid)))
+            .get_result(conn)
+            .optional()
+    }
+}
+
+impl NewQuestionTranslation {
+    pub fn question_id(&self) -> QuestionId {
+        QuestionId(self.question_id)
+    }
+}
+
+impl QuestionTranslation {
+    pub fn of_question(qid: QuestionId, conn: &Connection) -> QueryResult<Vec<QuestionTranslation>> {
+        use schema::question_translations::dsl::*;
+
+        question_translations
+            .filter(question_id.eq(qid))
+            .order(locale)
+            .load(conn)
+    }
+
+    pub fn upsert(new: &NewQuestionTranslation, conn: &Connection) -> QueryResult<QuestionTranslation> {
+        use schema::question_translations::dsl::*;
+
+        insert_into(question_translations)
+            .values(new)
+            .on_conflict((question_id, locale))
+            .do_update()
+            .set(new)
+            .get_result(conn)
+    }
+
+    pub fn delete(qid: QuestionId, in_locale: Locale, conn: &Connection) -> QueryResult<()> {
+        use schema::question_translations::dsl::*;
+
+        delete(question_translations
+            .filter(question_id.eq(qid))
+            .filter(locale.eq(in_locale.code()))
+        ).execute(conn)
+            .map(drop)
+    }
+}
+
+#[derive(AsExpression, Serialize, Deserialize, Deref, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::Integer"]
+pub struct RevisionId(pub(super) i32);
+
+impl_to_sql_for_id!(RevisionId);
+impl_from_param_for_id!(RevisionId);
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct FieldChange {
+    pub field: &'static str,
+    pub old: String,
+    pub new: String
+}
+
+impl QuestionRevision {
+    pub fn id(&self) -> RevisionId {
+        RevisionId(self.id)
+    }
+
+    pub fn question_id(&self) -> QuestionId {
+        QuestionId(self.question_id)
+    }
+
+    pub fn of_question(qid: QuestionId, conn: &Connection) -> QueryResult<Vec<QuestionRevision>> {
+        use schema::question_revisions::dsl::*;
+
+        question_revisions
+            .filter(question_id.eq(qid))
+            .order(id.desc())
+            .load(conn)
+    }
+
+    pub fn with_id(rid: RevisionId, conn: &Connection) -> QueryResult<Option<QuestionRevision>> {
+        use schema::question_revisions::dsl::*;
+
+        question_revisions
+            .filter(id.eq(rid))
+            .first(conn)
+            .optional()
+    }
+
+    /// Makes the question look like it did in this revision again,
+    /// which is recorded as a new revision itself.
+    pub fn restore(&self, editor: &str, conn: &Connection) -> QueryResult<Question> {
+        Question::update(
+            self.question_id(),
+            NewQuestion {
+                category_id: self.category_id,
+                string: &self.string,
+                correct: &self.correct,
+                incorrect: &self.incorrect,
+                tags: &self.tags,
+                locale: &self.locale
+            },
+            editor,
+            conn
+        )
+    }
+
+    pub fn diff(&self, newer: &QuestionRevision) -> Vec<FieldChange> {
+        let fields: [(&'static str, fn(&QuestionRevision) -> String); 8] = [
+            ("category_id", |rev| rev.category_id.to_string()),
+            ("string", |rev| rev.string.clone()),
+            ("correct", |rev| rev.correct.clone()),
+            ("incorrect1", |rev| rev.incorrect[0].clone()),
+            ("incorrect2", |rev| rev.incorrect[1].clone()),
+            ("incorrect3", |rev| rev.incorrect[2].clone()),
+            ("tags", |rev| rev.tags.join(", ")),
+            ("locale", |rev| rev.locale.clone())
+        ];
+
+        fields
+            .iter()
+            .map(|(field, get)| FieldChange {
+                field: *field,
+                old: get(self),
+                new: get(newer)
+            })
+            .filter(|change| change.old != change.new)
+            .collect()
+    }
+}
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreOrder {
+    Points,
+    WeightedPoints
+}
+
+impl Default for ScoreOrder {
+    fn default() -> Self {
+        ScoreOrder::WeightedPoints
+    }
+}
+
+impl<'v> FromFormValue<'v> for ScoreOrder {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "points" => Ok(ScoreOrder::Points),
+            "weighted_points" => Ok(ScoreOrder::WeightedPoints),
+            _ => Err(form_value)
+        }
+    }
+}
+
+/// Which category selections a score has to match to be compared.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CategorySet {
+    Any,
+    /// The score was played with exactly these categories, in any order.
+    Exactly(Vec<CategoryId>),
+    /// The score was played with at least these categories.
+    Containing(Vec<CategoryId>)
+}
+
+impl Default for CategorySet {
+    fn default() -> Self {
+        CategorySet::Any
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ScoreFilter {
+    pub since: Option<SystemTime>,
+    pub categories: CategorySet,
+    /// Daily challenges only compete among themselves,
+    /// so without a day only regular games are included.
+    pub daily: Option<NaiveDate>
+}
+
+type BoxedScores<'a> = schema::scores::BoxedQuery<'a, Pg>;
+
+impl ScoreFilter {
+    fn apply<'a>(&self) -> BoxedScores<'a> {
+        use schema::scores::dsl::*;
+
+        let mut query = match self.daily {
+            Some(day) => scores.filter(daily.eq(day)).into_boxed(),
+            None => scores.filter(daily.is_null()).into_boxed()
+        };
+        if let Some(since) = self.since {
+            query = query.filter(played_on.ge(since));
+        }
+
+        match &self.categories {
+            CategorySet::Any => query,
+            CategorySet::Exactly(set) => query
+                .filter(categories.contains(set.clone()))
+                .filter(categories.is_contained_by(set.clone())),
+            CategorySet::Containing(set) => query
+                .filter(categories.contains(set.clone()))
+        }
+    }
+}
+
 impl Score {
-    pub fn top_three(conn: &Connection) -> QueryResult<Vec<Score>> {
+    /// Loads one page of the scores matching the filter, best first.
+    pub fn leaderboard(
+        filter: &ScoreFilter,
+        order: ScoreOrder,
+        offset: i64,
+        limit: i64,
+        conn: &Connection
+    ) -> QueryResult<Vec<Score>> {
         use schema::scores::dsl::*;
 
-        scores
+        let query = filter.apply();
+        match order {
+            ScoreOrder::Points => query.order((points.desc(), weighted_points.desc(), played_on)),
+            ScoreOrder::WeightedPoints => query.order((weighted_points.desc(), points.desc(), played_on))
+        }
+        .offset(offset)
+        .limit(limit)
+        .load(conn)
+    }
+
+    pub fn count(filter: &ScoreFilter, conn: &Connection) -> QueryResult<i64> {
+        filter.apply()
+            .count()
+            .get_result(conn)
+    }
+
+    /// The best score of a player among the ones matching the filter.
+    pub fn best_of(
+        player: &str,
+        filter: &ScoreFilter,
+        order: ScoreOrder,
+        conn: &Connection
+    ) -> QueryResult<Option<Score>> {
+        use schema::scores::dsl::*;
+
+        let query = filter.apply()
+            .filter(name.eq(player));
+        match order {
+            ScoreOrder::Points => query.order((points.desc(), weighted_points.desc())),
+            ScoreOrder::WeightedPoints => query.order((weighted_points.desc(), points.desc()))
+        }
+        .first(conn)
+        .optional()
+    }
+
+    /// The rank of this score among all scores matching the filter,
+    /// where scores with the same points share a rank.
+    pub fn rank(&self, filter: &ScoreFilter, order: ScoreOrder, conn: &Connection) -> QueryResult<u64> {
+        use schema::scores::dsl::*;
+
+        let query = filter.apply();
+        match order {
+            ScoreOrder::Points => query.filter(points.gt(self.points)),
+            ScoreOrder::WeightedPoints => query.filter(weighted_points.gt(self.weighted_points))
+        }
+        .count()
+        .get_result::<i64>(conn)
+        .map(|count| count as u64 + 1)
+    }
+
+    /// The best three scores matching the filter.
+    pub fn top_three(filter: &ScoreFilter, conn: &Connection) -> QueryResult<Vec<Score>> {
+        use schema::scores::dsl::*;
+
+        filter.apply()
             .order(weighted_points.desc())
             .limit(3)
             .load(conn)
     }
 
+    /// Placement among the scores of the same kind, which for a daily
+    /// challenge are the ones of the same day and otherwise all regular games.
     pub fn placement(&self, conn: &Connection) -> QueryResult<u64> {
-        use schema::scores::dsl::*;
-
-        scores
-            .filter(weighted_points.gt(self.weighted_points))
-            .count()
-            .get_result::<i64>(conn)
-            .map(|count| count as u64 + 1)
+        self.rank(&self.same_kind(), ScoreOrder::WeightedPoints, conn)
     }
 
+    /// Up to ten scores of the same kind right above and below this one.
     pub fn neighbours(&self, conn: &Connection) -> QueryResult<(Vec<Score>, Vec<Score>)> {
         use schema::scores::dsl::*;
 
-        let higher = scores
+        let higher = self.same_kind()
+            .apply()
             .order(weighted_points.desc())
             .filter(weighted_points.gt(self.weighted_points))
             .limit(10)
             .load(conn)?;
 
-        let lower = scores
+        let lower = self.same_kind()
+            .apply()
             .order(weighted_points.desc())
             .filter(weighted_points.lt(self.weighted_points))
             .limit(10)
@@ -279,6 +1542,301 @@ impl Score {
 
         Ok((higher, lower))
     }
+
+    fn same_kind(&self) -> ScoreFilter {
+        ScoreFilter {
+            daily: self.daily,
+            ..<_>::default()
+        }
+    }
+}
+
+impl TeamScore {
+    pub fn leaderboard(offset: i64, limit: i64, conn: &Connection) -> QueryResult<Vec<TeamScore>> {
+        use schema::team_scores::dsl::*;
+
+        team_scores
+            .order((weighted_points.desc(), points.desc(), played_on))
+            .offset(offset)
+            .limit(limit)
+            .load(conn)
+    }
+
+    pub fn placement(&self, conn: &Connection) -> QueryResult<u64> {
+        use schema::team_scores::dsl::*;
+
+        team_scores
+            .filter(weighted_points.gt(self.weighted_points))
+            .count()
+            .get_result::<i64>(conn)
+            .map(|count| count as u64 + 1)
+    }
+}
+
+#[derive(AsExpression, Serialize, Deserialize, Deref, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::Integer"]
+pub struct PlayerId(pub(super) i32);
+
+impl_to_sql_for_id!(PlayerId);
+impl_from_param_for_id!(PlayerId);
+
+impl std::str::FromStr for PlayerId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(PlayerId)
+    }
+}
+
+#[derive(Debug)]
+pub enum PlayerError {
+    Query(diesel::result::Error),
+    NameInUse
+}
+
+/// How often and how well a player answered questions of a category.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CategoryAccuracy {
+    pub category: CategoryId,
+    pub answers: i64,
+    pub correct: i64
+}
+
+impl Player {
+    pub fn id(&self) -> PlayerId {
+        PlayerId(self.id)
+    }
+
+    pub fn named(named: &str, conn: &Connection) -> QueryResult<Option<Player>> {
+        use schema::players::dsl::*;
+
+        players
+            .filter(name.eq(named))
+            .first(conn)
+            .optional()
+    }
+
+    pub fn with_id(pid: PlayerId, conn: &Connection) -> QueryResult<Option<Player>> {
+        use schema::players::dsl::*;
+
+        players
+            .filter(id.eq(pid))
+            .first(conn)
+            .optional()
+    }
+
+    /// The unique name decides, so two registrations racing for a name can't both win.
+    pub fn insert(new: &NewPlayer, conn: &Connection) -> Result<Player, PlayerError> {
+        use {
+            schema::players::dsl::*,
+            diesel::result::{Error, DatabaseErrorKind}
+        };
+
+        insert_into(players)
+            .values(new)
+            .get_result(conn)
+            .map_err(|e| match e {
+                Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => PlayerError::NameInUse,
+                e => PlayerError::Query(e)
+            })
+    }
+
+    pub fn games_played(&self, conn: &Connection) -> QueryResult<i64> {
+        use schema::games::dsl::*;
+
+        games
+            .filter(player_id.eq(self.id))
+            .count()
+            .get_result(conn)
+    }
+
+    pub fn best_scores(&self, limit: i64, conn: &Connection) -> QueryResult<Vec<Score>> {
+        use schema::scores::dsl::*;
+
+        scores
+            .filter(player_id.eq(self.id))
+            .order((weighted_points.desc(), points.desc()))
+            .limit(limit)
+            .load(conn)
+    }
+
+    /// The categories the player picked most often, with how often they were picked.
+    pub fn favourite_categories(&self, conn: &Connection) -> QueryResult<Vec<(CategoryId, i64)>> {
+        use {
+            schema::scores::dsl::*,
+            diesel::{dsl::sql, sql_types::Integer}
+        };
+
+        let picked = scores
+            .filter(player_id.eq(self.id))
+            .select(sql::<Integer>("unnest(categories)"))
+            .load::<i32>(conn)?;
+
+        let mut counts = std::collections::HashMap::<_, i64>::new();
+        for cat in picked {
+            *counts.entry(CategoryId(cat)).or_default() += 1;
+        }
+
+        let mut favourites = counts
+            .into_iter()
+            .collect::<Vec<_>>();
+        favourites.sort_by_key(|&(cat, count)| (std::cmp::Reverse(count), cat));
+
+        Ok(favourites)
+    }
+
+    pub fn accuracy(&self, conn: &Connection) -> QueryResult<Vec<CategoryAccuracy>> {
+        use {
+            schema::{answers, games, questions},
+            diesel::dsl::count_star
+        };
+
+        let counts = answers::table
+            .inner_join(games::table)
+            .inner_join(questions::table)
+            .filter(games::player_id.eq(self.id))
+            .group_by((questions::category_id, answers::correct))
+            .select((questions::category_id, answers::correct, count_star()))
+            .load::<(i32, bool, i64)>(conn)?;
+
+        let mut accuracy = Vec::<CategoryAccuracy>::new();
+        for (cat, correct, count) in counts {
+            let category = CategoryId(cat);
+            let index = match accuracy.iter().position(|acc| acc.category == category) {
+                Some(index) => index,
+                None => {
+                    accuracy.push(CategoryAccuracy { category, answers: 0, correct: 0 });
+                    accuracy.len() - 1
+                }
+            };
+            accuracy[index].answers += count;
+            if correct {
+                accuracy[index].correct += count;
+            }
+        }
+        accuracy.sort_by_key(|acc| acc.category);
+
+        Ok(accuracy)
+    }
+}
+
+impl ReviewEntry {
+    pub fn question_id(&self) -> QuestionId {
+        QuestionId(self.question_id)
+    }
+
+    pub fn of(player: PlayerId, question: QuestionId, conn: &Connection) -> QueryResult<Option<ReviewEntry>> {
+        use schema::review_schedule::dsl::*;
+
+        review_schedule
+            .filter(player_id.eq(player))
+            .filter(question_id.eq(question))
+            .first(conn)
+            .optional()
+    }
+
+    /// Inserts the entry or replaces the existing one for the same player and question.
+    pub fn save(new: &NewReviewEntry, conn: &Connection) -> QueryResult<ReviewEntry> {
+        use schema::review_schedule::dsl::*;
+
+        insert_into(review_schedule)
+            .values(new)
+            .on_conflict((player_id, question_id))
+            .do_update()
+            .set(new)
+            .get_result(conn)
+    }
+
+    /// The questions the player has already practised that aren't due again yet.
+    pub fn not_due(player: PlayerId, now: SystemTime, conn: &Connection) -> QueryResult<Vec<QuestionId>> {
+        use schema::review_schedule::dsl::*;
+
+        review_schedule
+            .filter(player_id.eq(player))
+            .filter(due_at.gt(now))
+            .select(question_id)
+            .load::<i32>(conn)
+            .map(|ids| ids
+                .into_iter()
+                .map(QuestionId)
+                .collect()
+            )
+    }
+
+    /// How many questions the player has in each box.
+    pub fn box_counts(player: PlayerId, conn: &Connection) -> QueryResult<Vec<(i16, i64)>> {
+        use {
+            schema::review_schedule::dsl::*,
+            diesel::dsl::count_star
+        };
+
+        review_schedule
+            .filter(player_id.eq(player))
+            .group_by(leitner_box)
+            .select((leitner_box, count_star()))
+            .order(leitner_box)
+            .load(conn)
+    }
+}
+
+impl DailyChallenge {
+    pub fn questions(&self) -> Vec<QuestionId> {
+        self.questions
+            .iter()
+            .copied()
+            .map(QuestionId)
+            .collect()
+    }
+
+    pub fn of_day(on: NaiveDate, conn: &Connection) -> QueryResult<Option<DailyChallenge>> {
+        use schema::daily_challenges::dsl::*;
+
+        daily_challenges
+            .find(on)
+            .first(conn)
+            .optional()
+    }
+
+    /// Stores the challenge unless the day already has one,
+    /// either way the one that's stored is returned.
+    pub fn insert(new: &NewDailyChallenge, conn: &Connection) -> QueryResult<DailyChallenge> {
+        use schema::daily_challenges::dsl::*;
+
+        insert_into(daily_challenges)
+            .values(new)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        daily_challenges
+            .find(new.day)
+            .first(conn)
+    }
+}
+
+impl DailyPlay {
+    /// Records that the challenger started the day's challenge,
+    /// returns false if they already did.
+    pub fn claim(new: &NewDailyPlay, conn: &Connection) -> QueryResult<bool> {
+        use schema::daily_plays::dsl::*;
+
+        insert_into(daily_plays)
+            .values(new)
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map(|inserted| inserted > 0)
+    }
+
+    pub fn exists(on: NaiveDate, by: &str, conn: &Connection) -> QueryResult<bool> {
+        use {
+            schema::daily_plays::dsl::*,
+            diesel::dsl::exists
+        };
+
+        diesel::select(exists(daily_plays
+            .filter(day.eq(on))
+            .filter(challenger.eq(by))
+        )).get_result(conn)
+    }
 }
 
 #[derive(Debug)]
@@ -287,7 +1845,17 @@ pub enum AdminError {
     NameInUse
 }
 
+#[derive(AsExpression, Serialize, Deserialize, Deref, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::Integer"]
+pub struct AdminId(pub(super) i32);
+
+impl_to_sql_for_id!(AdminId);
+
 impl Admin {
+    pub fn id(&self) -> AdminId {
+        AdminId(self.id)
+    }
+
     pub fn named(named: &str, conn: &Connection) -> QueryResult<Option<Admin>> {
         use schema::admins::dsl::*;
 
@@ -315,18 +1883,133 @@ impl Admin {
     }
 }
 
+#[derive(AsExpression, Serialize, Deserialize, Deref, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy)]
+#[sql_type = "diesel::sql_types::Integer"]
+pub struct ApiTokenId(pub(super) i32);
+
+impl_to_sql_for_id!(ApiTokenId);
+impl_from_param_for_id!(ApiTokenId);
+
+impl std::str::FromStr for ApiTokenId {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.parse().map(ApiTokenId)
+    }
+}
+
+impl ApiToken {
+    /// When a token was last used is only this precise,
+    /// so a busy token doesn't cause a write with every request.
+    const USE_RECORDED_EVERY: Duration = Duration::from_secs(60);
+
+    pub fn id(&self) -> ApiTokenId {
+        ApiTokenId(self.id)
+    }
+
+    pub fn admin(&self, conn: &Connection) -> QueryResult<Admin> {
+        use schema::admins::dsl::*;
+
+        admins
+            .filter(id.eq(self.admin_id))
+            .first(conn)
+    }
+
+    pub fn with_id(tid: ApiTokenId, conn: &Connection) -> QueryResult<Option<ApiToken>> {
+        use schema::api_tokens::dsl::*;
+
+        api_tokens
+            .filter(id.eq(tid))
+            .first(conn)
+            .optional()
+    }
+
+    /// Including revoked ones, newest first.
+    pub fn of_admin(admin: AdminId, conn: &Connection) -> QueryResult<Vec<ApiToken>> {
+        use schema::api_tokens::dsl::*;
+
+        api_tokens
+            .filter(admin_id.eq(admin))
+            .order(id.desc())
+            .load(conn)
+    }
+
+    /// Returns false if the admin has no such token or it was already revoked.
+    pub fn revoke(tid: ApiTokenId, admin: AdminId, conn: &Connection) -> QueryResult<bool> {
+        use schema::api_tokens::dsl::*;
+
+        update(api_tokens
+            .filter(id.eq(tid))
+            .filter(admin_id.eq(admin))
+            .filter(revoked_at.is_null())
+        ).set(revoked_at.eq(SystemTime::now()))
+            .execute(conn)
+            .map(|updated| updated > 0)
+    }
+
+    pub fn mark_used(tid: ApiTokenId, conn: &Connection) -> QueryResult<()> {
+        use schema::api_tokens::dsl::*;
+
+        let now = SystemTime::now();
+        let recorded_before = now
+            .checked_sub(Self::USE_RECORDED_EVERY)
+            .unwrap_or(SystemTime::UNIX_EPOCH);
+
+        update(api_tokens
+            .filter(id.eq(tid))
+            .filter(last_used_at.is_null().or(last_used_at.lt(recorded_before)))
+        ).set(last_used_at.eq(now))
+            .execute(conn)
+            .map(drop)
+    }
+}
+
 impl <'a> NewQuestion<'a> {
     pub fn with_category(
         category: &Category,
         string: &'a str,
         correct: &'a str,
-        incorrect: &'a [String]
+        incorrect: &'a [String],
+        tags: &'a [String],
+        locale: Locale
     ) -> NewQuestion<'a> {
         NewQuestion {
             category_id: category.id,
             string,
             correct,
-            incorrect
+            incorrect,
+            tags,
+            locale: locale.code()
+        }
+    }
+}
+
+impl <'a> NewTeamScore<'a> {
+    /// The team's score is the one their game would have given a single player.
+    pub fn of(team: &'a str, members: &'a [String], score: NewScore) -> NewTeamScore<'a> {
+        NewTeamScore {
+            team,
+            members,
+            points: score.points,
+            weighted_points: score.weighted_points,
+            played_on: score.played_on,
+            duration_secs: score.duration_secs,
+            categories: score.categories
+        }
+    }
+}
+
+impl <'a> NewQuestionRevision<'a> {
+    pub fn of(question: &'a Question, editor: &'a str) -> NewQuestionRevision<'a> {
+        NewQuestionRevision {
+            question_id: question.id,
+            category_id: question.category_id,
+            string: &question.string,
+            correct: &question.correct,
+            incorrect: &question.incorrect,
+            tags: &question.tags,
+            locale: &question.locale,
+            editor: Some(editor)
         }
     }
 }
@@ -350,10 +2033,26 @@ macro_rules! impl_insert {
 
 impl_insert!(QuestionStats, NewQuestionStats, question_stats);
 
+impl_insert!(pub, QuestionMedia, NewQuestionMedia, question_media);
+
+impl_insert!(QuestionRevision, NewQuestionRevision, question_revisions);
+
+impl_insert!(pub, QuestionReport, NewQuestionReport, question_reports);
+
+impl_insert!(pub, Answer, NewAnswer, answers);
+
+impl_insert!(pub, Game, NewGame, games);
+
 impl_insert!(pub, Category, NewCategory, categories);
 
 impl_insert!(pub, Score, NewScore, scores);
 
+impl_insert!(pub, TeamScore, NewTeamScore, team_scores);
+
+impl_insert!(pub, Duel, NewDuel, duels);
+
+impl_insert!(pub, ApiToken, NewApiToken, api_tokens);
+
 #[cfg(test)]
 mod test {
     use {
@@ -396,4 +2095,173 @@ mod test {
             Ok(())
         })
     }
+
+    #[test]
+    fn search_matches_question_and_answers() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let cat = Category::insert(&NewCategory { name: "search test" }, &conn)?;
+            let add = |string: &str, correct: &str| Question::insert(
+                &NewQuestion {
+                    category_id: cat.id,
+                    string,
+                    correct,
+                    incorrect: &["Oslo".into(), "Bern".into(), "Wien".into()],
+                    tags: &[],
+                    locale: "en"
+                },
+                "tester",
+                &conn
+            );
+            let capital = add("What is the capital of Iceland?", "Reykjavik")?;
+            let river = add("Which river flows through Vienna?", "Danube")?;
+
+            let filter = |search: &str| QuestionFilter {
+                search: Some(search.into()),
+                category: Some(cat.id()),
+                ..Default::default()
+            };
+            let found = |filter: &QuestionFilter| Question::search(filter, QuestionOrder::Id, false, 0, 10, &conn)
+                .map(|found| found.into_iter().map(|q| q.id).collect::<Vec<_>>());
+
+            assert_eq!(found(&filter("reykjavik"))?, vec![capital.id]);
+            assert_eq!(found(&filter("vienna river"))?, vec![river.id]);
+            assert_eq!(found(&filter("bern"))?, vec![capital.id, river.id]);
+            assert_eq!(Question::count(&filter("bern"), &conn)?, 2);
+            assert_eq!(Question::count(&filter("paris"), &conn)?, 0);
+
+            let unanswered = QuestionFilter {
+                category: Some(cat.id()),
+                min_correct_rate: Some(1),
+                ..Default::default()
+            };
+            assert!(found(&unanswered)?.is_empty());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn edits_and_reopening_need_review() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, StatusError, _>(|| {
+            let cat = Category::insert(&NewCategory { name: "review test" }, &conn)?;
+            let incorrect = ["1".to_string(), "2".into(), "3".into()];
+            let new = |string| NewQuestion {
+                category_id: cat.id,
+                string,
+                correct: "0",
+                incorrect: &incorrect,
+                tags: &[],
+                locale: "en"
+            };
+            let question = Question::insert(&new("Zero?"), "Tom", &conn)?;
+
+            Question::submit_for_review(question.id(), "Tom", &conn)?;
+            Question::approve(question.id(), "Ann", &conn)?;
+            let unchanged = Question::update(question.id(), new("Zero?"), "Tom", &conn)?;
+            assert_eq!(unchanged.status, QuestionStatus::Published);
+
+            let edited = Question::update(question.id(), new("Nothing?"), "Tom", &conn)?;
+            assert_eq!(edited.status, QuestionStatus::InReview);
+            assert_eq!(edited.approved_by, None);
+
+            Question::retire(question.id(), &conn)?;
+            assert_eq!(Question::reopen(question.id(), &conn)?.status, QuestionStatus::Draft);
+            match Question::reopen(question.id(), &conn) {
+                Err(StatusError::InvalidTransition) => { /* good */ },
+                _ => panic!()
+            };
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn edited_questions_dont_duplicate_themselves() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let cat = Category::insert(&NewCategory { name: "duplicate test" }, &conn)?;
+            let question = Question::insert(
+                &NewQuestion {
+                    category_id: cat.id,
+                    string: "Which planet has the most moons?",
+                    correct: "Saturn",
+                    incorrect: &["Mars".into(), "Venus".into(), "Earth".into()],
+                    tags: &[],
+                    locale: "en"
+                },
+                "Tom",
+                &conn
+            )?;
+
+            assert_eq!(
+                Question::duplicates("which planet has the most moons", None, &conn)?,
+                Duplicates::Exact(question.id())
+            );
+            assert_eq!(
+                Question::duplicates("Which planet has the most moons?", Some(question.id()), &conn)?,
+                Duplicates::None
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn only_published_questions_load_as_published() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let (cat, published) = crate::test::insert_questions("published test", &["Kept?", "Trashed?"], &conn)?;
+            let draft = Question::insert(
+                &NewQuestion::with_category(&cat, "Draft?", "0", &["1".into(), "2".into(), "3".into()], &[], Locale::En),
+                "Tom",
+                &conn
+            )?;
+            Question::delete(published[1].id(), &conn)?;
+
+            let loaded = |question: &Question| Question::load_published(question.id(), &conn)
+                .map(|loaded| loaded.is_some());
+
+            assert!(loaded(&published[0])?);
+            assert!(!loaded(&published[1])?);
+            assert!(!loaded(&draft)?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn revision_diff_lists_changed_fields() {
+        let revision = |string: &str, tags: &[&str]| QuestionRevision {
+            id: 1,
+            question_id: 1,
+            category_id: 1,
+            string: string.into(),
+            correct: "42".into(),
+            incorrect: ["41".into(), "43".into(), "44".into()],
+            tags: tags.iter().copied().map(String::from).collect(),
+            locale: "en".into(),
+            editor: None,
+            revised_at: std::time::SystemTime::UNIX_EPOCH
+        };
+
+        let old = revision("What is the answer?", &["nerd"]);
+        let new = revision("What is the answer to everything?", &["nerd"]);
+
+        assert_eq!(old.diff(&new), vec![FieldChange {
+            field: "string",
+            old: "What is the answer?".into(),
+            new: "What is the answer to everything?".into()
+        }]);
+        assert!(old.diff(&old).is_empty())
+    }
 }
\ No newline at end of file
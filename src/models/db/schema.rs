@@ -6,6 +6,32 @@ table! {
     }
 }
 
+table! {
+    api_tokens (id) {
+        id -> Int4,
+        admin_id -> Int4,
+        name -> Text,
+        secret -> Text,
+        scopes -> Array<Text>,
+        created_at -> Timestamp,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    answers (id) {
+        id -> Int4,
+        question_id -> Int4,
+        chosen -> Nullable<Int2>,
+        time_taken_ms -> Int4,
+        joker_used -> Bool,
+        answered_at -> Timestamp,
+        game_id -> Nullable<Int4>,
+        correct -> Bool,
+    }
+}
+
 table! {
     categories (id) {
         id -> Int4,
@@ -13,6 +39,92 @@ table! {
     }
 }
 
+table! {
+    daily_challenges (day) {
+        day -> Date,
+        questions -> Array<Int4>,
+    }
+}
+
+table! {
+    daily_plays (day, challenger) {
+        day -> Date,
+        challenger -> Text,
+        started_at -> Timestamp,
+    }
+}
+
+table! {
+    duels (id) {
+        id -> Int4,
+        code -> Text,
+        seed -> Int8,
+        questions -> Array<Int4>,
+        challenger_game -> Int4,
+        opponent_game -> Nullable<Int4>,
+        created_at -> Timestamp,
+        finished_at -> Nullable<Timestamp>,
+        accepted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    games (id) {
+        id -> Int4,
+        player -> Text,
+        started_at -> Timestamp,
+        player_id -> Nullable<Int4>,
+        visitor -> Nullable<Text>,
+        seed -> Nullable<Int8>,
+    }
+}
+
+table! {
+    players (id) {
+        id -> Int4,
+        name -> Text,
+        password -> Text,
+        registered_at -> Timestamp,
+    }
+}
+
+table! {
+    question_media (id) {
+        id -> Int4,
+        question_id -> Int4,
+        answer -> Nullable<Int2>,
+        file_name -> Text,
+        content_type -> Text,
+    }
+}
+
+table! {
+    question_reports (id) {
+        id -> Int4,
+        question_id -> Int4,
+        reason -> Int2,
+        comment -> Nullable<Text>,
+        reported_at -> Timestamp,
+        resolved_at -> Nullable<Timestamp>,
+        resolved_by -> Nullable<Text>,
+    }
+}
+
+table! {
+    question_revisions (id) {
+        id -> Int4,
+        question_id -> Int4,
+        category_id -> Int4,
+        string -> Text,
+        correct -> Text,
+        incorrect -> Array<Text>,
+        tags -> Array<Text>,
+        locale -> Text,
+        editor -> Nullable<Text>,
+        revised_at -> Timestamp,
+    }
+}
+
 table! {
     question_stats (id) {
         id -> Int4,
@@ -22,6 +134,17 @@ table! {
     }
 }
 
+table! {
+    question_translations (id) {
+        id -> Int4,
+        question_id -> Int4,
+        locale -> Text,
+        string -> Text,
+        correct -> Text,
+        incorrect -> Array<Text>,
+    }
+}
+
 table! {
     questions (id) {
         id -> Int4,
@@ -29,6 +152,23 @@ table! {
         string -> Text,
         correct -> Text,
         incorrect -> Array<Text>,
+        tags -> Array<Text>,
+        locale -> Text,
+        status -> Int2,
+        submitted_by -> Nullable<Text>,
+        approved_by -> Nullable<Text>,
+        deleted_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    review_schedule (player_id, question_id) {
+        player_id -> Int4,
+        question_id -> Int4,
+        leitner_box -> Int2,
+        due_at -> Timestamp,
+        reviewed_at -> Timestamp,
     }
 }
 
@@ -41,16 +181,55 @@ table! {
         played_on -> Timestamp,
         duration -> Int8,
         categories -> Array<Int4>,
+        player_id -> Nullable<Int4>,
+        daily -> Nullable<Date>,
+    }
+}
+
+table! {
+    team_scores (id) {
+        id -> Int4,
+        team -> Text,
+        members -> Array<Text>,
+        points -> Int4,
+        weighted_points -> Int4,
+        played_on -> Timestamp,
+        duration -> Int8,
+        categories -> Array<Int4>,
     }
 }
 
+joinable!(answers -> games (game_id));
+joinable!(api_tokens -> admins (admin_id));
+joinable!(answers -> questions (question_id));
+joinable!(games -> players (player_id));
+joinable!(question_media -> questions (question_id));
+joinable!(question_reports -> questions (question_id));
+joinable!(question_revisions -> questions (question_id));
 joinable!(question_stats -> questions (question_id));
+joinable!(question_translations -> questions (question_id));
 joinable!(questions -> categories (category_id));
+joinable!(review_schedule -> players (player_id));
+joinable!(review_schedule -> questions (question_id));
+joinable!(scores -> players (player_id));
 
 allow_tables_to_appear_in_same_query!(
     admins,
+    answers,
+    api_tokens,
     categories,
+    daily_challenges,
+    daily_plays,
+    duels,
+    games,
+    players,
+    question_media,
+    question_reports,
+    question_revisions,
     question_stats,
+    question_translations,
     questions,
+    review_schedule,
     scores,
+    team_scores,
 );
@@ -0,0 +1,222 @@
+use {
+    serde::Serialize,
+    diesel::QueryResult,
+    uuid::Uuid,
+    crate::models::{
+        i18n::Locale,
+        game::{self, GameState, POINTS_PER_QUESTION},
+        db::{
+            Connection,
+            DuelId,
+            GameId,
+            models::{Answer, Duel, NewDuel, Game, Question}
+        }
+    }
+};
+
+/// The part a game plays in a duel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuelRole {
+    /// Others get to play the questions of this game afterwards.
+    Challenger,
+    /// The game replays the questions of a challenge.
+    Opponent(DuelId)
+}
+
+/// Either creates the challenge or marks it as answered, depending on the role
+/// of the ended game, and returns the code of the duel.
+pub fn conclude(game_state: &GameState, conn: &Connection) -> QueryResult<Option<String>> {
+    match (game_state.duel(), game_state.game()) {
+        (Some(DuelRole::Challenger), Some(game)) => {
+            let questions = game_state.served();
+            if questions.is_empty() {
+                return Ok(None)
+            }
+
+            let code = Uuid::new_v4()
+                .to_simple()
+                .to_string();
+            Duel::insert(&NewDuel {
+                code: &code,
+                seed: game::stored_seed(game_state.seed()),
+                questions,
+                challenger_game: *game
+            }, conn)?;
+            Ok(Some(code))
+        },
+        (Some(DuelRole::Opponent(duel)), Some(game)) => Duel::finish(duel, game, conn)
+            .map(|duel| duel.map(|duel| duel.code)),
+        _ => Ok(None)
+    }
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DuelAnswer {
+    answer: Option<String>,
+    correct: bool,
+    time_ms: i32
+}
+
+#[derive(Serialize, Debug)]
+pub struct ComparedQuestion {
+    question: String,
+    correct: String,
+    challenger: Option<DuelAnswer>,
+    opponent: Option<DuelAnswer>
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Side {
+    name: String,
+    points: i32,
+    total_ms: i64
+}
+
+/// Both games next to each other, question by question.
+#[derive(Serialize, Debug)]
+pub struct Comparison {
+    challenger: Side,
+    opponent: Side,
+    questions: Vec<ComparedQuestion>
+}
+
+pub fn challenger_name(duel: &Duel, conn: &Connection) -> QueryResult<String> {
+    Game::with_id(duel.challenger_game(), conn)
+        .map(|game| game.map_or_else(String::new, |game| game.player))
+}
+
+/// None until the opponent is done, the challenger's answers would give everything away.
+pub fn compare(duel: &Duel, locale: Locale, conn: &Connection) -> QueryResult<Option<Comparison>> {
+    let opponent_game = match (duel.opponent_game(), duel.finished_at) {
+        (Some(game), Some(_)) => game,
+        _ => return Ok(None)
+    };
+
+    let questions = Question::load_translated(&duel.questions(), locale, conn)?;
+    let challenger = Answer::of_game(duel.challenger_game(), conn)?;
+    let opponent = Answer::of_game(opponent_game, conn)?;
+
+    let answer_to = |question: &Question, answers: &[Answer]| answers
+        .iter()
+        .find(|answer| answer.question_id() == question.id())
+        .map(|answer| DuelAnswer {
+            answer: answer.chosen.and_then(|chosen| match chosen {
+                0 => Some(question.correct.clone()),
+                n => question.incorrect.get(n as usize - 1).cloned()
+            }),
+            correct: answer.correct,
+            time_ms: answer.time_taken_ms
+        });
+
+    Ok(Some(Comparison {
+        challenger: side(duel.challenger_game(), &challenger, conn)?,
+        opponent: side(opponent_game, &opponent, conn)?,
+        questions: questions
+            .iter()
+            .map(|question| ComparedQuestion {
+                question: question.string.clone(),
+                correct: question.correct.clone(),
+                challenger: answer_to(question, &challenger),
+                opponent: answer_to(question, &opponent)
+            })
+            .collect()
+    }))
+}
+
+fn side(game: GameId, answers: &[Answer], conn: &Connection) -> QueryResult<Side> {
+    Ok(Side {
+        name: Game::with_id(game, conn)?.map_or_else(String::new, |game| game.player),
+        points: answers
+            .iter()
+            .filter(|answer| answer.correct)
+            .count() as i32
+            * POINTS_PER_QUESTION,
+        total_ms: answers
+            .iter()
+            .map(|answer| answer.time_taken_ms as i64)
+            .sum()
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::test::{CONN, insert_questions},
+        diesel::Connection,
+        std::time::{Duration, SystemTime}
+    };
+
+    #[test]
+    fn opponents_replay_the_challenge() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let (cat, _) = insert_questions("Duels", &["First?", "Second?"], &conn)?;
+
+            let mut challenge = game::new_game_state("Ann".into(), None, "ann", 7, &[cat.id()], vec![], Locale::En, &conn)?;
+            challenge.challenge_others();
+            challenge.load_more_questions(&conn).ok();
+            for answer in &["0", "1"] {
+                challenge.next_question().ok();
+                game::answer(answer, &mut challenge, &conn).ok();
+            }
+            let code = conclude(&challenge, &conn)?.unwrap();
+            let duel = Duel::with_code(&code, &conn)?.unwrap();
+            assert_eq!(duel.questions(), challenge.served());
+            assert!(compare(&duel, Locale::En, &conn)?.is_none());
+
+            let mut opponent = game::new_duel_state("Bob".into(), None, "bob", &duel, Locale::En, &conn)?.unwrap();
+            assert!(game::new_duel_state("Cid".into(), None, "cid", &duel, Locale::En, &conn)?.is_none());
+            for answer in &["0", "0"] {
+                opponent.next_question().ok();
+                game::answer(answer, &mut opponent, &conn).ok();
+            }
+            assert_eq!(opponent.served(), challenge.served());
+            assert_eq!(conclude(&opponent, &conn)?, Some(code.clone()));
+
+            let duel = Duel::with_code(&code, &conn)?.unwrap();
+            let comparison = compare(&duel, Locale::En, &conn)?.unwrap();
+            assert_eq!(comparison.challenger.points, POINTS_PER_QUESTION);
+            assert_eq!(comparison.opponent.points, 2 * POINTS_PER_QUESTION);
+            assert_eq!(comparison.questions[1].challenger.as_ref().unwrap().answer, Some("1".into()));
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn abandoned_duels_can_be_taken() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let (cat, _) = insert_questions("Duels", &["First?"], &conn)?;
+
+            let mut challenge = game::new_game_state("Ann".into(), None, "ann", 7, &[cat.id()], vec![], Locale::En, &conn)?;
+            challenge.challenge_others();
+            challenge.load_more_questions(&conn).ok();
+            challenge.next_question().ok();
+            game::answer("0", &mut challenge, &conn).ok();
+            let code = conclude(&challenge, &conn)?.unwrap();
+            let duel = Duel::with_code(&code, &conn)?.unwrap();
+
+            let bob = game::new_duel_state("Bob".into(), None, "bob", &duel, Locale::En, &conn)?.unwrap();
+            let cid = game::new_game_state("Cid".into(), None, "cid", 7, &[cat.id()], vec![], Locale::En, &conn)?
+                .game()
+                .unwrap();
+            let later = SystemTime::now() + Duel::ABANDONED_AFTER + Duration::from_secs(1);
+            assert!(Duel::with_code(&code, &conn)?.unwrap().is_taken(SystemTime::now()));
+            assert!(!Duel::accept(duel.id(), cid, SystemTime::now(), &conn)?);
+            assert!(!Duel::with_code(&code, &conn)?.unwrap().is_taken(later));
+            assert!(Duel::accept(duel.id(), cid, later, &conn)?);
+
+            // bob gave up, so finishing late doesn't count anymore
+            assert_eq!(conclude(&bob, &conn)?, None);
+            assert!(compare(&Duel::with_code(&code, &conn)?.unwrap(), Locale::En, &conn)?.is_none());
+
+            Ok(())
+        })
+    }
+}
\ No newline at end of file
@@ -1,49 +1,133 @@
 use {
-    diesel::{PgConnection, QueryResult},
+    serde::Serialize,
+    diesel::{PgConnection, QueryResult, Connection},
+    chrono::{Datelike, NaiveDate},
     rand::{
+        Rng,
+        SeedableRng,
         thread_rng,
         seq::SliceRandom
     },
+    rand_chacha::ChaCha8Rng,
     std::{
-        time::SystemTime,
+        time::{Instant, SystemTime},
         iter::FromIterator,
         collections::{VecDeque, HashSet}
     },
     crate::{
         models::stopwatch::Stopwatch,
+        models::practice,
+        models::duels::DuelRole,
+        models::web::{Session, Broadcaster, EventStream, event},
+        models::i18n::Locale,
         models::db::{
             QuestionId,
             CategoryId,
-            models::{Question, Category, NewScore, Score}
+            GameId,
+            PlayerId,
+            ScoreFilter,
+            ScoreOrder,
+            CategorySet,
+            Returning,
+            models::{Question, Category, Game, NewGame, Answer, NewAnswer, NewScore, Score, ReviewEntry, Duel}
         }
     }
 };
 
+pub const POINTS_PER_QUESTION: i32 = 30;
+
 #[derive(Debug)]
 pub enum QuestionError {
     Query(diesel::result::Error),
     NoneRemaining
 }
 
+/// All randomness in a game comes from here, so a game
+/// played with the same seed and answers plays out the same.
+/// ChaCha8Rng is used since its output is fixed across versions,
+/// unlike StdRng which would change how stored games replay.
+#[derive(Debug)]
+struct GameRng {
+    seed: u64,
+    rng: ChaCha8Rng
+}
+
+impl GameRng {
+    fn new(seed: u64) -> GameRng {
+        GameRng {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed)
+        }
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(thread_rng().gen())
+    }
+}
+
 #[derive(Debug, Default)]
 pub struct GameState {
+    game: Option<GameId>,
     user: String,
+    player: Option<PlayerId>,
     categories: Vec<Category>,
+    tags: Vec<String>,
+    locale: Locale,
     pub current_question: Option<Question>,
     can_proceed: bool,
     questions: VecDeque<Question>,
     points: i32,
     joker: bool,
     pub stopwatch: Stopwatch,
+    // when the current question was shown and whether the joker was used on it
+    question_shown: Option<Instant>,
+    joker_on_current: bool,
     answered: Vec<QuestionId>,
-    total_categories: HashSet<Category>
+    rng: GameRng,
+    // questions from earlier games, only asked again once nothing else is left
+    seen: Vec<QuestionId>,
+    // in practice mode there is no score, instead every answer
+    // updates the player's review schedule
+    practice: bool,
+    not_due: Vec<QuestionId>,
+    // daily challenges get all of their questions up front
+    daily: Option<NaiveDate>,
+    duel: Option<DuelRole>,
+    total_categories: HashSet<Category>,
+    events: Broadcaster
+}
+
+#[derive(Serialize)]
+struct QuestionStarted<'a> {
+    category: &'a str,
+    string: &'a str,
+    answers: Vec<&'a str>
+}
+
+#[derive(Serialize)]
+struct AnswerRevealed<'a> {
+    correct: &'a str,
+    answered_correctly: bool
+}
+
+#[derive(Serialize)]
+struct ScoreboardUpdated {
+    points: i32,
+    weighted_points: i32,
+    joker: bool
 }
 
 impl GameState {
-    pub fn new(user: String, categories: Vec<Category>) -> GameState {
+    pub fn new(game: GameId, seed: u64, user: String, categories: Vec<Category>, tags: Vec<String>, locale: Locale) -> GameState {
         GameState {
+            game: Some(game),
+            rng: GameRng::new(seed),
             user,
             categories: categories.clone(),
+            tags,
+            locale,
             can_proceed: true,
             joker: true,
             stopwatch: Stopwatch::start(),
@@ -60,6 +144,9 @@ impl GameState {
                 .map(Question::id)
                 .map(|id| self.answered.push(id));
             self.current_question = self.questions.pop_front();
+            self.question_shown = Some(Instant::now());
+            self.joker_on_current = false;
+            self.send_question_started();
             self.current_question()
                 .ok_or(NextQuestionError::NoneRemaining)
         } else {
@@ -68,29 +155,99 @@ impl GameState {
     }
 
     pub fn current_question(&self) -> Option<(&Category, &Question)> {
+        // questions picked by tag may belong to categories
+        // that weren't selected, so look through all of them
         self.current_question
             .as_ref()
-            .and_then(|q| self.categories
+            .and_then(|q| self.total_categories
                 .iter()
                 .find(|cat| q.is_of_category(cat))
                 .map(|cat| (cat, q))
             )
     }
 
+    fn send_question_started(&mut self) {
+        let question = match self.current_question.as_ref() {
+            Some(question) => question,
+            None => return
+        };
+        let category = self.total_categories
+            .iter()
+            .find(|cat| question.is_of_category(cat))
+            .map_or("", |cat| cat.name.as_str());
+
+        // in the same order as on the page
+        let mut answers = question
+            .incorrect
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once(question.correct.as_str()))
+            .collect::<Vec<_>>();
+        pseudo_shuffle(&mut answers);
+
+        self.events.send(event::QUESTION_STARTED, &QuestionStarted {
+            category,
+            string: &question.string,
+            answers
+        });
+    }
+
+    fn scoreboard(&self) -> ScoreboardUpdated {
+        ScoreboardUpdated {
+            points: self.points,
+            weighted_points: self.weighted_points(),
+            joker: self.joker
+        }
+    }
+
+    /// The stream starts with the current score and then follows every question and answer.
+    /// Every session gets one stream, a new one ends the one before.
+    pub fn subscribe(&mut self, by: Session) -> Option<EventStream> {
+        let scoreboard = self.scoreboard();
+        self.events.subscribe(by, event::SCOREBOARD_UPDATED, &scoreboard)
+    }
+
     pub fn load_more_questions(&mut self, conn: &PgConnection) -> Result<(), QuestionError> {
         self.can_proceed = true;
-        Question::load_set(&self.categories, &self.answered, conn)
+        if self.daily.is_some() || self.is_duel_opponent() {
+            return Err(QuestionError::NoneRemaining)
+        }
+
+        let excluded = [&self.answered[..], &self.not_due[..]].concat();
+        let mut questions = Question::load_set(&self.categories, &self.tags, &excluded, &self.seen, self.locale, conn)
             .map_err(QuestionError::Query)
             .into_iter()
             .filter(|vec| !vec.is_empty())
             .next()
-            .ok_or(QuestionError::NoneRemaining)
-            .map(|mut questions| self.questions
-                .extend({
-                    questions.shuffle(&mut thread_rng());
-                    questions
-                })
+            .ok_or(QuestionError::NoneRemaining)?;
+
+        self.load_missing_categories(&questions, conn)
+            .map_err(QuestionError::Query)?;
+
+        questions.shuffle(&mut self.rng.rng);
+        // the sort is stable, so both parts stay shuffled
+        questions.sort_by_key(|q| self.seen.contains(&q.id()));
+        self.questions.extend(questions);
+        Ok(())
+    }
+
+    fn load_missing_categories(&mut self, questions: &[Question], conn: &PgConnection) -> QueryResult<()> {
+        let missing = questions
+            .iter()
+            .map(Question::category_id)
+            .filter(|id| !self.total_categories
+                .iter()
+                .any(|cat| cat.id() == *id)
             )
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        if !missing.is_empty() {
+            self.total_categories.extend(Category::load_with_ids(&missing, conn)?);
+        }
+
+        Ok(())
     }
 
     pub fn set_categories(&mut self, categories: Vec<Category>) {
@@ -99,7 +256,7 @@ impl GameState {
     }
 
     pub fn increment_points(&mut self) {
-        self.points += 30
+        self.points += POINTS_PER_QUESTION
     }
 
     pub fn use_joker(&mut self) -> Result<[&str; 2], JokerError> {
@@ -107,16 +264,18 @@ impl GameState {
             Err(JokerError::AlreadyUsed)
         } else {
             self.joker = false;
+            self.joker_on_current = true;
             self.two_incorrect()
                 .ok_or(JokerError::NoQuestion)
         }
     }
 
-    fn two_incorrect(&self) -> Option<[&str; 2]> {
+    fn two_incorrect(&mut self) -> Option<[&str; 2]> {
+        let rng = &mut self.rng.rng;
         self.current_question
             .as_ref()
             .map(|q| q.incorrect
-                .choose_multiple(&mut thread_rng(), 2)
+                .choose_multiple(rng, 2)
             ).and_then(|mut iter| Some([
             iter.next()?.as_str(),
             iter.next()?.as_str()
@@ -143,11 +302,20 @@ impl GameState {
                 .elapsed()
                 .as_secs()
                 as _,
-            categories: self
-                .categories
-                .iter()
-                .map(Category::id)
-                .collect()
+            // the selected categories along with those of every question loaded
+            // for the game, since questions picked by tag, daily challenges
+            // and duels can bring in categories that weren't selected
+            categories: {
+                let mut ids = self
+                    .total_categories
+                    .iter()
+                    .map(Category::id)
+                    .collect::<Vec<_>>();
+                ids.sort_unstable();
+                ids
+            },
+            player_id: self.player.map(|id| *id),
+            daily: self.daily
         }
     }
 
@@ -158,6 +326,66 @@ impl GameState {
     pub fn joker(&self) -> bool {
         self.joker
     }
+
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Whether the current question still needs an answer.
+    pub fn awaits_answer(&self) -> bool {
+        !self.can_proceed && self.current_question.is_some()
+    }
+
+    pub fn is_practice(&self) -> bool {
+        self.practice
+    }
+
+    pub fn player(&self) -> Option<PlayerId> {
+        self.player
+    }
+
+    pub fn daily(&self) -> Option<NaiveDate> {
+        self.daily
+    }
+
+    pub fn game(&self) -> Option<GameId> {
+        self.game
+    }
+
+    pub fn duel(&self) -> Option<DuelRole> {
+        self.duel
+    }
+
+    pub fn is_duel_opponent(&self) -> bool {
+        match self.duel {
+            Some(DuelRole::Opponent(_)) => true,
+            _ => false
+        }
+    }
+
+    /// After the game, others can be challenged to play the same questions.
+    pub fn challenge_others(&mut self) {
+        self.duel = Some(DuelRole::Challenger);
+    }
+
+    /// Every question shown so far in order, answered or not.
+    pub fn served(&self) -> Vec<QuestionId> {
+        self.answered
+            .iter()
+            .copied()
+            .chain(self.current_question.as_ref().map(Question::id))
+            .collect()
+    }
+
+    /// The seed to replay the game with.
+    pub fn seed(&self) -> u64 {
+        self.rng.seed
+    }
+}
+
+/// The seed the way it is stored, postgres has no unsigned integers but the bits are what matters.
+pub fn stored_seed(seed: u64) -> i64 {
+    seed as i64
 }
 
 pub enum NextQuestionError {
@@ -188,6 +416,8 @@ pub fn correct_ratio(question: &Question, conn: &PgConnection) -> QueryResult<u8
 
 pub struct Scores {
     pub placement: u64,
+    /// Placement among the scores played with the same categories.
+    pub set_placement: u64,
     pub top_three: Vec<Score>,
     pub lower: Vec<Score>,
     pub higher: Vec<Score>,
@@ -196,9 +426,19 @@ pub struct Scores {
 pub fn scores(user_score: &Score, conn: &PgConnection) -> QueryResult<Scores> {
     let (higher, lower) = user_score.neighbours(&conn)?;
 
+    let same_kind = ScoreFilter {
+        daily: user_score.daily,
+        ..<_>::default()
+    };
+    let same_set = ScoreFilter {
+        categories: CategorySet::Exactly(user_score.categories.clone()),
+        ..same_kind.clone()
+    };
+
     Ok(Scores {
         placement: user_score.placement(conn)?,
-        top_three: Score::top_three(conn)?,
+        set_placement: user_score.rank(&same_set, ScoreOrder::WeightedPoints, conn)?,
+        top_three: Score::top_three(&same_kind, conn)?,
         lower,
         higher
     })
@@ -222,34 +462,190 @@ pub fn answer(answer: &str, game_state: &mut GameState, conn: &PgConnection) ->
 
     game_state.can_proceed = true;
 
-    if answer == cq.correct {
-        update_stats(cq, true, conn)?;
+    let new = NewAnswer {
+        question_id: *cq.id(),
+        chosen: cq.answer_index(answer),
+        time_taken_ms: game_state
+            .question_shown
+            .map(|shown| shown.elapsed().as_millis())
+            .unwrap_or_default()
+            .min(i32::MAX as u128)
+            as i32,
+        joker_used: game_state.joker_on_current,
+        game_id: game_state.game.map(|id| *id),
+        correct: answer == cq.correct
+    };
+
+    let review = game_state.player.filter(|_| game_state.practice);
+    update_stats(cq, &new, review, conn)?;
+
+    game_state.events.send(event::ANSWER_REVEALED, &AnswerRevealed {
+        correct: &cq.correct,
+        answered_correctly: new.correct
+    });
+
+    if new.correct {
         game_state.increment_points();
+    }
+    let scoreboard = game_state.scoreboard();
+    game_state.events.send(event::SCOREBOARD_UPDATED, &scoreboard);
+
+    if new.correct {
         Ok(Answered::Correctly)
     } else {
-        update_stats(cq, false, conn)?;
         Ok(Answered::Incorrectly)
     }
 }
 
-fn update_stats(question: &Question, correct: bool, conn: &PgConnection) -> Result<(), AnswerError> {
-    if correct {
-        question.stats().add_correct(conn)
-    } else {
-        question.stats().add_incorrect(conn)
-    }.map_err(AnswerError::Query)
+// every answer is recorded on its own, the stats only keep the totals;
+// in practice mode the player's review schedule is updated along with them
+fn update_stats(question: &Question, new: &NewAnswer, review: Option<PlayerId>, conn: &PgConnection) -> Result<(), AnswerError> {
+    conn.transaction(|| {
+        Answer::insert(new, conn)?;
+        if new.correct {
+            question.stats().add_correct(conn)?;
+        } else {
+            question.stats().add_incorrect(conn)?;
+        }
+
+        if let Some(player) = review {
+            practice::review(player, question.id(), new.correct, conn)?;
+        }
+
+        Ok(())
+    }).map_err(AnswerError::Query)
 }
 
-pub fn new_game_state(user: String, categories: &[CategoryId], conn: &PgConnection) -> QueryResult<GameState> {
+pub fn new_game_state(
+    user: String,
+    player: Option<PlayerId>,
+    visitor: &str,
+    seed: u64,
+    categories: &[CategoryId],
+    tags: Vec<String>,
+    locale: Locale,
+    conn: &PgConnection
+) -> QueryResult<GameState> {
+    // an account is the better way to recognise someone, since it works across devices
+    let returning = player.map_or(Returning::Visitor(visitor), Returning::Player);
+    let seen = Answer::seen_by(returning, conn)?;
+
+    let game = Game::insert(
+        &NewGame {
+            player: &user,
+            player_id: player.map(|id| *id),
+            visitor: Some(visitor),
+            seed: stored_seed(seed)
+        },
+        conn
+    )?;
     Category::load_with_ids(&categories, &conn)
-        .map(|cats| GameState::new(user, cats))
+        .map(|cats| GameState {
+            player,
+            seen,
+            ..GameState::new(game.id(), seed, user, cats, tags, locale)
+        })
+}
+
+/// Practice games only serve questions that are due for the player
+/// or that they have never practised before.
+pub fn new_practice_state(
+    user: String,
+    player: PlayerId,
+    visitor: &str,
+    seed: u64,
+    categories: &[CategoryId],
+    tags: Vec<String>,
+    locale: Locale,
+    conn: &PgConnection
+) -> QueryResult<GameState> {
+    let not_due = ReviewEntry::not_due(player, SystemTime::now(), conn)?;
+
+    new_game_state(user, Some(player), visitor, seed, categories, tags, locale, conn)
+        .map(|state| GameState {
+            practice: true,
+            not_due,
+            // questions don't need to be avoided, the schedule decides
+            seen: vec![],
+            ..state
+        })
+}
+
+/// The daily challenge is played with exactly the questions of the day,
+/// the categories are whichever they belong to.
+pub fn new_daily_state(
+    user: String,
+    player: Option<PlayerId>,
+    visitor: &str,
+    day: NaiveDate,
+    questions: Vec<Question>,
+    locale: Locale,
+    conn: &PgConnection
+) -> QueryResult<GameState> {
+    let mut categories = questions
+        .iter()
+        .map(Question::category_id)
+        .collect::<Vec<_>>();
+    categories.sort();
+    categories.dedup();
+
+    // the questions are the same for everyone, so the joker might as well be
+    let seed = day.num_days_from_ce() as u64;
+    new_game_state(user, player, visitor, seed, &categories, vec![], locale, conn)
+        .map(|state| GameState {
+            daily: Some(day),
+            questions: questions.into(),
+            seen: vec![],
+            ..state
+        })
+}
+
+/// Answers a challenge with the challenger's questions in the same order,
+/// with the same seed so even the joker leaves the same answers.
+/// Takes the duel with the new game, None if someone else has taken it already.
+pub fn new_duel_state(
+    user: String,
+    player: Option<PlayerId>,
+    visitor: &str,
+    duel: &Duel,
+    locale: Locale,
+    conn: &PgConnection
+) -> QueryResult<Option<GameState>> {
+    let questions = Question::load_translated(&duel.questions(), locale, conn)?;
+    let mut categories = questions
+        .iter()
+        .map(Question::category_id)
+        .collect::<Vec<_>>();
+    categories.sort();
+    categories.dedup();
+
+    // two people may have opened the link at the same time,
+    // the game of whoever lost the race isn't kept
+    let accepted = conn.transaction(|| {
+        let state = new_game_state(user, player, visitor, duel.seed as u64, &categories, vec![], locale, conn)?;
+        match state.game() {
+            Some(game) if Duel::accept(duel.id(), game, SystemTime::now(), conn)? => Ok(state),
+            _ => Err(diesel::result::Error::RollbackTransaction)
+        }
+    });
+
+    match accepted {
+        Ok(state) => Ok(Some(GameState {
+            duel: Some(DuelRole::Opponent(duel.id())),
+            questions: questions.into(),
+            seen: vec![],
+            ..state
+        })),
+        Err(diesel::result::Error::RollbackTransaction) => Ok(None),
+        Err(e) => Err(e)
+    }
 }
 
 #[cfg(test)]
 mod test {
     use {
         super::*,
-        crate::test::CONN,
+        crate::test::{CONN, insert_questions},
         diesel::Connection,
     };
 
@@ -269,4 +665,137 @@ mod test {
         }
 
     }
+
+    #[test]
+    fn stats_are_derivable_from_answers() {
+        use crate::models::db::models::{NewCategory, NewQuestion};
+
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let cat = Category::insert(&NewCategory { name: "Answers" }, &conn)?;
+            let incorrect = ["41".to_string(), "43".into(), "44".into()];
+            let question = Question::insert(
+                &NewQuestion::with_category(&cat, "What is the answer?", "42", &incorrect, &[], Locale::En),
+                "Tom",
+                &conn
+            )?;
+
+            let mut game_state = new_game_state("Tom".into(), None, "visitor", 1, &[cat.id()], vec![], Locale::En, &conn)?;
+            for ans in &["42", "41", "44"] {
+                game_state.current_question = Some(question.clone());
+                assert!(answer(ans, &mut game_state, &conn).is_ok());
+            }
+
+            assert_eq!(
+                question.stats().load(&conn)?,
+                question.stats().from_answers(&conn)?
+            );
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn returning_players_get_unseen_questions_first() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, QuestionError, _>(|| {
+            let (cat, questions) = insert_questions("Returning", &["First?", "Second?", "Third?"], &conn)
+                .map_err(QuestionError::Query)?;
+
+            let mut first_game = new_game_state("Tom".into(), None, "returning", 1, &[cat.id()], vec![], Locale::En, &conn)
+                .map_err(QuestionError::Query)?;
+            first_game.current_question = Some(questions[1].clone());
+            assert!(answer("0", &mut first_game, &conn).is_ok());
+
+            let mut second_game = new_game_state("Tom".into(), None, "returning", 1, &[cat.id()], vec![], Locale::En, &conn)
+                .map_err(QuestionError::Query)?;
+            second_game.load_more_questions(&conn)?;
+            let order = second_game.questions
+                .iter()
+                .map(Question::id)
+                .collect::<Vec<_>>();
+            assert_eq!(order.len(), 3);
+            assert_eq!(order[2], questions[1].id());
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn daily_and_regular_scores_stay_apart() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let insert = |name, weighted_points, daily| Score::insert(&NewScore {
+                name,
+                points: 300,
+                weighted_points,
+                played_on: SystemTime::now(),
+                duration_secs: 3,
+                categories: vec![],
+                player_id: None,
+                daily
+            }, &conn);
+            // high enough to be among the top three and right next to each other
+            let regular = insert("Regular", 1_000_000, None)?;
+            let daily = insert("Daily", 1_000_001, Some(NaiveDate::from_ymd(2026, 10, 19)))?;
+
+            let listed = |of: &Score, other: &Score| scores(of, &conn)
+                .map(|scores| [scores.top_three, scores.higher, scores.lower]
+                    .iter()
+                    .any(|listed| listed.contains(other))
+                );
+
+            assert!(!listed(&regular, &daily)?);
+            assert!(!listed(&daily, &regular)?);
+
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn games_replay_from_their_seed() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, QuestionError, _>(|| {
+            let (cat, _) = insert_questions("Seeded", &["A?", "B?", "C?", "D?", "E?", "F?"], &conn)
+                .map_err(QuestionError::Query)?;
+
+            // question order and joker picks, the visitor is new every time so nothing counts as seen
+            let play = |seed, visitor| -> Result<(Vec<String>, Vec<String>), QuestionError> {
+                let mut game_state = new_game_state("Tom".into(), None, visitor, seed, &[cat.id()], vec![], Locale::En, &conn)
+                    .map_err(QuestionError::Query)?;
+                game_state.load_more_questions(&conn)?;
+                let order = game_state.questions
+                    .iter()
+                    .map(|q| q.string.clone())
+                    .collect();
+                game_state.next_question().ok();
+                let joker = match game_state.use_joker() {
+                    Ok(picks) => picks.iter().map(|s| s.to_string()).collect(),
+                    Err(_) => panic!("the joker should be available")
+                };
+
+                Ok((order, joker))
+            };
+            let strings = |strings: &[&str]| strings
+                .iter()
+                .map(|s| s.to_string())
+                .collect::<Vec<_>>();
+
+            let first = play(42, "first")?;
+            assert_eq!(first.0, strings(&["A?", "C?", "F?", "E?", "D?", "B?"]));
+            assert_eq!(first.1, strings(&["2", "1"]));
+            assert_eq!(first, play(42, "second")?);
+            assert_eq!(play(43, "third")?.0, strings(&["E?", "D?", "F?", "B?", "A?", "C?"]));
+
+            Ok(())
+        })
+    }
 }
\ No newline at end of file
@@ -0,0 +1,559 @@
+use {
+    serde::{Serialize, Deserialize},
+    once_cell::sync::Lazy,
+    std::{
+        cmp::Ordering,
+        collections::HashMap
+    },
+    rocket_contrib::templates::Template,
+    rocket::{
+        Request,
+        Outcome,
+        http::{RawStr, Cookie, Cookies},
+        request::{self, FromRequest, FromFormValue}
+    }
+};
+
+const LOCALE_COOKIE: &str = "locale";
+
+pub type Catalog = HashMap<&'static str, &'static str>;
+
+static DE: Lazy<Catalog> = Lazy::new(|| DE_MESSAGES.iter().copied().collect());
+static EN: Lazy<Catalog> = Lazy::new(|| EN_MESSAGES.iter().copied().collect());
+
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Locale {
+    #[serde(rename = "de")]
+    De,
+    #[serde(rename = "en")]
+    En
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Locale::De
+    }
+}
+
+impl Locale {
+    pub const ALL: [Locale; 2] = [Locale::De, Locale::En];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            Locale::De => "de",
+            Locale::En => "en"
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Locale::De => "Deutsch",
+            Locale::En => "English"
+        }
+    }
+
+    pub fn from_code(code: &str) -> Option<Locale> {
+        Locale::ALL
+            .iter()
+            .copied()
+            .find(|locale| code.eq_ignore_ascii_case(locale.code()))
+    }
+
+    pub fn catalog(self) -> &'static Catalog {
+        match self {
+            Locale::De => &DE,
+            Locale::En => &EN
+        }
+    }
+
+    // languages without a quality value have 1, and languages with the
+    // same quality keep the order they were listed in
+    fn from_accept_language(header: &str) -> Option<Locale> {
+        let mut langs = header
+            .split(',')
+            .filter_map(|lang| {
+                let mut parts = lang.split(';');
+                let code = parts.next()?.trim().split('-').next()?;
+                let quality = parts
+                    .find_map(|param| {
+                        let mut pair = param.trim().splitn(2, '=');
+                        match (pair.next(), pair.next()) {
+                            (Some("q"), Some(q)) => q.trim().parse::<f32>().ok(),
+                            _ => None
+                        }
+                    })
+                    .unwrap_or(1.0);
+                Some((code, quality))
+            })
+            .filter(|&(_, quality)| quality > 0.0)
+            .collect::<Vec<_>>();
+        langs.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+        langs
+            .into_iter()
+            .find_map(|(code, _)| Locale::from_code(code))
+    }
+
+    pub fn remember(self, cookies: &mut Cookies) {
+        cookies.add(Cookie::new(LOCALE_COOKIE, self.code()))
+    }
+}
+
+impl <'a, 'r> FromRequest<'a, 'r> for Locale {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let from_cookie = request
+            .cookies()
+            .get(LOCALE_COOKIE)
+            .and_then(|cookie| Locale::from_code(cookie.value()));
+
+        Outcome::Success(from_cookie
+            .or_else(|| request
+                .headers()
+                .get_one("Accept-Language")
+                .and_then(Locale::from_accept_language)
+            )
+            .unwrap_or_default()
+        )
+    }
+}
+
+impl <'v> FromFormValue<'v> for Locale {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        Locale::from_code(form_value.as_str())
+            .ok_or(form_value)
+    }
+}
+
+#[derive(Serialize)]
+pub struct AvailableLocale {
+    code: &'static str,
+    name: &'static str,
+    selected: bool
+}
+
+pub fn available(current: Locale) -> Vec<AvailableLocale> {
+    Locale::ALL
+        .iter()
+        .map(|&locale| AvailableLocale {
+            code: locale.code(),
+            name: locale.name(),
+            selected: locale == current
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+struct Localized<C> {
+    #[serde(flatten)]
+    context: C,
+    locale: &'static str,
+    t: &'static Catalog
+}
+
+pub fn render<C: Serialize>(name: &'static str, locale: Locale, context: C) -> Template {
+    Template::render(name, Localized {
+        context,
+        locale: locale.code(),
+        t: locale.catalog()
+    })
+}
+
+const DE_MESSAGES: &[(&str, &str)] = &[
+    ("start", "Start"),
+    ("play", "Spielen"),
+    ("welcome", "Willkommen"),
+    ("confirm", "Bestätigen"),
+    ("admin_login", "Admin-Login"),
+    ("language", "Sprache"),
+    ("points", "Punkte"),
+    ("weighted_points", "Gewichtete Punkte"),
+    ("user", "User"),
+    ("played_on", "Gespielt am"),
+    ("duration", "Spieldauer"),
+    ("categories", "Kategorien"),
+    ("use_joker", "Joker einsetzen"),
+    ("joker", "Joker"),
+    ("elapsed_time", "Vergangene Zeit"),
+    ("ratio_prefix", "Diese Frage wird zu"),
+    ("ratio_suffix", "% richtig beantwortet."),
+    ("quit", "Beenden"),
+    ("no_questions_left", "Es sind keine Fragen mehr in dieser Kategorie übrig. Du kannst entweder mehr Kategorien auswählen oder das Spiel beenden."),
+    ("current_standing", "Aktueller Stand:"),
+    ("continue_playing", "Weiterspielen"),
+    ("end_game", "Spiel beenden"),
+    ("end", "Ende"),
+    ("placement_prefix", "Du bist auf Platz #"),
+    ("placement_suffix", " gelandet."),
+    ("top_three", "Die Top-3:"),
+    ("neighbours", "Deine Nachbarn:"),
+    ("leaderboard", "Bestenliste"),
+    ("rank", "Platz"),
+    ("range_day", "Heute"),
+    ("range_week", "Diese Woche"),
+    ("range_month", "Diesen Monat"),
+    ("range_all", "Insgesamt"),
+    ("sort_by", "Sortieren nach"),
+    ("previous_page", "Zurück"),
+    ("next_page", "Weiter"),
+    ("page", "Seite"),
+    ("find_player", "Spieler suchen"),
+    ("set_exact", "Genau diese Kategorien"),
+    ("set_contains", "Mindestens diese Kategorien"),
+    ("set_placement_prefix", "Mit genau diesen Kategorien bist du auf Platz #"),
+    ("set_placement_suffix", "."),
+    ("best_rank", "Bester Platz von"),
+    ("player_not_found", "Keine Ergebnisse in diesem Zeitraum für"),
+    ("no_scores", "Noch keine Ergebnisse in diesem Zeitraum."),
+    ("wrong_answer", "Falsch geantwortet"),
+    ("failed_prefix", "Das war die falsche Antwort. Du hättest"),
+    ("failed_infix", "Punkte erreicht, was"),
+    ("failed_suffix", "gewichtete Punkte gewesen wären."),
+    ("back_to_start", "Zurück zum Start"),
+    ("add_admin", "Admin hinzufügen"),
+    ("register", "Registrieren"),
+    ("add_question", "Frage hinzufügen"),
+    ("question", "Frage"),
+    ("question_text", "Fragetext"),
+    ("correct_answer", "Richtige Antwort"),
+    ("incorrect_answer", "Falsche Antwort"),
+    ("tags_input", "Tags (kommagetrennt)"),
+    ("tags", "Tags"),
+    ("category", "Kategorie"),
+    ("add", "Hinzufügen"),
+    ("add_category", "Kategorie hinzufügen"),
+    ("name", "Name"),
+    ("questions", "Fragen"),
+    ("id", "ID"),
+    ("media", "Medien"),
+    ("edit", "Bearbeiten"),
+    ("delete", "Löschen"),
+    ("status", "Status"),
+    ("status_0", "Entwurf"),
+    ("status_1", "In Prüfung"),
+    ("status_2", "Veröffentlicht"),
+    ("status_3", "Ausgemustert"),
+    ("submit", "Zur Prüfung einreichen"),
+    ("approve", "Freigeben"),
+    ("reject", "Ablehnen"),
+    ("retire", "Ausmustern"),
+    ("reopen", "Wieder aufnehmen"),
+    ("trash", "Papierkorb"),
+    ("restore", "Wiederherstellen"),
+    ("purge", "Endgültig löschen"),
+    ("purge_answered", "Beantwortete Fragen bleiben im Papierkorb"),
+    ("edit_duplicate", "Eine andere Frage hat schon diesen Text"),
+    ("report_question", "Fehler in dieser Frage melden"),
+    ("report_reason_0", "Die richtige Antwort ist falsch"),
+    ("report_reason_1", "Tippfehler"),
+    ("report_reason_2", "Die Frage ist unklar"),
+    ("report_reason_3", "Anstößiger Inhalt"),
+    ("report_reason_4", "Sonstiges"),
+    ("report_comment", "Kommentar (optional)"),
+    ("report_send", "Melden"),
+    ("report_sent", "Danke für deine Meldung!"),
+    ("report_limited", "Du hast in letzter Zeit zu viele Meldungen geschickt. Bitte versuche es später erneut."),
+    ("reports", "Gemeldete Fragen"),
+    ("report_count", "Meldungen"),
+    ("resolve", "Erledigt"),
+    ("analytics", "Auswertung"),
+    ("answers", "Antworten"),
+    ("correct_rate", "Richtig beantwortet"),
+    ("picks", "Gewählt (richtig / falsch 1-3)"),
+    ("avg_time", "Durchschnittliche Antwortzeit"),
+    ("joker_uses", "Joker eingesetzt"),
+    ("flags", "Auffälligkeiten"),
+    ("flag_always_correct", "Immer richtig beantwortet"),
+    ("flag_never_correct", "Nie richtig beantwortet"),
+    ("flag_unpicked_distractor", "Nie gewählte falsche Antwort"),
+    ("duplicates", "Mögliche Duplikate"),
+    ("cluster", "Gruppe"),
+    ("api_tokens", "API-Tokens"),
+    ("scopes", "Berechtigungen"),
+    ("scope_read", "Lesen"),
+    ("scope_write", "Schreiben"),
+    ("last_used", "Zuletzt verwendet"),
+    ("revoke", "Widerrufen"),
+    ("token_revoked", "widerrufen"),
+    ("token_created", "Neuer Token, er wird nur dieses eine Mal angezeigt:"),
+    ("search", "Suchen"),
+    ("any", "Alle"),
+    ("min_correct_rate", "Mindestens % richtig"),
+    ("max_correct_rate", "Höchstens % richtig"),
+    ("created_at", "Erstellt"),
+    ("descending", "Absteigend"),
+    ("duplicate_rejected", "Die Frage wurde nicht hinzugefügt, da es sie schon gibt:"),
+    ("similar_added", "Die Frage wurde hinzugefügt, ähnelt aber stark dieser Frage:"),
+    ("name_in_use", "Dieser Name wird schon verwendet. Bitte wähle einen anderen."),
+    ("registration_failed", "Bei der Registrierung ist ein Fehler aufgetreten. Bitte versuche es später erneut."),
+    ("player_account", "Spielerkonto"),
+    ("password", "Passwort"),
+    ("log_in", "Einloggen"),
+    ("log_out", "Ausloggen"),
+    ("wrong_credentials", "Name oder Passwort sind falsch."),
+    ("profile", "Profil"),
+    ("games_played", "Gespielte Spiele"),
+    ("best_scores", "Beste Ergebnisse"),
+    ("favourite_categories", "Lieblingskategorien"),
+    ("times_picked", "Gewählt"),
+    ("accuracy", "Trefferquote"),
+    ("correct_answers", "Richtig"),
+    ("practice_mode", "Übungsmodus (ohne Bestenliste)"),
+    ("correct_answer_was", "Die richtige Antwort wäre gewesen:"),
+    ("practice_again_soon", "Diese Frage kommt bald wieder."),
+    ("practice_progress", "Dein Lernfortschritt"),
+    ("leitner_box", "Fach"),
+    ("daily_challenge", "Tägliche Herausforderung"),
+    ("daily_explanation", "Jeden Tag bekommen alle dieselben Fragen. Du hast einen Versuch."),
+    ("daily_already_played", "Du hast die heutige Herausforderung schon gespielt. Morgen gibt es eine neue."),
+    ("daily_leaderboard", "Bestenliste von heute"),
+    ("daily_unavailable", "Heute gibt es leider keine Fragen für die Herausforderung."),
+    ("daily_done", "Du hast alle Fragen der heutigen Herausforderung beantwortet!"),
+    ("rooms", "Quizraum"),
+    ("join_room", "Raum beitreten"),
+    ("create_room", "Raum erstellen"),
+    ("room_code", "Raumcode"),
+    ("room_lobby", "Warte auf den Start..."),
+    ("room_scoreboard", "Zwischenstand"),
+    ("room_finished", "Das Quiz ist vorbei!"),
+    ("room_next", "Weiter"),
+    ("remaining_time", "Verbleibende Zeit"),
+    ("answered", "Geantwortet"),
+    ("teams", "Teamspiel"),
+    ("join_team", "Team beitreten"),
+    ("create_team", "Team erstellen"),
+    ("team_name", "Teamname"),
+    ("members", "Mitglieder"),
+    ("voting_first", "Die erste Antwort zählt"),
+    ("voting_majority", "Die Mehrheit entscheidet"),
+    ("votes", "Stimmen"),
+    ("waiting_for_team", "Warte auf den Rest des Teams..."),
+    ("team_leaderboard", "Team-Bestenliste"),
+    ("duel_mode", "Danach jemanden zum Duell herausfordern"),
+    ("duel", "Duell"),
+    ("duel_link", "Zum Duell"),
+    ("duel_share", "Wer diesen Link hat, kann die Herausforderung einmal annehmen und dieselben Fragen spielen."),
+    ("duel_challenge_by", "Herausforderung von"),
+    ("duel_questions", "Fragen"),
+    ("duel_accept", "Herausforderung annehmen"),
+    ("duel_in_progress", "Die Herausforderung wird gerade gespielt."),
+    ("duel_done", "Du hast alle Fragen der Herausforderung beantwortet!"),
+    ("total_time", "Gesamtzeit")
+];
+
+const EN_MESSAGES: &[(&str, &str)] = &[
+    ("start", "Start"),
+    ("play", "Play"),
+    ("welcome", "Welcome"),
+    ("confirm", "Confirm"),
+    ("admin_login", "Admin login"),
+    ("language", "Language"),
+    ("points", "Points"),
+    ("weighted_points", "Weighted points"),
+    ("user", "User"),
+    ("played_on", "Played on"),
+    ("duration", "Duration"),
+    ("categories", "Categories"),
+    ("use_joker", "Use joker"),
+    ("joker", "Joker"),
+    ("elapsed_time", "Elapsed time"),
+    ("ratio_prefix", "This question is answered correctly"),
+    ("ratio_suffix", "% of the time."),
+    ("quit", "Quit"),
+    ("no_questions_left", "There are no questions left in this category. You can either select more categories or end the game."),
+    ("current_standing", "Current standing:"),
+    ("continue_playing", "Continue playing"),
+    ("end_game", "End game"),
+    ("end", "End"),
+    ("placement_prefix", "You placed #"),
+    ("placement_suffix", "."),
+    ("top_three", "The top 3:"),
+    ("neighbours", "Your neighbours:"),
+    ("leaderboard", "Leaderboard"),
+    ("rank", "Rank"),
+    ("range_day", "Today"),
+    ("range_week", "This week"),
+    ("range_month", "This month"),
+    ("range_all", "All time"),
+    ("sort_by", "Sort by"),
+    ("previous_page", "Previous"),
+    ("next_page", "Next"),
+    ("page", "Page"),
+    ("find_player", "Find player"),
+    ("set_exact", "Exactly these categories"),
+    ("set_contains", "At least these categories"),
+    ("set_placement_prefix", "Among games with exactly these categories you placed #"),
+    ("set_placement_suffix", "."),
+    ("best_rank", "Best rank of"),
+    ("player_not_found", "No scores in this time range for"),
+    ("no_scores", "No scores in this time range yet."),
+    ("wrong_answer", "Wrong answer"),
+    ("failed_prefix", "That was the wrong answer. You would have reached"),
+    ("failed_infix", "points, which would have been"),
+    ("failed_suffix", "weighted points."),
+    ("back_to_start", "Back to start"),
+    ("add_admin", "Add admin"),
+    ("register", "Register"),
+    ("add_question", "Add question"),
+    ("question", "Question"),
+    ("question_text", "Question text"),
+    ("correct_answer", "Correct answer"),
+    ("incorrect_answer", "Incorrect answer"),
+    ("tags_input", "Tags (comma separated)"),
+    ("tags", "Tags"),
+    ("category", "Category"),
+    ("add", "Add"),
+    ("add_category", "Add category"),
+    ("name", "Name"),
+    ("questions", "Questions"),
+    ("id", "ID"),
+    ("media", "Media"),
+    ("edit", "Edit"),
+    ("delete", "Delete"),
+    ("status", "Status"),
+    ("status_0", "Draft"),
+    ("status_1", "In review"),
+    ("status_2", "Published"),
+    ("status_3", "Retired"),
+    ("submit", "Submit for review"),
+    ("approve", "Approve"),
+    ("reject", "Reject"),
+    ("retire", "Retire"),
+    ("reopen", "Reopen"),
+    ("trash", "Trash"),
+    ("restore", "Restore"),
+    ("purge", "Delete permanently"),
+    ("purge_answered", "Answered questions stay in the trash"),
+    ("edit_duplicate", "Another question already has this text"),
+    ("report_question", "Report a problem with this question"),
+    ("report_reason_0", "The correct answer is wrong"),
+    ("report_reason_1", "Typo"),
+    ("report_reason_2", "The question is unclear"),
+    ("report_reason_3", "Offensive content"),
+    ("report_reason_4", "Other"),
+    ("report_comment", "Comment (optional)"),
+    ("report_send", "Report"),
+    ("report_sent", "Thanks for your report!"),
+    ("report_limited", "You have sent too many reports lately. Please try again later."),
+    ("reports", "Reported questions"),
+    ("report_count", "Reports"),
+    ("resolve", "Resolve"),
+    ("analytics", "Analytics"),
+    ("answers", "Answers"),
+    ("correct_rate", "Answered correctly"),
+    ("picks", "Picked (correct / incorrect 1-3)"),
+    ("avg_time", "Average time to answer"),
+    ("joker_uses", "Jokers used"),
+    ("flags", "Flags"),
+    ("flag_always_correct", "Always answered correctly"),
+    ("flag_never_correct", "Never answered correctly"),
+    ("flag_unpicked_distractor", "Incorrect answer nobody picks"),
+    ("duplicates", "Likely duplicates"),
+    ("cluster", "Cluster"),
+    ("api_tokens", "API tokens"),
+    ("scopes", "Scopes"),
+    ("scope_read", "Read"),
+    ("scope_write", "Write"),
+    ("last_used", "Last used"),
+    ("revoke", "Revoke"),
+    ("token_revoked", "revoked"),
+    ("token_created", "New token, it is only shown this once:"),
+    ("search", "Search"),
+    ("any", "Any"),
+    ("min_correct_rate", "At least % correct"),
+    ("max_correct_rate", "At most % correct"),
+    ("created_at", "Created"),
+    ("descending", "Descending"),
+    ("duplicate_rejected", "The question was not added because it already exists:"),
+    ("similar_added", "The question was added, but it's very similar to this question:"),
+    ("name_in_use", "This name is already in use. Please choose another one."),
+    ("registration_failed", "An error occurred during registration. Please try again later."),
+    ("player_account", "Player account"),
+    ("password", "Password"),
+    ("log_in", "Log in"),
+    ("log_out", "Log out"),
+    ("wrong_credentials", "Wrong name or password."),
+    ("profile", "Profile"),
+    ("games_played", "Games played"),
+    ("best_scores", "Best scores"),
+    ("favourite_categories", "Favourite categories"),
+    ("times_picked", "Picked"),
+    ("accuracy", "Accuracy"),
+    ("correct_answers", "Correct"),
+    ("practice_mode", "Practice mode (no leaderboard)"),
+    ("correct_answer_was", "The correct answer would have been:"),
+    ("practice_again_soon", "This question will come back soon."),
+    ("practice_progress", "Your learning progress"),
+    ("leitner_box", "Box"),
+    ("daily_challenge", "Daily challenge"),
+    ("daily_explanation", "Everyone gets the same questions every day. You have one attempt."),
+    ("daily_already_played", "You have already played today's challenge. There will be a new one tomorrow."),
+    ("daily_leaderboard", "Today's leaderboard"),
+    ("daily_unavailable", "Unfortunately there are no questions for today's challenge."),
+    ("daily_done", "You answered all of today's questions!"),
+    ("rooms", "Quiz room"),
+    ("join_room", "Join room"),
+    ("create_room", "Create room"),
+    ("room_code", "Room code"),
+    ("room_lobby", "Waiting for the start..."),
+    ("room_scoreboard", "Scoreboard"),
+    ("room_finished", "The quiz is over!"),
+    ("room_next", "Next"),
+    ("remaining_time", "Time remaining"),
+    ("answered", "Answered"),
+    ("teams", "Team play"),
+    ("join_team", "Join team"),
+    ("create_team", "Create team"),
+    ("team_name", "Team name"),
+    ("members", "Members"),
+    ("voting_first", "The first answer counts"),
+    ("voting_majority", "The majority decides"),
+    ("votes", "Votes"),
+    ("waiting_for_team", "Waiting for the rest of the team..."),
+    ("team_leaderboard", "Team leaderboard"),
+    ("duel_mode", "Challenge someone to a duel afterwards"),
+    ("duel", "Duel"),
+    ("duel_link", "Go to the duel"),
+    ("duel_share", "Anyone with this link can accept the challenge once and play the same questions."),
+    ("duel_challenge_by", "Challenge by"),
+    ("duel_questions", "Questions"),
+    ("duel_accept", "Accept the challenge"),
+    ("duel_in_progress", "The challenge is being played right now."),
+    ("duel_done", "You answered all of the challenge's questions!"),
+    ("total_time", "Total time")
+];
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn catalogs_have_same_keys() {
+        let mut de = DE.keys().collect::<Vec<_>>();
+        let mut en = EN.keys().collect::<Vec<_>>();
+        de.sort_unstable();
+        en.sort_unstable();
+
+        assert_eq!(de, en)
+    }
+
+    #[test]
+    fn accept_language() {
+        assert_eq!(
+            Locale::from_accept_language("fr-CH, fr;q=0.9, en;q=0.8, de;q=0.7"),
+            Some(Locale::En)
+        );
+        assert_eq!(
+            Locale::from_accept_language("en;q=0.1, de;q=0.9"),
+            Some(Locale::De)
+        );
+        assert_eq!(
+            Locale::from_accept_language("de;q=0, en"),
+            Some(Locale::En)
+        );
+        assert_eq!(Locale::from_accept_language("it"), None)
+    }
+}
@@ -0,0 +1,220 @@
+use {
+    uuid::Uuid,
+    std::{
+        fs,
+        io::{self, Read, Write},
+        path::PathBuf
+    },
+    rocket::{
+        Data,
+        fairing::{Fairing, AdHoc},
+        http::ContentType
+    },
+    crate::models::db::{
+        Connection,
+        QuestionId,
+        models::{QuestionMedia, NewQuestionMedia}
+    }
+};
+
+const DEFAULT_DIR: &str = "content/media";
+const MAX_SIZE: u64 = 10 * 1024 * 1024;
+// 0 is the correct answer, 1 to 3 are the incorrect ones
+const MAX_ANSWER: i16 = 3;
+
+#[derive(Debug)]
+pub struct MediaDir(PathBuf);
+
+impl MediaDir {
+    pub fn path(&self, media: &QuestionMedia) -> PathBuf {
+        self.0.join(&media.file_name)
+    }
+}
+
+pub fn fairing() -> impl Fairing {
+    AdHoc::on_attach("Media directory", |rocket| {
+        let dir: PathBuf = rocket
+            .config()
+            .get_str("media_dir")
+            .unwrap_or(DEFAULT_DIR)
+            .into();
+
+        match fs::create_dir_all(&dir) {
+            Ok(()) => Ok(rocket.manage(MediaDir(dir))),
+            Err(_) => Err(rocket)
+        }
+    })
+}
+
+/// The only kinds of files that can be attached. Anything that browsers
+/// might run, like SVG or HTML, must never be served from our own origin.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaType {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Mpeg,
+    Ogg,
+    Wav
+}
+
+// enough for the longest signature, "RIFF", the size and "WEBP" or "WAVE"
+const SIGNATURE_LEN: u64 = 12;
+
+impl MediaType {
+    pub const ALL: [MediaType; 7] = [
+        MediaType::Png,
+        MediaType::Jpeg,
+        MediaType::Gif,
+        MediaType::Webp,
+        MediaType::Mpeg,
+        MediaType::Ogg,
+        MediaType::Wav
+    ];
+
+    pub fn content_type(self) -> ContentType {
+        match self {
+            MediaType::Png => ContentType::PNG,
+            MediaType::Jpeg => ContentType::JPEG,
+            MediaType::Gif => ContentType::GIF,
+            MediaType::Webp => ContentType::WEBP,
+            MediaType::Mpeg => ContentType::new("audio", "mpeg"),
+            MediaType::Ogg => ContentType::new("audio", "ogg"),
+            MediaType::Wav => ContentType::WAV
+        }
+    }
+
+    /// Tells the type from the first bytes of a file, whatever the client claims it is.
+    pub fn sniff(head: &[u8]) -> Option<MediaType> {
+        let riff = |kind: &[u8]| head.starts_with(b"RIFF") && head.get(8..12) == Some(kind);
+
+        if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(MediaType::Png)
+        } else if head.starts_with(&[0xff, 0xd8, 0xff]) {
+            Some(MediaType::Jpeg)
+        } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+            Some(MediaType::Gif)
+        } else if riff(b"WEBP") {
+            Some(MediaType::Webp)
+        } else if riff(b"WAVE") {
+            Some(MediaType::Wav)
+        } else if head.starts_with(b"OggS") {
+            Some(MediaType::Ogg)
+        } else if head.starts_with(b"ID3") || is_mpeg_frame(head) {
+            Some(MediaType::Mpeg)
+        } else {
+            None
+        }
+    }
+
+    /// The type media stored earlier gets served with, if it's still allowed.
+    pub fn of_stored(content_type: &str) -> Option<MediaType> {
+        let parsed = ContentType::parse_flexible(content_type)?;
+        MediaType::ALL
+            .iter()
+            .copied()
+            .find(|media_type| media_type.content_type() == parsed)
+    }
+}
+
+// untagged mp3 files start right away with the sync bits of a frame header
+fn is_mpeg_frame(head: &[u8]) -> bool {
+    head.len() > 1 && head[0] == 0xff && head[1] & 0xe0 == 0xe0
+}
+
+#[derive(Debug)]
+pub enum MediaError {
+    UnsupportedType,
+    InvalidAnswer,
+    TooLarge,
+    Io(io::Error),
+    Query(diesel::result::Error)
+}
+
+impl From<io::Error> for MediaError {
+    fn from(e: io::Error) -> Self {
+        MediaError::Io(e)
+    }
+}
+
+/// Stores an upload with the type its content has, the type the client sent is ignored.
+pub fn store(
+    question: QuestionId,
+    answer: Option<i16>,
+    data: Data,
+    dir: &MediaDir,
+    conn: &Connection
+) -> Result<QuestionMedia, MediaError> {
+    if answer.map_or(false, |ans| ans < 0 || ans > MAX_ANSWER) {
+        return Err(MediaError::InvalidAnswer)
+    }
+
+    let mut stream = data.open();
+    let mut head = Vec::new();
+    stream
+        .by_ref()
+        .take(SIGNATURE_LEN)
+        .read_to_end(&mut head)?;
+    let media_type = MediaType::sniff(&head)
+        .ok_or(MediaError::UnsupportedType)?;
+
+    let file_name = Uuid::new_v4().to_string();
+    let path = dir.0.join(&file_name);
+    let mut file = fs::File::create(&path)?;
+    file.write_all(&head)?;
+
+    // read one byte more than allowed so we can tell if the upload was cut off
+    let written = head.len() as u64 + io::copy(
+        &mut stream.take(MAX_SIZE + 1 - head.len() as u64),
+        &mut file
+    )?;
+
+    if written > MAX_SIZE {
+        fs::remove_file(&path).ok();
+        return Err(MediaError::TooLarge)
+    }
+
+    QuestionMedia::insert(
+        &NewQuestionMedia {
+            question_id: *question,
+            answer,
+            file_name: &file_name,
+            content_type: &media_type.content_type().to_string()
+        },
+        conn
+    ).map_err(|e| {
+        fs::remove_file(&path).ok();
+        MediaError::Query(e)
+    })
+}
+
+pub fn remove(media: &QuestionMedia, dir: &MediaDir) -> io::Result<()> {
+    match fs::remove_file(dir.path(media)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(())
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn types_come_from_the_content() {
+        assert_eq!(MediaType::sniff(b"\x89PNG\r\n\x1a\n\0\0\0\x0d"), Some(MediaType::Png));
+        assert_eq!(MediaType::sniff(b"RIFF\0\0\0\0WAVEfmt "), Some(MediaType::Wav));
+        assert_eq!(MediaType::sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some(MediaType::Webp));
+        assert_eq!(MediaType::sniff(b"ID3\x04\0\0"), Some(MediaType::Mpeg));
+        assert_eq!(MediaType::sniff(b"<svg xmlns="), None);
+        assert_eq!(MediaType::sniff(b"<!DOCTYPE html>"), None);
+    }
+
+    #[test]
+    fn only_allowed_types_are_served_as_stored() {
+        assert_eq!(MediaType::of_stored("image/png"), Some(MediaType::Png));
+        assert_eq!(MediaType::of_stored("audio/ogg"), Some(MediaType::Ogg));
+        assert_eq!(MediaType::of_stored("image/svg+xml"), None);
+    }
+}
\ No newline at end of file
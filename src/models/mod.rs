@@ -2,5 +2,14 @@ pub mod db;
 pub mod web;
 pub mod game;
 pub mod account;
+pub mod analytics;
+pub mod daily;
+pub mod duels;
+pub mod media;
+pub mod practice;
+pub mod rooms;
+pub mod teams;
+pub mod i18n;
+pub mod similarity;
 pub mod stopwatch;
 pub mod tera;
\ No newline at end of file
@@ -0,0 +1,93 @@
+use {
+    serde::Serialize,
+    diesel::QueryResult,
+    std::time::{Duration, SystemTime},
+    crate::models::db::{
+        Connection,
+        PlayerId,
+        QuestionId,
+        models::{ReviewEntry, NewReviewEntry}
+    }
+};
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// How long a question stays in each box before it's due again.
+/// New and missed questions start in the first box.
+const INTERVALS: [Duration; 5] = [
+    Duration::from_secs(DAY),
+    Duration::from_secs(2 * DAY),
+    Duration::from_secs(4 * DAY),
+    Duration::from_secs(8 * DAY),
+    Duration::from_secs(16 * DAY)
+];
+
+/// The box a question moves to: one up if it was answered correctly,
+/// back to the first one if not.
+pub fn next_box(current: Option<i16>, correct: bool) -> i16 {
+    match (current, correct) {
+        (_, false) | (None, true) => 0,
+        (Some(current), true) => (current + 1).min(INTERVALS.len() as i16 - 1)
+    }
+}
+
+pub fn interval(leitner_box: i16) -> Duration {
+    INTERVALS[(leitner_box.max(0) as usize).min(INTERVALS.len() - 1)]
+}
+
+pub fn review(player: PlayerId, question: QuestionId, correct: bool, conn: &Connection) -> QueryResult<ReviewEntry> {
+    let current = ReviewEntry::of(player, question, conn)?
+        .map(|entry| entry.leitner_box);
+    let leitner_box = next_box(current, correct);
+    let now = SystemTime::now();
+
+    ReviewEntry::save(
+        &NewReviewEntry {
+            player_id: *player,
+            question_id: *question,
+            leitner_box,
+            due_at: now + interval(leitner_box),
+            reviewed_at: now
+        },
+        conn
+    )
+}
+
+#[derive(Serialize, Debug)]
+pub struct BoxCount {
+    /// Starting at 1 for display.
+    leitner_box: i16,
+    questions: i64
+}
+
+/// Every box with the number of questions in it, including empty ones.
+pub fn progress(player: PlayerId, conn: &Connection) -> QueryResult<Vec<BoxCount>> {
+    let counts = ReviewEntry::box_counts(player, conn)?;
+
+    Ok((0..INTERVALS.len() as i16)
+        .map(|leitner_box| BoxCount {
+            leitner_box: leitner_box + 1,
+            questions: counts
+                .iter()
+                .find(|&&(b, _)| b == leitner_box)
+                .map_or(0, |&(_, count)| count)
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn questions_move_through_the_boxes() {
+        assert_eq!(next_box(None, true), 0);
+        assert_eq!(next_box(None, false), 0);
+        assert_eq!(next_box(Some(0), true), 1);
+        assert_eq!(next_box(Some(3), true), 4);
+        assert_eq!(next_box(Some(4), true), 4);
+        assert_eq!(next_box(Some(3), false), 0);
+        assert!(interval(0) < interval(1));
+        assert_eq!(interval(10), interval(4))
+    }
+}
@@ -0,0 +1,467 @@
+use {
+    serde::Serialize,
+    diesel::QueryResult,
+    std::{
+        hash::Hash,
+        sync::{Arc, Mutex, TryLockError},
+        collections::{HashMap, VecDeque},
+        time::{Duration, Instant}
+    },
+    rand::{
+        Rng,
+        SeedableRng,
+        thread_rng,
+        rngs::StdRng,
+        seq::SliceRandom
+    },
+    rocket::{
+        http::RawStr,
+        request::FromParam
+    },
+    crate::models::{
+        i18n::Locale,
+        web::{Session, Broadcaster, event},
+        db::{
+            Connection,
+            models::{Category, Question}
+        }
+    }
+};
+
+pub const MAX_ROUNDS: usize = 10;
+pub const ANSWER_WINDOW: Duration = Duration::from_secs(20);
+// rooms nobody finished are cleaned up after this long
+pub const MAX_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+const POINTS: i32 = 30;
+// answering instantly gives this much on top, answering at the end of the window nothing
+const SPEED_BONUS: i32 = 20;
+const CODE_LEN: usize = 6;
+// no 0/O or 1/I, the code gets read out loud and typed on phones
+const CODE_CHARS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RoomCode(String);
+
+impl RoomCode {
+    pub fn generate<R: Rng>(rng: &mut R) -> RoomCode {
+        RoomCode((0..CODE_LEN)
+            .map(|_| *CODE_CHARS.choose(rng).unwrap() as char)
+            .collect()
+        )
+    }
+
+    /// Codes are case insensitive, since phones like to capitalise the first letter only.
+    pub fn parse(code: &str) -> Option<RoomCode> {
+        let code = code.trim().to_uppercase();
+        if code.len() == CODE_LEN && code.bytes().all(|b| CODE_CHARS.contains(&b)) {
+            Some(RoomCode(code))
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl <'r> FromParam<'r> for RoomCode {
+    type Error = &'r RawStr;
+
+    fn from_param(param: &'r RawStr) -> Result<Self, Self::Error> {
+        RoomCode::parse(param.as_str())
+            .ok_or(param)
+    }
+}
+
+/// Rooms and teams are both kept under a code until they expire.
+pub trait Expiring {
+    fn is_expired(&self, now: Instant) -> bool;
+}
+
+// one that's locked right now is clearly still in use
+impl <V: Expiring> Expiring for Arc<Mutex<V>> {
+    fn is_expired(&self, now: Instant) -> bool {
+        match self.try_lock() {
+            Ok(value) => value.is_expired(now),
+            Err(TryLockError::WouldBlock) => false,
+            Err(TryLockError::Poisoned(_)) => true
+        }
+    }
+}
+
+/// Forgets everything that expired and keeps the value under a code nothing else has.
+pub fn insert_with_new_code<V: Expiring>(by_code: &mut HashMap<RoomCode, V>, value: V, now: Instant) -> RoomCode {
+    by_code.retain(|_, kept| !kept.is_expired(now));
+
+    let mut rng = thread_rng();
+    loop {
+        let code = RoomCode::generate(&mut rng);
+        if !by_code.contains_key(&code) {
+            by_code.insert(code.clone(), value);
+            return code
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Lobby,
+    Question,
+    Scoreboard,
+    Finished
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RoomError {
+    NotHost,
+    NotInRoom,
+    NameTaken,
+    AlreadyJoined,
+    WrongPhase,
+    TooLate,
+    AlreadyAnswered
+}
+
+#[derive(Debug)]
+struct RoomPlayer {
+    name: String,
+    points: i32
+}
+
+#[derive(Debug)]
+struct Round {
+    question: Question,
+    answers: Vec<String>,
+    started: Instant,
+    // by player index, whether they were right and how long they took
+    responses: HashMap<usize, (bool, Duration)>
+}
+
+/// A quiz everyone in a room plays at the same time, with the host moving it along.
+/// Members are told apart by `K`, which is the session in practice.
+#[derive(Debug)]
+pub struct Room<K = Session> {
+    host: K,
+    players: Vec<RoomPlayer>,
+    members: HashMap<K, usize>,
+    questions: VecDeque<Question>,
+    round: Option<Round>,
+    rounds: usize,
+    played: usize,
+    phase: Phase,
+    rng: StdRng,
+    created: Instant,
+    pub events: Broadcaster<K>
+}
+
+#[derive(Serialize, Debug)]
+pub struct QuestionView {
+    string: String,
+    answers: Vec<String>,
+    remaining_secs: u64,
+    answered: usize
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct Standing {
+    name: String,
+    points: i32
+}
+
+#[derive(Serialize)]
+struct QuestionStarted<'a> {
+    round: usize,
+    rounds: usize,
+    string: &'a str,
+    answers: &'a [String],
+    remaining_secs: u64
+}
+
+#[derive(Serialize)]
+struct AnswerRevealed<'a> {
+    correct: &'a str,
+    answered: usize,
+    answered_correctly: usize
+}
+
+#[derive(Serialize)]
+struct ScoreboardUpdated {
+    standings: Vec<Standing>
+}
+
+/// What everyone in the room gets to see, so nothing about the correct
+/// answer is in here until the round is over.
+#[derive(Serialize, Debug)]
+pub struct RoomView {
+    phase: Phase,
+    round: usize,
+    rounds: usize,
+    question: Option<QuestionView>,
+    correct: Option<String>,
+    standings: Vec<Standing>
+}
+
+/// Picks the questions for a room from the given categories.
+pub fn load_questions(categories: &[Category], locale: Locale, conn: &Connection) -> QueryResult<Vec<Question>> {
+    let mut questions = Question::load_set(categories, &[], &[], &[], locale, conn)?;
+    questions.shuffle(&mut thread_rng());
+    questions.truncate(MAX_ROUNDS);
+    Ok(questions)
+}
+
+impl <K: Hash + Eq> Room<K> {
+    pub fn new(host: K, questions: Vec<Question>) -> Room<K> {
+        Room {
+            host,
+            players: Vec::new(),
+            members: HashMap::new(),
+            rounds: questions.len(),
+            questions: questions.into(),
+            round: None,
+            played: 0,
+            phase: Phase::Lobby,
+            rng: StdRng::from_rng(thread_rng()).unwrap_or_else(|_| StdRng::seed_from_u64(0)),
+            created: Instant::now(),
+            events: Broadcaster::default()
+        }
+    }
+
+    pub fn is_host(&self, member: &K) -> bool {
+        self.host == *member
+    }
+
+    pub fn is_member(&self, member: &K) -> bool {
+        self.is_host(member) || self.members.contains_key(member)
+    }
+
+    /// Joining is possible until the room is finished, late players just start with 0 points.
+    /// Everyone joins once, so their points stay under the name they joined with.
+    pub fn join(&mut self, member: K, name: String) -> Result<(), RoomError> {
+        if self.phase == Phase::Finished {
+            return Err(RoomError::WrongPhase)
+        }
+        if self.members.contains_key(&member) {
+            return Err(RoomError::AlreadyJoined)
+        }
+        if self.players.iter().any(|player| player.name == name) {
+            return Err(RoomError::NameTaken)
+        }
+
+        self.members.insert(member, self.players.len());
+        self.players.push(RoomPlayer { name, points: 0 });
+        self.broadcast();
+        self.send_standings();
+        Ok(())
+    }
+
+    /// Starts the next round or closes the current one, depending on the phase.
+    pub fn advance(&mut self, member: &K, now: Instant) -> Result<Phase, RoomError> {
+        if !self.is_host(member) {
+            return Err(RoomError::NotHost)
+        }
+
+        self.phase = match self.phase {
+            Phase::Lobby | Phase::Scoreboard => self.start_round(now),
+            Phase::Question => self.close_round(),
+            Phase::Finished => return Err(RoomError::WrongPhase)
+        };
+        self.broadcast();
+        Ok(self.phase)
+    }
+
+    fn start_round(&mut self, now: Instant) -> Phase {
+        match self.questions.pop_front() {
+            Some(question) => {
+                let mut answers = question.incorrect.clone();
+                answers.push(question.correct.clone());
+                answers.shuffle(&mut self.rng);
+
+                self.played += 1;
+                self.events.send(event::QUESTION_STARTED, &QuestionStarted {
+                    round: self.played,
+                    rounds: self.rounds,
+                    string: &question.string,
+                    answers: &answers,
+                    remaining_secs: ANSWER_WINDOW.as_secs()
+                });
+                self.events.set_deadline(Some(now + ANSWER_WINDOW));
+                self.round = Some(Round {
+                    question,
+                    answers,
+                    started: now,
+                    responses: HashMap::new()
+                });
+                Phase::Question
+            },
+            None => Phase::Finished
+        }
+    }
+
+    fn close_round(&mut self) -> Phase {
+        self.events.set_deadline(None);
+        if let Some(round) = &self.round {
+            for (&player, &(correct, took)) in &round.responses {
+                if correct {
+                    self.players[player].points += points(took);
+                }
+            }
+
+            self.events.send(event::ANSWER_REVEALED, &AnswerRevealed {
+                correct: &round.question.correct,
+                answered: round.responses.len(),
+                answered_correctly: round.responses
+                    .values()
+                    .filter(|(correct, _)| *correct)
+                    .count()
+            });
+        }
+        self.send_standings();
+
+        Phase::Scoreboard
+    }
+
+    pub fn answer(&mut self, member: &K, answer: &str, now: Instant) -> Result<(), RoomError> {
+        let player = *self.members
+            .get(member)
+            .ok_or(RoomError::NotInRoom)?;
+        let round = match (self.phase, self.round.as_mut()) {
+            (Phase::Question, Some(round)) => round,
+            _ => return Err(RoomError::WrongPhase)
+        };
+
+        let took = now.duration_since(round.started);
+        if took > ANSWER_WINDOW {
+            return Err(RoomError::TooLate)
+        }
+        if round.responses.contains_key(&player) {
+            return Err(RoomError::AlreadyAnswered)
+        }
+
+        round.responses.insert(player, (answer == round.question.correct, took));
+        self.broadcast();
+        Ok(())
+    }
+
+    fn standings(&self) -> Vec<Standing> {
+        let mut standings = self.players
+            .iter()
+            .map(|player| Standing {
+                name: player.name.clone(),
+                points: player.points
+            })
+            .collect::<Vec<_>>();
+        standings.sort_by(|a, b| b.points.cmp(&a.points).then_with(|| a.name.cmp(&b.name)));
+        standings
+    }
+
+    pub fn view(&self, now: Instant) -> RoomView {
+        let standings = self.standings();
+        let round = self.round
+            .as_ref()
+            .filter(|_| self.phase == Phase::Question || self.phase == Phase::Scoreboard);
+
+        RoomView {
+            phase: self.phase,
+            round: self.played,
+            rounds: self.rounds,
+            question: round.map(|round| QuestionView {
+                string: round.question.string.clone(),
+                answers: round.answers.clone(),
+                remaining_secs: ANSWER_WINDOW
+                    .checked_sub(now.duration_since(round.started))
+                    .unwrap_or_default()
+                    .as_secs(),
+                answered: round.responses.len()
+            }),
+            correct: round
+                .filter(|_| self.phase == Phase::Scoreboard)
+                .map(|round| round.question.correct.clone()),
+            standings
+        }
+    }
+
+    fn broadcast(&mut self) {
+        let view = self.view(Instant::now());
+        self.events.send(event::STATE, &view);
+    }
+
+    fn send_standings(&mut self) {
+        let standings = self.standings();
+        self.events.send(event::SCOREBOARD_UPDATED, &ScoreboardUpdated { standings });
+    }
+}
+
+impl <K> Expiring for Room<K> {
+    fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.created) > MAX_AGE
+    }
+}
+
+fn points(took: Duration) -> i32 {
+    let remaining = ANSWER_WINDOW
+        .checked_sub(took)
+        .unwrap_or_default();
+    POINTS + (SPEED_BONUS as u128 * remaining.as_millis() / ANSWER_WINDOW.as_millis()) as i32
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::test::{CONN, insert_questions},
+        diesel::Connection
+    };
+
+    #[test]
+    fn codes_are_case_insensitive() {
+        let code = RoomCode::generate(&mut thread_rng());
+        assert_eq!(RoomCode::parse(&code.as_str().to_lowercase()), Some(code));
+        assert_eq!(RoomCode::parse("ABC"), None);
+        assert_eq!(RoomCode::parse("ABCDE0"), None)
+    }
+
+    #[test]
+    fn rooms_play_through_rounds() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let (_, questions) = insert_questions("Rooms", &["Zero?"], &conn)?;
+
+            let (host, fast, slow, late) = (0, 1, 2, 3);
+            let mut room = Room::new(host, questions);
+            let start = Instant::now();
+
+            assert_eq!(room.join(fast, "Fast".into()), Ok(()));
+            assert_eq!(room.join(slow, "Slow".into()), Ok(()));
+            assert_eq!(room.join(late, "Fast".into()), Err(RoomError::NameTaken));
+            assert_eq!(room.join(slow, "Slower".into()), Err(RoomError::AlreadyJoined));
+            assert_eq!(room.join(late, "Late".into()), Ok(()));
+            assert_eq!(room.advance(&fast, start), Err(RoomError::NotHost));
+            assert_eq!(room.answer(&fast, "0", start), Err(RoomError::WrongPhase));
+
+            assert_eq!(room.advance(&host, start), Ok(Phase::Question));
+            assert!(room.view(start).correct.is_none());
+            assert_eq!(room.answer(&fast, "0", start), Ok(()));
+            assert_eq!(room.answer(&fast, "1", start), Err(RoomError::AlreadyAnswered));
+            assert_eq!(room.answer(&slow, "0", start + ANSWER_WINDOW), Ok(()));
+            assert_eq!(room.answer(&late, "0", start + ANSWER_WINDOW * 2), Err(RoomError::TooLate));
+
+            assert_eq!(room.advance(&host, start), Ok(Phase::Scoreboard));
+            let view = room.view(start);
+            assert_eq!(view.correct, Some("0".to_string()));
+            assert_eq!(view.standings, vec![
+                Standing { name: "Fast".into(), points: POINTS + SPEED_BONUS },
+                Standing { name: "Slow".into(), points: POINTS },
+                Standing { name: "Late".into(), points: 0 }
+            ]);
+
+            assert_eq!(room.advance(&host, start), Ok(Phase::Finished));
+            assert_eq!(room.advance(&host, start), Err(RoomError::WrongPhase));
+
+            Ok(())
+        })
+    }
+}
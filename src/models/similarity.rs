@@ -0,0 +1,200 @@
+use {
+    std::collections::{HashSet, HashMap},
+    crate::models::db::QuestionId
+};
+
+/// Questions at least this similar are considered likely duplicates.
+pub const THRESHOLD: f32 = 0.6;
+
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// works like postgres' pg_trgm: every word is padded with two spaces
+// in front and one behind, then split into groups of three characters
+fn trigrams(normalized: &str) -> HashSet<[char; 3]> {
+    normalized
+        .split(' ')
+        .filter(|word| !word.is_empty())
+        .flat_map(|word| {
+            let padded = "  "
+                .chars()
+                .chain(word.chars())
+                .chain(std::iter::once(' '))
+                .collect::<Vec<_>>();
+            padded
+                .windows(3)
+                .map(|w| [w[0], w[1], w[2]])
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn jaccard(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f32 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.
+    }
+    a.intersection(b).count() as f32 / union as f32
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Duplicates<I = QuestionId> {
+    None,
+    Exact(I),
+    Similar(Vec<I>)
+}
+
+/// Compares a new question text against the texts of all existing questions.
+pub fn check<I: Copy>(text: &str, existing: &[(I, String)]) -> Duplicates<I> {
+    let normalized = normalize(text);
+    let own = trigrams(&normalized);
+
+    if let Some((id, _)) = existing
+        .iter()
+        .find(|(_, other)| normalize(other) == normalized)
+    {
+        return Duplicates::Exact(*id)
+    }
+
+    let mut similar = existing
+        .iter()
+        .map(|(id, other)| (*id, jaccard(&own, &trigrams(&normalize(other)))))
+        .filter(|(_, sim)| *sim >= THRESHOLD)
+        .collect::<Vec<_>>();
+
+    if similar.is_empty() {
+        Duplicates::None
+    } else {
+        similar.sort_by(|(_, a), (_, b)| b
+            .partial_cmp(a)
+            .unwrap_or(std::cmp::Ordering::Equal)
+        );
+        Duplicates::Similar(similar
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+        )
+    }
+}
+
+/// Groups questions into clusters of likely duplicates, ordered by their smallest id.
+/// Questions without any duplicates don't show up at all.
+pub fn clusters<I: Copy + Ord>(texts: &[(I, String)]) -> Vec<Vec<I>> {
+    let grams = texts
+        .iter()
+        .map(|(_, text)| trigrams(&normalize(text)))
+        .collect::<Vec<_>>();
+
+    // simple union-find over the indices of `texts`
+    let mut parents = (0..texts.len()).collect::<Vec<_>>();
+    fn root(parents: &mut [usize], mut i: usize) -> usize {
+        while parents[i] != i {
+            parents[i] = parents[parents[i]];
+            i = parents[i];
+        }
+        i
+    }
+
+    for i in 0..texts.len() {
+        for j in i + 1..texts.len() {
+            if jaccard(&grams[i], &grams[j]) >= THRESHOLD {
+                let (ri, rj) = (root(&mut parents, i), root(&mut parents, j));
+                parents[rj] = ri;
+            }
+        }
+    }
+
+    let mut clusters = HashMap::<_, Vec<_>>::new();
+    for (i, (id, _)) in texts.iter().enumerate() {
+        clusters
+            .entry(root(&mut parents, i))
+            .or_default()
+            .push(*id);
+    }
+
+    let mut clusters = clusters
+        .into_iter()
+        .map(|(_, mut ids)| {
+            ids.sort_unstable();
+            ids
+        })
+        .filter(|ids| ids.len() > 1)
+        .collect::<Vec<_>>();
+    clusters.sort_unstable();
+    clusters
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn texts(texts: &[&str]) -> Vec<(usize, String)> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| (i, text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn normalization() {
+        assert_eq!(
+            normalize("  What's the CAPITAL of   France?"),
+            "what s the capital of france"
+        )
+    }
+
+    #[test]
+    fn detects_duplicates() {
+        let existing = texts(&[
+            "What is the capital of France?",
+            "How many legs does a spider have?"
+        ]);
+
+        assert_eq!(
+            check("what is the capital of france", &existing),
+            Duplicates::Exact(0)
+        );
+        assert_eq!(
+            check("What's the capital city of France?", &existing),
+            Duplicates::Similar(vec![0])
+        );
+        assert_eq!(
+            check("Who painted the Mona Lisa?", &existing),
+            Duplicates::None
+        )
+    }
+
+    #[test]
+    fn clusters_similar_questions() {
+        let bank = texts(&[
+            "What is the capital of France?",
+            "Who painted the Mona Lisa?",
+            "What's the capital of France?",
+            "Which is the capital of France?"
+        ]);
+
+        assert_eq!(
+            clusters(&bank),
+            vec![vec![0, 2, 3]]
+        );
+
+        let shuffled = vec![
+            (4, "Who painted the Mona Lisa?".to_string()),
+            (3, "Who has painted the Mona Lisa?".to_string()),
+            (2, "What's the capital of France?".to_string()),
+            (0, "What is the capital of France?".to_string())
+        ];
+        assert_eq!(
+            clusters(&shuffled),
+            vec![vec![0, 2], vec![3, 4]]
+        )
+    }
+}
@@ -0,0 +1,306 @@
+use {
+    serde::Serialize,
+    std::{
+        hash::Hash,
+        collections::HashMap,
+        time::{Duration, Instant}
+    },
+    rocket::{
+        http::RawStr,
+        request::FromFormValue
+    },
+    crate::models::{
+        game::{GameState, QuestionError, NextQuestionError},
+        rooms::Expiring,
+        web::Session,
+        db::{Connection, QuestionId}
+    }
+};
+
+// teams nobody finished playing are cleaned up after this long
+pub const MAX_AGE: Duration = Duration::from_secs(6 * 60 * 60);
+/// A majority vote is settled with the votes so far once this is over,
+/// so a member who went away can't hold up the rest of the team.
+pub const ANSWER_WINDOW: Duration = Duration::from_secs(30);
+
+/// How a team settles on the answer to a question.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Voting {
+    /// Whoever answers first answers for everyone.
+    First,
+    /// Once everyone voted or the answer window is over, the most common answer counts.
+    Majority
+}
+
+impl <'v> FromFormValue<'v> for Voting {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "first" => Ok(Voting::First),
+            "majority" => Ok(Voting::Majority),
+            _ => Err(form_value)
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TeamError {
+    NotInTeam,
+    NameTaken,
+    Finished,
+    WrongQuestion,
+    AlreadyVoted
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TeamResult {
+    pub points: i32,
+    pub weighted_points: i32,
+    pub placement: u64
+}
+
+/// Several members playing one game together, so the scoring is the one of `GameState`.
+/// Members are told apart by `K`, which is the session in practice.
+#[derive(Debug)]
+pub struct Team<K = Session> {
+    name: String,
+    voting: Voting,
+    members: HashMap<K, String>,
+    // member names and their answers to the current question,
+    // in the order they came in
+    votes: Vec<(String, String)>,
+    pub game: GameState,
+    result: Option<TeamResult>,
+    created: Instant,
+    // when the current question was asked
+    asked: Instant
+}
+
+impl <K: Hash + Eq> Team<K> {
+    pub fn new(name: String, voting: Voting, game: GameState) -> Team<K> {
+        Team {
+            name,
+            voting,
+            members: HashMap::new(),
+            votes: Vec::new(),
+            game,
+            result: None,
+            created: Instant::now(),
+            asked: Instant::now()
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn voting(&self) -> Voting {
+        self.voting
+    }
+
+    pub fn is_member(&self, member: &K) -> bool {
+        self.members.contains_key(member)
+    }
+
+    /// The names of all members, sorted.
+    pub fn members(&self) -> Vec<String> {
+        let mut names = self.members
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    pub fn votes(&self) -> usize {
+        self.votes.len()
+    }
+
+    pub fn has_voted(&self, member: &K) -> bool {
+        self.members
+            .get(member)
+            .map_or(false, |name| self.votes.iter().any(|(voter, _)| voter == name))
+    }
+
+    pub fn join(&mut self, member: K, name: String) -> Result<(), TeamError> {
+        if self.result.is_some() {
+            return Err(TeamError::Finished)
+        }
+        if self.members.values().any(|taken| *taken == name) {
+            return Err(TeamError::NameTaken)
+        }
+
+        self.members.insert(member, name);
+        Ok(())
+    }
+
+    /// How long the members have left to vote on the current question.
+    pub fn remaining(&self, now: Instant) -> Duration {
+        ANSWER_WINDOW
+            .checked_sub(now.duration_since(self.asked))
+            .unwrap_or_default()
+    }
+
+    fn is_overdue(&self, now: Instant) -> bool {
+        now.duration_since(self.asked) >= ANSWER_WINDOW
+    }
+
+    /// Makes sure there is a question to answer, false once there are none left.
+    pub fn next_question(&mut self, conn: &Connection, now: Instant) -> Result<bool, QuestionError> {
+        let asked = match self.game.next_question() {
+            Ok(_) => true,
+            Err(NextQuestionError::HasNotAnswered) => return Ok(true),
+            Err(NextQuestionError::NoneRemaining) => match self.game.load_more_questions(conn) {
+                Ok(()) => self.game.next_question().is_ok(),
+                Err(QuestionError::NoneRemaining) => false,
+                Err(e) => return Err(e)
+            }
+        };
+
+        if asked {
+            self.asked = now;
+        }
+        Ok(asked)
+    }
+
+    /// Records the member's answer to the current question and returns
+    /// the team's answer once it is settled.
+    pub fn vote(&mut self, member: &K, question: QuestionId, answer: String, now: Instant) -> Result<Option<String>, TeamError> {
+        if self.result.is_some() {
+            return Err(TeamError::Finished)
+        }
+        let name = self.members
+            .get(member)
+            .ok_or(TeamError::NotInTeam)?;
+        // someone else may have answered in the meantime
+        let current = self.game.current_question
+            .as_ref()
+            .map(|q| q.id());
+        if !self.game.awaits_answer() || current != Some(question) {
+            return Err(TeamError::WrongQuestion)
+        }
+        if self.votes.iter().any(|(voter, _)| voter == name) {
+            return Err(TeamError::AlreadyVoted)
+        }
+
+        self.votes.push((name.clone(), answer));
+        let settled = match self.voting {
+            Voting::First => self.votes
+                .first()
+                .map(|(_, answer)| answer.clone()),
+            Voting::Majority if self.votes.len() >= self.members.len() || self.is_overdue(now) => majority(&self.votes)
+                .map(String::from),
+            Voting::Majority => None
+        };
+
+        if settled.is_some() {
+            self.votes.clear();
+        }
+        Ok(settled)
+    }
+
+    /// Settles a majority vote with the votes so far once the answer window is over,
+    /// for when the members that are left have all voted already.
+    pub fn settle_overdue(&mut self, now: Instant) -> Option<String> {
+        if self.result.is_some() || self.voting != Voting::Majority || !self.is_overdue(now) {
+            return None
+        }
+
+        let settled = majority(&self.votes)
+            .map(String::from);
+        self.votes.clear();
+        settled
+    }
+
+    pub fn finish(&mut self, result: TeamResult) {
+        self.result = Some(result);
+    }
+
+    pub fn result(&self) -> Option<&TeamResult> {
+        self.result.as_ref()
+    }
+}
+
+impl <K> Expiring for Team<K> {
+    fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.created) > MAX_AGE
+    }
+}
+
+// ties go to the answer that was given first
+fn majority(votes: &[(String, String)]) -> Option<&str> {
+    votes
+        .iter()
+        .rev()
+        .map(|(_, answer)| answer.as_str())
+        .max_by_key(|answer| votes
+            .iter()
+            .filter(|(_, other)| other == answer)
+            .count()
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        crate::test::{CONN, insert_questions},
+        diesel::Connection
+    };
+
+    fn votes(answers: &[&str]) -> Vec<(String, String)> {
+        answers
+            .iter()
+            .enumerate()
+            .map(|(i, answer)| (i.to_string(), answer.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn majority_prefers_earlier_answers_on_ties() {
+        assert_eq!(majority(&votes(&["a", "b", "b"])), Some("b"));
+        assert_eq!(majority(&votes(&["a", "b", "b", "a"])), Some("a"));
+        assert_eq!(majority(&[]), None)
+    }
+
+    #[test]
+    fn majority_waits_for_everyone_within_the_window() {
+        let conn = CONN
+            .lock()
+            .unwrap();
+        conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let (_, mut questions) = insert_questions("Teams", &["Zero?"], &conn)?;
+            let question = questions.remove(0);
+
+            let (ann, bob, cid, outsider) = (0, 1, 2, 3);
+            let mut team = Team::new("Quizzers".into(), Voting::Majority, GameState::default());
+            assert_eq!(team.join(ann, "Ann".into()), Ok(()));
+            assert_eq!(team.join(bob, "Ann".into()), Err(TeamError::NameTaken));
+            assert_eq!(team.join(bob, "Bob".into()), Ok(()));
+            assert_eq!(team.join(cid, "Cid".into()), Ok(()));
+
+            let id = question.id();
+            let now = Instant::now();
+            assert_eq!(team.vote(&ann, id, "0".into(), now), Err(TeamError::WrongQuestion));
+            team.game.current_question = Some(question);
+
+            assert_eq!(team.vote(&outsider, id, "0".into(), now), Err(TeamError::NotInTeam));
+            assert_eq!(team.vote(&ann, id, "1".into(), now), Ok(None));
+            assert_eq!(team.vote(&ann, id, "0".into(), now), Err(TeamError::AlreadyVoted));
+            assert_eq!(team.vote(&bob, id, "0".into(), now), Ok(None));
+            assert!(team.has_voted(&bob));
+            assert_eq!(team.settle_overdue(now), None);
+            assert_eq!(team.vote(&cid, id, "0".into(), now), Ok(Some("0".into())));
+            assert_eq!(team.votes(), 0);
+
+            // cid went away, so the others don't wait for them past the answer window
+            assert_eq!(team.vote(&ann, id, "1".into(), now), Ok(None));
+            assert_eq!(team.settle_overdue(now + ANSWER_WINDOW), Some("1".into()));
+            assert_eq!(team.vote(&bob, id, "2".into(), now + ANSWER_WINDOW), Ok(Some("2".into())));
+
+            Ok(())
+        })
+    }
+}
@@ -2,7 +2,12 @@ use {
     uuid::Uuid,
     derive_more::Deref,
     owning_ref::MutexGuardRef,
-    crate::models::web::{SyncedAdminSessions, Or500, AdminSessions},
+    crate::models::{
+        web::{SyncedAdminSessions, Or500, AdminSessions},
+        account::{self, Scope},
+        db::{DbConn, Connection, PlayerId, models::Player}
+    },
+    diesel::QueryResult,
     rocket::{
         State,
         Request,
@@ -10,7 +15,7 @@ use {
         Response,
         response::Responder,
         outcome::IntoOutcome,
-        http::{Cookie, Status},
+        http::{Cookie, Cookies, Status, Method},
         request::{self, FromRequest}
     }
 };
@@ -72,12 +77,59 @@ impl <'a, 'r> FromRequest<'a, 'r> for NewSession {
 #[derive(Debug, Deref, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct Admin(Session);
 
-pub struct AdminGuard<'a>(MutexGuardRef<'a, AdminSessions, Admin>);
+pub struct AdminGuard<'a>(AdminIdentity<'a>);
+
+enum AdminIdentity<'a> {
+    Session(MutexGuardRef<'a, AdminSessions, String>),
+    /// Scripts send an api token instead of logging in.
+    Token(String)
+}
+
+impl <'a> AdminGuard<'a> {
+    pub fn name(&self) -> &str {
+        match &self.0 {
+            AdminIdentity::Session(name) => name,
+            AdminIdentity::Token(name) => name
+        }
+    }
+
+    pub fn is_token(&self) -> bool {
+        match self.0 {
+            AdminIdentity::Session(_) => false,
+            AdminIdentity::Token(_) => true
+        }
+    }
+}
+
+const BEARER: &str = "Bearer ";
+
+/// Reading only needs the `read` scope, anything else needs `write`.
+fn bearer_admin<'a>(header: &str, request: &Request) -> request::Outcome<AdminGuard<'a>, ()> {
+    if !header.starts_with(BEARER) {
+        return Outcome::Failure((Status::Unauthorized, ()))
+    }
+
+    let conn = request.guard::<DbConn>()?;
+    let scope = match request.method() {
+        Method::Get => Scope::Read,
+        _ => Scope::Write
+    };
+
+    match account::verify_token(header[BEARER.len()..].trim(), scope, &conn) {
+        Ok(Some(admin)) => Outcome::Success(AdminGuard(AdminIdentity::Token(admin.name))),
+        Ok(None) => Outcome::Failure((Status::Unauthorized, ())),
+        Err(_) => Outcome::Failure((Status::InternalServerError, ()))
+    }
+}
 
 impl <'a, 'r> FromRequest<'a, 'r> for AdminGuard<'a> {
     type Error = ();
 
     fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        if let Some(header) = request.headers().get_one("Authorization") {
+            return bearer_admin(header, request)
+        }
+
         request
             .guard::<State<SyncedAdminSessions>>()?
             .inner()
@@ -93,13 +145,13 @@ impl <'a, 'r> FromRequest<'a, 'r> for AdminGuard<'a> {
                     .get(&sess)
                     .ok_or(())
                 )
-            ).map(AdminGuard)
+            ).map(|name| AdminGuard(AdminIdentity::Session(name)))
             .into_outcome(Status::Unauthorized)
     }
 }
 
 pub enum Login<R1, R2 = R1> {
-    Success(R1),
+    Success(String, R1),
     Failure(R2)
 }
 
@@ -110,8 +162,8 @@ where
 {
     fn respond_to(self, request: &Request) -> Result<Response<'r>, Status> {
         match self {
-            Login::Success(resp) => {
-                add_admin_session(request)?;
+            Login::Success(name, resp) => {
+                add_admin_session(name, request)?;
                 resp.respond_to(request)
             },
             Login::Failure(resp) => resp.respond_to(request)
@@ -119,7 +171,7 @@ where
     }
 }
 
-fn add_admin_session(request: &Request) -> Result<(), Status> {
+fn add_admin_session(name: String, request: &Request) -> Result<(), Status> {
     request
         .guard::<State<SyncedAdminSessions>>()
         .success_or(Status::InternalServerError)?
@@ -132,7 +184,75 @@ fn add_admin_session(request: &Request) -> Result<(), Status> {
                 .guard::<NewSession>()
                 .success_or(Status::InternalServerError)
                 .map(Session::from)
-            ).map(Admin)?
+            ).map(Admin)?,
+            name
         );
     Ok(())
 }
+
+const PLAYER_COOKIE: &str = "player";
+
+/// A logged in player. Kept apart from the game session,
+/// since that one ends with every game.
+#[derive(Debug, Deref, Copy, Clone, PartialEq, Eq)]
+pub struct PlayerSession(PlayerId);
+
+impl PlayerSession {
+    pub fn log_in(player: PlayerId, cookies: &mut Cookies) {
+        cookies.add_private(Cookie::new(PLAYER_COOKIE, (*player).to_string()))
+    }
+
+    pub fn log_out(cookies: &mut Cookies) {
+        cookies.remove_private(Cookie::named(PLAYER_COOKIE))
+    }
+
+    /// The account of whoever is logged in, None for everyone else.
+    pub fn player(session: Option<PlayerSession>, conn: &Connection) -> QueryResult<Option<Player>> {
+        session.map_or(Ok(None), |session| Player::with_id(session.0, conn))
+    }
+}
+
+impl <'a, 'r> FromRequest<'a, 'r> for PlayerSession {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        request
+            .cookies()
+            .get_private(PLAYER_COOKIE)
+            .and_then(|cookie| cookie
+                .value()
+                .parse()
+                .ok()
+            ).map(PlayerSession)
+            .into_outcome((Status::Unauthorized, ()))
+    }
+}
+
+const VISITOR_COOKIE: &str = "visitor";
+
+/// Recognises returning players that don't have an account.
+/// Unlike the game session, the cookie outlives single games.
+#[derive(Debug, Deref, Clone, PartialEq, Eq)]
+pub struct Visitor(String);
+
+impl <'a, 'r> FromRequest<'a, 'r> for Visitor {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let mut cookies = request.cookies();
+        let known = cookies
+            .get_private(VISITOR_COOKIE)
+            .map(|cookie| cookie.value().to_string());
+
+        let id = known.unwrap_or_else(|| {
+            let id = Uuid::new_v4().to_string();
+            cookies.add_private(Cookie::build(VISITOR_COOKIE, id.clone())
+                .permanent()
+                .finish()
+            );
+            id
+        });
+
+        Outcome::Success(Visitor(id))
+    }
+}
\ No newline at end of file
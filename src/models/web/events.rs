@@ -0,0 +1,287 @@
+use {
+    serde::Serialize,
+    std::{
+        io::{self, Read},
+        time::{Duration, Instant},
+        sync::{
+            Arc,
+            Weak,
+            Mutex,
+            atomic::{AtomicUsize, Ordering},
+            mpsc::{self, Receiver, SyncSender, RecvTimeoutError}
+        }
+    },
+    super::Session,
+    rocket::{
+        Request,
+        Response,
+        response::Responder,
+        http::{Status, ContentType}
+    }
+};
+
+// comment lines keep proxies from closing idle connections and
+// let us notice clients that went away, since writing to them fails
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+// how often the remaining time is sent while there is a deadline
+const TICK: Duration = Duration::from_secs(1);
+// a client that can't keep up with this many events is dropped
+const BACKLOG: usize = 16;
+
+/// Every open stream keeps one of rocket's workers busy until it's closed,
+/// so there are never more of them than this, out of the 64 workers
+/// configured in Rocket.toml. The rest is left for all other requests.
+pub const MAX_STREAMS: usize = 48;
+/// How many streams a single game, team or room may have open,
+/// so one of them can't use up all the others' streams.
+pub const MAX_SUBSCRIBERS: usize = 16;
+
+static OPEN_STREAMS: AtomicUsize = AtomicUsize::new(0);
+
+// counts towards MAX_STREAMS for as long as it's around
+#[derive(Debug)]
+struct OpenStream;
+
+impl OpenStream {
+    fn open() -> Option<OpenStream> {
+        if OPEN_STREAMS.fetch_add(1, Ordering::SeqCst) >= MAX_STREAMS {
+            OPEN_STREAMS.fetch_sub(1, Ordering::SeqCst);
+            None
+        } else {
+            Some(OpenStream)
+        }
+    }
+}
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        OPEN_STREAMS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The names of the events sent to clients.
+pub mod event {
+    /// The whole state of a room, whenever anything about it changes.
+    pub const STATE: &str = "state";
+    pub const QUESTION_STARTED: &str = "question_started";
+    /// Sent every second until the deadline for answering is over.
+    /// Only rooms have such a deadline, single games and teams never send it.
+    pub const TIME_REMAINING: &str = "time_remaining";
+    pub const ANSWER_REVEALED: &str = "answer_revealed";
+    pub const SCOREBOARD_UPDATED: &str = "scoreboard_updated";
+}
+
+#[derive(Serialize)]
+struct TimeRemaining {
+    remaining_secs: u64
+}
+
+// shared with the readers, which send the remaining time on their own
+#[derive(Debug, Default, Clone)]
+struct Deadline(Arc<Mutex<Option<Instant>>>);
+
+impl Deadline {
+    fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.0
+            .lock()
+            .ok()?
+            .filter(|deadline| *deadline >= now)
+            .map(|deadline| deadline - now)
+    }
+}
+
+#[derive(Debug)]
+struct Subscriber<K> {
+    by: K,
+    sender: SyncSender<String>,
+    // gone once the client closed the stream
+    open: Weak<OpenStream>
+}
+
+/// Pushes server-sent events to everyone subscribed.
+/// Subscribers are told apart by `K`, which is the session in practice.
+#[derive(Debug)]
+pub struct Broadcaster<K = Session> {
+    subscribers: Vec<Subscriber<K>>,
+    deadline: Deadline
+}
+
+impl <K> Default for Broadcaster<K> {
+    fn default() -> Self {
+        Broadcaster {
+            subscribers: Vec::new(),
+            deadline: Deadline::default()
+        }
+    }
+}
+
+impl <K: PartialEq> Broadcaster<K> {
+    /// The stream starts with the given event, so new subscribers don't have to wait for the next one.
+    /// It replaces the subscriber's earlier stream, which ends right away.
+    /// Nothing is returned if there are too many streams open already.
+    pub fn subscribe<T: Serialize>(&mut self, by: K, event: &str, first: &T) -> Option<EventStream> {
+        self.subscribers.retain(|sub| sub.by != by && sub.open.upgrade().is_some());
+        if self.subscribers.len() >= MAX_SUBSCRIBERS {
+            return None
+        }
+        let open = Arc::new(OpenStream::open()?);
+
+        let (sender, receiver) = mpsc::sync_channel(BACKLOG);
+        if let Ok(message) = message(event, first) {
+            sender.try_send(message).ok();
+        }
+        self.subscribers.push(Subscriber {
+            by,
+            sender,
+            open: Arc::downgrade(&open)
+        });
+
+        Some(EventStream(EventReader {
+            receiver,
+            deadline: self.deadline.clone(),
+            idle_since: Instant::now(),
+            buffer: Vec::new(),
+            pos: 0,
+            _open: open
+        }))
+    }
+
+    /// Until the deadline, subscribers get a `time_remaining` event every second.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        if let Ok(mut current) = self.deadline.0.lock() {
+            *current = deadline;
+        }
+    }
+
+    pub fn send<T: Serialize>(&mut self, event: &str, data: &T) {
+        if let Ok(message) = message(event, data) {
+            self.subscribers.retain(|sub| sub.sender
+                .try_send(message.clone())
+                .is_ok()
+            );
+        }
+    }
+}
+
+fn message<T: Serialize>(event: &str, data: &T) -> serde_json::Result<String> {
+    serde_json::to_string(data)
+        .map(|json| format!("event: {}\ndata: {}\n\n", event, json))
+}
+
+/// Blocks until the next event, so every open stream occupies one worker.
+pub struct EventReader {
+    receiver: Receiver<String>,
+    deadline: Deadline,
+    idle_since: Instant,
+    buffer: Vec<u8>,
+    pos: usize,
+    _open: Arc<OpenStream>
+}
+
+impl EventReader {
+    fn next_message(&mut self) -> Option<Vec<u8>> {
+        loop {
+            match self.receiver.recv_timeout(TICK) {
+                Ok(message) => {
+                    self.idle_since = Instant::now();
+                    return Some(message.into_bytes())
+                },
+                Err(RecvTimeoutError::Timeout) => {
+                    let now = Instant::now();
+                    if let Some(remaining) = self.deadline.remaining(now) {
+                        let tick = TimeRemaining { remaining_secs: remaining.as_secs() };
+                        if let Ok(message) = message(event::TIME_REMAINING, &tick) {
+                            return Some(message.into_bytes())
+                        }
+                    }
+                    if now.duration_since(self.idle_since) >= KEEP_ALIVE {
+                        self.idle_since = now;
+                        return Some(b": keep-alive\n\n".to_vec())
+                    }
+                },
+                // the broadcaster is gone, so is the game or room
+                Err(RecvTimeoutError::Disconnected) => return None
+            }
+        }
+    }
+}
+
+impl Read for EventReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos >= self.buffer.len() {
+            self.buffer = match self.next_message() {
+                Some(message) => message,
+                None => return Ok(0)
+            };
+            self.pos = 0;
+        }
+
+        let len = buf.len().min(self.buffer.len() - self.pos);
+        buf[..len].copy_from_slice(&self.buffer[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+pub struct EventStream(EventReader);
+
+impl <'r> Responder<'r> for EventStream {
+    fn respond_to(self, _request: &Request) -> Result<Response<'r>, Status> {
+        Response::build()
+            .header(ContentType::new("text", "event-stream"))
+            .raw_header("Cache-Control", "no-cache")
+            // otherwise rocket waits for a full chunk before sending anything
+            .chunked_body(self.0, 1)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn events_reach_subscribers() {
+        let mut broadcaster = Broadcaster::default();
+        let EventStream(mut reader) = broadcaster.subscribe(0, "state", &1).unwrap();
+        broadcaster.send("state", &vec![2, 3]);
+        drop(broadcaster);
+
+        let mut received = String::new();
+        reader.read_to_string(&mut received).unwrap();
+        assert_eq!(received, "event: state\ndata: 1\n\nevent: state\ndata: [2,3]\n\n")
+    }
+
+    #[test]
+    fn remaining_time_is_sent_until_the_deadline() {
+        let mut broadcaster = Broadcaster::default();
+        broadcaster.set_deadline(Some(Instant::now() + Duration::from_secs(5)));
+        let EventStream(mut reader) = broadcaster.subscribe(0, "state", &1).unwrap();
+        reader.next_message().unwrap();
+
+        let tick = String::from_utf8(reader.next_message().unwrap()).unwrap();
+        assert!(tick.starts_with("event: time_remaining\ndata: {\"remaining_secs\":"));
+
+        broadcaster.set_deadline(None);
+        drop(broadcaster);
+        assert_eq!(reader.next_message(), None)
+    }
+
+    #[test]
+    fn streams_are_limited() {
+        let mut broadcaster = Broadcaster::default();
+        let EventStream(mut replaced) = broadcaster.subscribe(0, "state", &1).unwrap();
+        let _replacing = broadcaster.subscribe(0, "state", &2).unwrap();
+        replaced.next_message().unwrap();
+        assert_eq!(replaced.next_message(), None);
+
+        let others = (1..MAX_SUBSCRIBERS)
+            .map(|by| broadcaster.subscribe(by, "state", &1))
+            .collect::<Option<Vec<_>>>();
+        assert!(others.is_some());
+        assert!(broadcaster.subscribe(MAX_SUBSCRIBERS, "state", &1).is_none());
+
+        drop(others);
+        assert!(broadcaster.subscribe(MAX_SUBSCRIBERS, "state", &1).is_some())
+    }
+}
@@ -1,9 +1,11 @@
 mod auth;
 mod state;
 mod misc;
+mod events;
 
 pub use {
     auth::*,
     state::*,
-    misc::*
+    misc::*,
+    events::*
 };
\ No newline at end of file
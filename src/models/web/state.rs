@@ -3,11 +3,15 @@ use {
     derive_more::{Deref, DerefMut},
     crate::models::{
         game::GameState,
+        rooms::{Room, RoomCode},
+        teams::Team,
         web::{Session, Admin, SESSION_COOKIE}
     },
     std::{
-        sync::Mutex,
-        collections::{HashMap, HashSet}
+        net::IpAddr,
+        sync::{Arc, Mutex},
+        collections::HashMap,
+        time::{Duration, Instant}
     },
     rocket::{
         State,
@@ -112,9 +116,116 @@ impl <'a, 'r> FromRequest<'a, 'r> for EndGame {
     }
 }
 
-pub(super) type AdminSessions = HashSet<Admin>;
+// maps the sessions of logged in admins to their names
+pub(super) type AdminSessions = HashMap<Admin, String>;
 pub(super)  type SyncedAdminSessions = Mutex<AdminSessions>;
 
 pub fn init_admin_sessions() -> SyncedAdminSessions {
-    Mutex::new(HashSet::new())
+    Mutex::new(HashMap::new())
+}
+
+pub type Rooms = HashMap<RoomCode, Room>;
+pub type SyncedRooms = Mutex<Rooms>;
+
+pub fn init_rooms() -> SyncedRooms {
+    Mutex::new(HashMap::new())
+}
+
+// teams join with the same kind of code as rooms. every team has its own lock,
+// so storing one team's answer doesn't hold up all the others
+pub type Teams = HashMap<RoomCode, Arc<Mutex<Team>>>;
+pub type SyncedTeams = Mutex<Teams>;
+
+pub fn init_teams() -> SyncedTeams {
+    Mutex::new(HashMap::new())
+}
+
+const MAX_REPORTS: usize = 5;
+const REPORT_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+// players who already ended their game don't have a session anymore,
+// so they are told apart by their address instead
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub enum Reporter {
+    Session(Session),
+    Address(IpAddr)
+}
+
+pub type ReportLimits = Mutex<HashMap<Reporter, Vec<Instant>>>;
+
+pub fn init_report_limits() -> ReportLimits {
+    Mutex::new(HashMap::new())
+}
+
+// forgets the reports that are out of the window
+fn prune(limits: &mut HashMap<Reporter, Vec<Instant>>, now: Instant) {
+    limits.retain(|_, reports| {
+        reports.retain(|at| now.duration_since(*at) < REPORT_WINDOW);
+        !reports.is_empty()
+    });
+}
+
+/// Only succeeds if the player hasn't sent too many question reports lately.
+/// Nothing is counted until the allowance is spent on a valid report.
+#[derive(Debug)]
+pub struct ReportAllowance<'r> {
+    reporter: Reporter,
+    limits: State<'r, ReportLimits>
+}
+
+impl ReportAllowance<'_> {
+    /// Counts the report, fails if other requests used up the allowance in the meantime.
+    pub fn spend(self) -> Result<(), Status> {
+        let mut limits = self.limits
+            .lock()
+            .map_err(|_| Status::ServiceUnavailable)?;
+
+        let now = Instant::now();
+        prune(&mut limits, now);
+
+        let reports = limits
+            .entry(self.reporter)
+            .or_default();
+
+        if reports.len() >= MAX_REPORTS {
+            Err(Status::TooManyRequests)
+        } else {
+            reports.push(now);
+            Ok(())
+        }
+    }
+}
+
+impl <'a, 'r> FromRequest<'a, 'r> for ReportAllowance<'r> {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Self, Self::Error> {
+        let reporter = match request.guard::<Session>() {
+            Outcome::Success(sess) => Reporter::Session(sess),
+            _ => request
+                .client_ip()
+                .map(Reporter::Address)
+                .into_outcome((Status::BadRequest, ()))?
+        };
+
+        let limits = request
+            .guard::<State<ReportLimits>>()
+            .map_failure(|_| (Status::ServiceUnavailable, ()))?;
+        let used = {
+            let mut limits = limits
+                .lock()
+                .map_err(drop)
+                .into_outcome(Status::ServiceUnavailable)?;
+            prune(&mut limits, Instant::now());
+            limits
+                .get(&reporter)
+                .map_or(0, Vec::len)
+        };
+
+        if used >= MAX_REPORTS {
+            Outcome::Failure((Status::TooManyRequests, ()))
+        } else {
+            Outcome::Success(ReportAllowance { reporter, limits })
+        }
+    }
 }
@@ -0,0 +1,2 @@
+pub mod v1;
+pub mod openapi;
\ No newline at end of file
@@ -0,0 +1,1026 @@
+//! An OpenAPI 3 description of every mounted route, served at `/openapi.json`.
+//! Paths, methods and parameters are taken from the routes themselves,
+//! what they expect and answer with is described in `OPERATIONS`.
+
+use {
+    serde_json::{json, Map, Value},
+    rocket::{
+        get,
+        Route,
+        State,
+        http::Method
+    },
+    rocket_contrib::json::Json,
+    crate::models::media::MediaType,
+    super::v1::ErrorCode
+};
+
+const API_V1: &str = "/api/v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Auth {
+    Public,
+    /// The session cookie of a running game, room or team.
+    Session,
+    /// A logged in player.
+    Player,
+    /// The session of a logged in admin or an api token.
+    Admin
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Body {
+    Nothing,
+    /// Form fields, sent in the query string instead for `<query..>` routes.
+    Form(&'static [&'static str]),
+    Object(&'static str),
+    /// The raw file, its type is told from the content.
+    Media
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reply {
+    Page,
+    Redirect,
+    Done,
+    File,
+    Events,
+    Returns(&'static str),
+    Created(&'static str)
+}
+
+#[derive(Debug)]
+struct Operation {
+    method: Method,
+    path: &'static str,
+    summary: &'static str,
+    auth: Auth,
+    body: Body,
+    reply: Reply
+}
+
+macro_rules! operations {
+    ($($method:ident $path:literal $summary:literal, $auth:ident, $body:expr, $reply:expr;)*) => {
+        &[$(
+            Operation {
+                method: Method::$method,
+                path: $path,
+                summary: $summary,
+                auth: Auth::$auth,
+                body: $body,
+                reply: $reply
+            }
+        ),*]
+    };
+}
+
+use {Body::*, Reply::*};
+
+const CREDENTIALS: &[&str] = &["name", "password"];
+const JOIN: &[&str] = &["code", "name"];
+
+const OPERATIONS: &[Operation] = operations! {
+    Get "/" "Start page", Public, Nothing, Page;
+    Get "/content/static/{path}" "Static content like scripts and styles", Public, Nothing, File;
+    Get "/content/media/{id}" "Image or audio file of a question", Public, Nothing, File;
+    Get "/favicon.ico" "Favicon", Public, Nothing, File;
+    Get "/openapi.json" "This document", Public, Nothing, Returns("OpenApi");
+
+    Post "/settings" "Game settings for a user name", Public, Form(&["name"]), Page;
+    Post "/play/new_game" "Start a new game in a new session", Public,
+        Form(&["user", "categories", "tags", "locale", "practice", "duel"]), Redirect;
+    Get "/play" "The current or next question", Session, Nothing, Page;
+    Post "/play/answer" "Answer the current question", Session, Form(&["answer"]), Redirect;
+    Post "/play/resume" "Go on with other categories", Session, Form(&["categories"]), Redirect;
+    Get "/play/missed" "The correct answer after a wrong one in practice mode", Session, Nothing, Page;
+    Get "/play/events" "Events of the running game", Session, Nothing, Events;
+    Get "/play/use_joker" "Remove two incorrect answers", Session, Nothing, Returns("Joker");
+    Get "/play/end" "End the game and save the score", Session, Nothing, Page;
+    Get "/play/failed" "End the game after a wrong answer", Session, Nothing, Page;
+    Post "/play/report" "Report a problem with a question, rate limited", Public,
+        Form(&["question", "reason", "comment"]), Done;
+
+    Get "/leaderboard" "Scores, filtered and sorted", Public,
+        Form(&["range", "sort", "page", "player", "categories", "set"]), Page;
+    Get "/daily" "Today's challenge", Public, Nothing, Page;
+    Post "/daily/play" "Play today's challenge", Public, Form(&["user"]), Redirect;
+    Get "/duels/{code}" "A duel and, once both played, its comparison", Public, Nothing, Page;
+    Post "/duels/{code}/accept" "Play the challenger's questions, unless someone else is playing them", Public, Form(&["user"]), Redirect;
+
+    Get "/rooms" "Create or join a room", Public, Nothing, Page;
+    Post "/rooms/create" "Host a new room", Public, Form(&["categories"]), Redirect;
+    Get "/rooms/{code}/host" "Host view of a room", Session, Nothing, Page;
+    Post "/rooms/join" "Join a room", Public, Form(JOIN), Redirect;
+    Get "/rooms/{code}" "Player view of a room", Session, Nothing, Page;
+    Post "/rooms/{code}/advance" "Move a room to its next step, host only", Session, Nothing, Done;
+    Post "/rooms/{code}/answer" "Answer the question of the round", Session, Form(&["answer"]), Done;
+    Get "/rooms/{code}/events" "Events of a room", Session, Nothing, Events;
+
+    Get "/teams" "Create or join a team", Public, Nothing, Page;
+    Post "/teams/create" "Create a team", Public, Form(&["team", "name", "voting", "categories"]), Redirect;
+    Post "/teams/join" "Join a team", Public, Form(JOIN), Redirect;
+    Get "/teams/{code}" "The team's game", Session, Nothing, Page;
+    Post "/teams/{code}/answer" "Vote for an answer", Session, Form(&["question", "answer"]), Redirect;
+    Post "/teams/{code}/settle" "Settle a majority vote once the answer window is over", Session, Nothing, Redirect;
+    Get "/teams/{code}/events" "Events of a team", Session, Nothing, Events;
+    Get "/leaderboard/teams" "Team scores", Public, Nothing, Page;
+
+    Get "/player" "Player login and registration", Public, Nothing, Page;
+    Post "/player/register" "Register a player", Public, Form(CREDENTIALS), Redirect;
+    Post "/player/login" "Log in as a player", Public, Form(CREDENTIALS), Redirect;
+    Post "/player/logout" "Log out", Public, Nothing, Redirect;
+    Get "/player/profile" "The own profile", Player, Nothing, Redirect;
+    Get "/players/{id}" "A player's profile", Public, Nothing, Page;
+
+    Get "/admin" "Admin interface", Admin, Nothing, Page;
+    Get "/login" "Admin login page", Public, Nothing, Page;
+    Post "/login/verify" "Log in as an admin", Public, Form(CREDENTIALS), Redirect;
+    Post "/admin/register" "Register an admin", Public, Form(CREDENTIALS), Redirect;
+    Post "/admin/add_question" "Add a question", Admin,
+        Form(&["question", "correct", "incorrect1", "incorrect2", "incorrect3", "category", "tags", "locale"]), Redirect;
+    Post "/admin/add_category" "Add a category", Admin, Form(&["name"]), Redirect;
+    Delete "/admin/delete_question/{id}" "Move a question to the trash", Admin, Nothing, Done;
+    Get "/admin/trashed_questions" "Questions in the trash", Admin, Nothing, Returns("JsonQuestions");
+    Put "/admin/restore_question/{id}" "Restore a question from the trash", Admin, Nothing, Done;
+    Delete "/admin/purge_question/{id}" "Delete a trashed, never answered question for good", Admin, Nothing, Done;
+    Put "/admin/edit_question" "Edit a question, published ones go back to review, duplicates are refused", Admin, Object("Question"), Done;
+    Get "/admin/all_questions" "All questions", Admin, Nothing, Returns("JsonQuestions");
+    Get "/admin/questions" "Search, filter and sort questions a page at a time", Admin,
+        Form(&["search", "category", "status", "min_rate", "max_rate", "sort", "desc", "page"]), Returns("QuestionPage");
+    Get "/admin/tagged_questions" "Questions with a tag", Admin, Nothing, Returns("JsonQuestions");
+    Get "/admin/duplicate_questions" "Clusters of likely duplicates", Admin, Nothing, Returns("JsonClusters");
+    Get "/admin/analytics" "Answer statistics", Admin, Nothing, Returns("Analytics");
+    Get "/admin/open_reports" "Reported questions", Admin, Nothing, Returns("JsonReports");
+    Post "/admin/resolve_reports/{id}" "Resolve the reports of a question", Admin, Nothing, Done;
+    Get "/admin/all_categories" "All categories", Admin, Nothing, Returns("JsonCategories");
+    Get "/admin/all_tags" "All tags", Admin, Nothing, Returns("JsonTags");
+    Post "/admin/upload_media/{id}" "Attach an image or audio file", Admin, Media, Returns("QuestionMedia");
+    Get "/admin/question_media/{id}" "Media of a question", Admin, Nothing, Returns("JsonMedia");
+    Delete "/admin/delete_media/{id}" "Delete a media file", Admin, Nothing, Done;
+    Get "/admin/question_translations/{id}" "Translations of a question", Admin, Nothing, Returns("JsonTranslations");
+    Put "/admin/translate_question" "Add or replace a translation", Admin,
+        Object("NewQuestionTranslation"), Returns("QuestionTranslation");
+    Delete "/admin/delete_translation/{id}/{locale}" "Delete a translation", Admin, Nothing, Done;
+    Get "/admin/question_revisions/{id}" "Earlier versions of a question", Admin, Nothing, Returns("JsonRevisions");
+    Get "/admin/revision_diff/{from}/{to}" "Changes between two revisions", Admin, Nothing, Returns("JsonDiff");
+    Post "/admin/restore_revision/{id}" "Restore an earlier version unless it duplicates another question", Admin, Nothing, Returns("Question");
+    Post "/admin/submit_question/{id}" "Submit a draft for review", Admin, Nothing, Returns("Question");
+    Post "/admin/approve_question/{id}" "Publish a question in review", Admin, Nothing, Returns("Question");
+    Post "/admin/reject_question/{id}" "Send a question back to draft", Admin, Nothing, Returns("Question");
+    Post "/admin/retire_question/{id}" "Stop asking a question", Admin, Nothing, Returns("Question");
+    Post "/admin/reopen_question/{id}" "Turn a retired question back into a draft", Admin, Nothing, Returns("Question");
+    Get "/admin/api_tokens" "The own api tokens, logged in only", Admin, Nothing, Returns("JsonTokens");
+    Post "/admin/api_tokens" "Create an api token, logged in only", Admin, Object("NewToken"), Returns("CreatedToken");
+    Delete "/admin/api_tokens/{id}" "Revoke an api token, logged in only", Admin, Nothing, Done;
+
+    Post "/api/v1/games" "Start a new game in a new session", Public, Object("NewGame"), Created("GameView");
+    Get "/api/v1/game" "The running game", Session, Nothing, Returns("GameView");
+    Get "/api/v1/game/question" "The current question", Session, Nothing, Returns("DisplayData");
+    Post "/api/v1/game/next" "Move on to the next question", Session, Nothing, Returns("DisplayData");
+    Post "/api/v1/game/answer" "Answer the current question", Session, Object("AnswerRequest"), Returns("AnswerResult");
+    Post "/api/v1/game/joker" "Remove two incorrect answers", Session, Nothing, Returns("Joker");
+    Post "/api/v1/game/pause" "Stop the clock to pick other categories", Session, Nothing, Returns("Paused");
+    Post "/api/v1/game/resume" "Go on with other categories", Session, Object("Resume"), Returns("GameView");
+    Delete "/api/v1/game" "End the game and save the score", Session, Nothing, Returns("Results");
+};
+
+pub struct ApiDocument(Value);
+
+#[get("/openapi.json")]
+pub fn openapi(document: State<ApiDocument>) -> Json<Value> {
+    Json(document.0.clone())
+}
+
+/// Needs to be called once everything is mounted.
+pub fn document<'a>(routes: impl Iterator<Item = &'a Route>) -> ApiDocument {
+    let mut paths = Map::new();
+    for route in routes {
+        let path = openapi_path(route.uri.path());
+        let operation = describe(route);
+        let method = route.method.as_str().to_lowercase();
+
+        if let Value::Object(item) = paths.entry(path).or_insert_with(|| json!({})) {
+            item.insert(method, operation);
+        }
+    }
+
+    ApiDocument(json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Quiz",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "paths": paths,
+        "components": {
+            "securitySchemes": {
+                "session": {
+                    "type": "apiKey",
+                    "in": "cookie",
+                    "name": "session"
+                },
+                "player": {
+                    "type": "apiKey",
+                    "in": "cookie",
+                    "name": "player"
+                },
+                "admin": {
+                    "type": "apiKey",
+                    "in": "cookie",
+                    "name": "session",
+                    "description": "A session that logged in at /login/verify"
+                },
+                "token": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "An api token, GET requests need the read scope and all others the write scope"
+                }
+            },
+            "schemas": schemas()
+        }
+    }))
+}
+
+fn find(method: Method, path: &str) -> Option<&'static Operation> {
+    OPERATIONS
+        .iter()
+        .find(|op| op.method == method && op.path == path)
+}
+
+fn describe(route: &Route) -> Value {
+    let path = openapi_path(route.uri.path());
+    let mut parameters = route.uri
+        .path()
+        .split('/')
+        .filter_map(dynamic)
+        .map(|name| json!({
+            "name": name,
+            "in": "path",
+            "required": true,
+            "schema": { "type": "string" }
+        }))
+        .collect::<Vec<_>>();
+    let query = route.uri
+        .query()
+        .unwrap_or_default()
+        .split('&');
+    // `<query..>` takes the whole form from the query string
+    let collects_query = query
+        .clone()
+        .any(|segment| segment.ends_with("..>"));
+    parameters.extend(query
+        .filter(|segment| !segment.ends_with("..>"))
+        .filter_map(dynamic)
+        .map(query_parameter)
+    );
+
+    let op = match find(route.method, &path) {
+        Some(op) => op,
+        // still listed, so the document is never missing a route
+        None => return json!({
+            "summary": route.name.unwrap_or_default(),
+            "parameters": parameters,
+            "responses": { "default": { "description": "Undocumented" } }
+        })
+    };
+
+    let mut operation = json!({
+        "summary": op.summary,
+        "responses": responses(op.reply, path.starts_with(API_V1))
+    });
+    match (op.body, collects_query) {
+        (Form(fields), true) => parameters.extend(fields.iter().map(|&name| query_parameter(name))),
+        (Nothing, _) => {},
+        (body, _) => operation["requestBody"] = request_body(body)
+    }
+    operation["parameters"] = parameters.into();
+    match op.auth {
+        Auth::Public => {},
+        Auth::Session => operation["security"] = json!([{ "session": [] }]),
+        Auth::Player => operation["security"] = json!([{ "player": [] }]),
+        Auth::Admin => operation["security"] = json!([{ "admin": [] }, { "token": [] }])
+    }
+
+    operation
+}
+
+/// `/rooms/<code>/events` becomes `/rooms/{code}/events`.
+fn openapi_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| match dynamic(segment) {
+            Some(name) => format!("{{{}}}", name),
+            None => segment.to_owned()
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The name of a dynamic segment like `<id>` or `<path..>`.
+fn dynamic(segment: &str) -> Option<&str> {
+    if segment.starts_with('<') && segment.ends_with('>') {
+        Some(segment[1..segment.len() - 1].trim_end_matches(".."))
+    } else {
+        None
+    }
+}
+
+fn query_parameter(name: &str) -> Value {
+    json!({
+        "name": name,
+        "in": "query",
+        "schema": { "type": "string" }
+    })
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn nullable(name: &str) -> Value {
+    json!({ "allOf": [schema_ref(name)], "nullable": true })
+}
+
+fn request_body(body: Body) -> Value {
+    let content = match body {
+        Nothing => json!({}),
+        Form(fields) => {
+            let properties = fields
+                .iter()
+                .map(|&field| (field.to_owned(), json!({ "type": "string" })))
+                .collect::<Map<_, _>>();
+            json!({
+                "application/x-www-form-urlencoded": {
+                    "schema": { "type": "object", "properties": properties }
+                }
+            })
+        },
+        Object(schema) => json!({ "application/json": { "schema": schema_ref(schema) } }),
+        Media => Value::Object(MediaType::ALL
+            .iter()
+            .map(|media_type| (
+                media_type.content_type().to_string(),
+                json!({ "schema": { "type": "string", "format": "binary" } })
+            ))
+            .collect()
+        )
+    };
+
+    json!({ "required": true, "content": content })
+}
+
+fn responses(reply: Reply, api: bool) -> Value {
+    let json_content = |schema| json!({ "application/json": { "schema": schema_ref(schema) } });
+    let mut responses = match reply {
+        Page => json!({ "200": { "description": "HTML page", "content": { "text/html": {} } } }),
+        Redirect => json!({ "303": { "description": "Redirect to the next page" } }),
+        Done => json!({ "200": { "description": "Done" } }),
+        File => json!({ "200": { "description": "The file", "content": { "*/*": {} } } }),
+        Events => json!({
+            "200": { "description": "Server-sent events", "content": { "text/event-stream": {} } },
+            "503": { "description": "Too many open event streams" }
+        }),
+        Returns(schema) => json!({ "200": { "description": "OK", "content": json_content(schema) } }),
+        Created(schema) => json!({ "201": { "description": "Created", "content": json_content(schema) } })
+    };
+    if api {
+        responses["default"] = json!({ "description": "Error", "content": json_content("ApiError") });
+    }
+
+    responses
+}
+
+fn object(properties: Value) -> Value {
+    let required = properties
+        .as_object()
+        .map(|properties| properties.keys().cloned().collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn array(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+fn list(name: &str) -> Value {
+    array(schema_ref(name))
+}
+
+fn schemas() -> Value {
+    let int = json!({ "type": "integer" });
+    let number = json!({ "type": "number" });
+    let string = json!({ "type": "string" });
+    let boolean = json!({ "type": "boolean" });
+    let strings = array(string.clone());
+    let nullable_int = json!({ "type": "integer", "nullable": true });
+    let nullable_string = json!({ "type": "string", "nullable": true });
+    let error_codes = ErrorCode::ALL
+        .iter()
+        .map(|code| serde_json::to_value(code).unwrap_or_default())
+        .collect::<Vec<_>>();
+
+    json!({
+        "OpenApi": { "type": "object" },
+        "SystemTime": object(json!({
+            "secs_since_epoch": int,
+            "nanos_since_epoch": int
+        })),
+        "Category": object(json!({
+            "id": int,
+            "name": string
+        })),
+        "Question": {
+            "type": "object",
+            "properties": {
+                "id": int,
+                "category_id": int,
+                "string": string,
+                "correct": string,
+                "incorrect": strings,
+                "tags": strings,
+                "locale": string,
+                "status": {
+                    "type": "integer",
+                    "enum": [0, 1, 2, 3],
+                    "description": "Draft, in review, published or retired"
+                },
+                "submitted_by": nullable_string,
+                "approved_by": nullable_string,
+                "deleted_at": nullable("SystemTime"),
+                "created_at": schema_ref("SystemTime")
+            },
+            "required": ["id", "category_id", "string", "correct", "incorrect"]
+        },
+        "JsonQuestions": object(json!({ "questions": list("Question") })),
+        "QuestionPage": object(json!({
+            "questions": list("Question"),
+            "page": int,
+            "pages": int,
+            "total": int
+        })),
+        "JsonClusters": object(json!({ "clusters": array(list("Question")) })),
+        "JsonCategories": object(json!({ "categories": list("Category") })),
+        "JsonTags": object(json!({ "tags": strings })),
+        "QuestionReport": object(json!({
+            "id": int,
+            "question_id": int,
+            "reason": {
+                "type": "integer",
+                "enum": [0, 1, 2, 3, 4],
+                "description": "Wrong answer, typo, unclear, offensive or other"
+            },
+            "comment": nullable_string,
+            "reported_at": schema_ref("SystemTime"),
+            "resolved_at": nullable("SystemTime"),
+            "resolved_by": nullable_string
+        })),
+        "ReportedQuestion": object(json!({
+            "question": schema_ref("Question"),
+            "reports": list("QuestionReport")
+        })),
+        "JsonReports": object(json!({ "questions": list("ReportedQuestion") })),
+        "Flag": {
+            "oneOf": [
+                { "type": "string", "enum": ["always_correct", "never_correct"] },
+                object(json!({ "unpicked_distractor": int }))
+            ]
+        },
+        "QuestionAnalytics": object(json!({
+            "question": int,
+            "category": int,
+            "string": string,
+            "answers": int,
+            "correct_rate": number,
+            "picks": array(int.clone()),
+            "avg_secs": number,
+            "joker_uses": int,
+            "flags": list("Flag")
+        })),
+        "CategoryAnalytics": object(json!({
+            "category": int,
+            "name": string,
+            "answers": int,
+            "correct_rate": number,
+            "avg_secs": number,
+            "joker_uses": int
+        })),
+        "Analytics": object(json!({
+            "questions": list("QuestionAnalytics"),
+            "categories": list("CategoryAnalytics")
+        })),
+        "QuestionMedia": object(json!({
+            "id": int,
+            "question_id": int,
+            "answer": nullable_int,
+            "file_name": string,
+            "content_type": string
+        })),
+        "JsonMedia": object(json!({ "media": list("QuestionMedia") })),
+        "QuestionTranslation": object(json!({
+            "id": int,
+            "question_id": int,
+            "locale": string,
+            "string": string,
+            "correct": string,
+            "incorrect": strings
+        })),
+        "NewQuestionTranslation": object(json!({
+            "question_id": int,
+            "locale": string,
+            "string": string,
+            "correct": string,
+            "incorrect": strings
+        })),
+        "JsonTranslations": object(json!({ "translations": list("QuestionTranslation") })),
+        "QuestionRevision": object(json!({
+            "id": int,
+            "question_id": int,
+            "category_id": int,
+            "string": string,
+            "correct": string,
+            "incorrect": strings,
+            "tags": strings,
+            "locale": string,
+            "editor": nullable_string,
+            "revised_at": schema_ref("SystemTime")
+        })),
+        "JsonRevisions": object(json!({ "revisions": list("QuestionRevision") })),
+        "FieldChange": object(json!({
+            "field": string,
+            "old": string,
+            "new": string
+        })),
+        "JsonDiff": object(json!({ "changes": list("FieldChange") })),
+        "ApiToken": object(json!({
+            "id": int,
+            "name": string,
+            "scopes": list("Scope"),
+            "created_at": schema_ref("SystemTime"),
+            "last_used_at": nullable("SystemTime"),
+            "revoked_at": nullable("SystemTime")
+        })),
+        "Scope": { "type": "string", "enum": ["read", "write"] },
+        "JsonTokens": object(json!({ "tokens": list("ApiToken") })),
+        "NewToken": object(json!({
+            "name": string,
+            "scopes": list("Scope")
+        })),
+        "CreatedToken": object(json!({
+            "token": string,
+            "details": schema_ref("ApiToken")
+        })),
+        "Joker": object(json!({ "incorrect": strings })),
+        "DisplayMedia": object(json!({
+            "url": string,
+            "audio": boolean
+        })),
+        "DisplayAnswer": object(json!({
+            "text": string,
+            "media": nullable("DisplayMedia")
+        })),
+        "DisplayData": object(json!({
+            "id": int,
+            "question": string,
+            "media": nullable("DisplayMedia"),
+            "answers": list("DisplayAnswer"),
+            "category": string,
+            "points": int,
+            "joker": boolean,
+            "ratio": int,
+            "elapsed_secs": int
+        })),
+        "NewGame": {
+            "type": "object",
+            "properties": {
+                "user": string,
+                "categories": array(int.clone()),
+                "tags": strings,
+                "locale": { "type": "string", "enum": ["de", "en"] },
+                "practice": boolean,
+                "duel": boolean
+            },
+            "required": ["user", "categories"]
+        },
+        "GameView": object(json!({
+            "points": int,
+            "weighted_points": int,
+            "joker": boolean,
+            "elapsed_secs": int,
+            "practice": boolean,
+            "seed": int
+        })),
+        "AnswerRequest": object(json!({ "answer": string })),
+        "AnswerResult": object(json!({
+            "correct": boolean,
+            "correct_answer": string,
+            "points": int,
+            "weighted_points": int,
+            "game_over": boolean,
+            "duel": nullable_string
+        })),
+        "Paused": object(json!({ "categories": list("Category") })),
+        "Resume": object(json!({ "categories": array(int.clone()) })),
+        "BoxCount": object(json!({
+            "leitner_box": int,
+            "questions": int
+        })),
+        "Results": object(json!({
+            "points": int,
+            "weighted_points": int,
+            "placement": nullable_int,
+            "set_placement": nullable_int,
+            "practice_progress": { "type": "array", "items": schema_ref("BoxCount"), "nullable": true },
+            "duel": nullable_string
+        })),
+        "ApiError": object(json!({
+            "error": object(json!({
+                "code": { "type": "string", "enum": error_codes },
+                "message": string
+            }))
+        }))
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        super::*,
+        std::collections::HashSet,
+        serde::{Serialize, de::DeserializeOwned},
+        diesel::Connection,
+        rocket::{
+            local::Client,
+            request::{FromForm, FormItems}
+        },
+        crate::{
+            test::{rocket, CONN, insert_questions},
+            models::{
+                analytics,
+                practice,
+                game,
+                account::{self, Scope},
+                i18n::Locale,
+                db::{
+                    FieldChange,
+                    ReportReason,
+                    models::{
+                        Admin,
+                        NewAdmin,
+                        Answer,
+                        NewAnswer,
+                        Player,
+                        NewPlayer,
+                        QuestionMedia,
+                        NewQuestionMedia,
+                        QuestionReport,
+                        NewQuestionReport,
+                        QuestionRevision,
+                        QuestionTranslation,
+                        NewQuestionTranslation
+                    }
+                }
+            },
+            routing::{
+                pages::{
+                    admin::api::{
+                        JsonQuestions,
+                        QuestionPage,
+                        JsonClusters,
+                        JsonCategories,
+                        JsonTags,
+                        ReportedQuestion,
+                        JsonReports,
+                        JsonMedia,
+                        JsonTranslations,
+                        JsonRevisions,
+                        JsonDiff,
+                        JsonTokens,
+                        NewToken,
+                        CreatedToken
+                    },
+                    play::{Joker, DisplayData, NewCategories},
+                    start::Settings,
+                    leaderboard::LeaderboardQuery,
+                    teams::NewTeam
+                },
+                api::v1::{self, GameView, AnswerResult, Paused, Results, AnswerRequest, Resume}
+            }
+        }
+    };
+
+    fn refs(value: &Value, found: &mut Vec<String>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(r)) = map.get("$ref") {
+                    found.push(r.trim_start_matches("#/components/schemas/").to_owned());
+                }
+                map.values().for_each(|value| refs(value, found));
+            },
+            Value::Array(values) => values.iter().for_each(|value| refs(value, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn every_route_is_described() {
+        let rocket = rocket();
+        let undescribed = rocket
+            .routes()
+            .filter(|route| find(route.method, &openapi_path(route.uri.path())).is_none())
+            .map(|route| route.uri.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(undescribed, Vec::<String>::new());
+
+        // and nothing is described that isn't there anymore
+        let stale = OPERATIONS
+            .iter()
+            .filter(|op| !rocket
+                .routes()
+                .any(|route| route.method == op.method && openapi_path(route.uri.path()) == op.path)
+            )
+            .map(|op| op.path)
+            .collect::<Vec<_>>();
+        assert_eq!(stale, Vec::<&str>::new())
+    }
+
+    #[test]
+    fn every_schema_exists() {
+        let rocket = rocket();
+        let ApiDocument(doc) = document(rocket.routes());
+        let mut found = vec![];
+        refs(&doc, &mut found);
+
+        let schemas = &doc["components"]["schemas"];
+        for name in found {
+            assert!(schemas.get(&name).is_some(), "missing schema {}", name)
+        }
+    }
+
+    // checks a value the way a client reads it: every field is described,
+    // nothing required is missing and the types fit. Names of the schemas used are collected
+    fn check(value: &Value, schema: &Value, schemas: &Value, used: &mut HashSet<String>) -> Result<(), String> {
+        if value.is_null() && schema["nullable"] == true {
+            return Ok(())
+        }
+        if let Some(name) = schema["$ref"].as_str() {
+            let name = name.trim_start_matches("#/components/schemas/");
+            used.insert(name.to_owned());
+            return check(value, &schemas[name], schemas, used)
+                .map_err(|e| format!("{}: {}", name, e))
+        }
+        if let Some(all) = schema["allOf"].as_array() {
+            return all
+                .iter()
+                .try_for_each(|schema| check(value, schema, schemas, used))
+        }
+        if let Some(options) = schema["oneOf"].as_array() {
+            return options
+                .iter()
+                .find_map(|option| check(value, option, schemas, used).ok())
+                .ok_or_else(|| format!("{} fits none of {}", value, schema))
+        }
+
+        if let Some(options) = schema["enum"].as_array() {
+            if !options.contains(value) {
+                return Err(format!("{} isn't one of {:?}", value, options))
+            }
+        }
+        let fits = match schema["type"].as_str() {
+            Some("object") => value.is_object(),
+            Some("array") => value.is_array(),
+            Some("integer") => value.is_i64() || value.is_u64(),
+            Some("number") => value.is_number(),
+            Some("string") => value.is_string(),
+            Some("boolean") => value.is_boolean(),
+            _ => true
+        };
+        if !fits {
+            return Err(format!("{} isn't of type {}", value, schema["type"]))
+        }
+
+        for item in value.as_array().into_iter().flatten() {
+            check(item, &schema["items"], schemas, used)?;
+        }
+        if let (Some(fields), Some(properties)) = (value.as_object(), schema["properties"].as_object()) {
+            for (field, value) in fields {
+                let property = properties
+                    .get(field)
+                    .ok_or_else(|| format!("{} isn't described", field))?;
+                check(value, property, schemas, used)
+                    .map_err(|e| format!("{}: {}", field, e))?;
+            }
+            for required in schema["required"].as_array().into_iter().flatten() {
+                if !fields.contains_key(required.as_str().unwrap_or_default()) {
+                    return Err(format!("{} is missing", required))
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn sample(value: impl Serialize) -> Value {
+        serde_json::to_value(value).unwrap()
+    }
+
+    // requests are never serialized, the documented example has to be accepted instead
+    fn request<T: DeserializeOwned>(example: Value) -> Value {
+        if let Err(e) = serde_json::from_value::<T>(example.clone()) {
+            panic!("{} isn't accepted: {}", example, e)
+        }
+        example
+    }
+
+    #[test]
+    fn schemas_match_serialization() {
+        let rocket = rocket();
+        let ApiDocument(doc) = document(rocket.routes());
+        let error = Client::new(rocket)
+            .unwrap()
+            .get("/api/v1/game")
+            .dispatch()
+            .body_string()
+            .unwrap();
+
+        let conn = CONN
+            .lock()
+            .unwrap();
+        let mut samples = conn.test_transaction::<_, diesel::result::Error, _>(|| {
+            let (cat, questions) = insert_questions("Schemas", &["Schema?"], &conn)?;
+            let question = &questions[0];
+            let id = *question.id();
+
+            let report = QuestionReport::insert(&NewQuestionReport {
+                question_id: id,
+                reason: ReportReason::Typo,
+                comment: Some("Scheme?")
+            }, &conn)?;
+            let media = QuestionMedia::insert(&NewQuestionMedia {
+                question_id: id,
+                answer: None,
+                file_name: "schema.png",
+                content_type: "image/png"
+            }, &conn)?;
+            let translation = QuestionTranslation::upsert(&NewQuestionTranslation {
+                question_id: id,
+                locale: "de".into(),
+                string: "Schema?".into(),
+                correct: "0".into(),
+                incorrect: vec!["1".into(), "2".into(), "3".into()]
+            }, &conn)?;
+            Answer::insert(&NewAnswer {
+                question_id: id,
+                chosen: Some(1),
+                time_taken_ms: 1500,
+                joker_used: true,
+                game_id: None,
+                correct: false
+            }, &conn)?;
+
+            let admin = Admin::insert(&NewAdmin { name: "Schemas", password: "-" }, &conn).unwrap();
+            let (token, secret) = account::create_token(&admin, "Schemas", &[Scope::Read, Scope::Write], &conn).unwrap();
+            let player = Player::insert(&NewPlayer { name: "Schemas", password: "-" }, &conn).unwrap();
+            let state = game::new_game_state("Schemas".into(), None, "schemas", 7, &[cat.id()], vec![], Locale::En, &conn)?;
+
+            let flags = analytics::flags(20, &[20, 0, 0, 0])
+                .into_iter()
+                .chain(analytics::flags(20, &[0, 20, 0, 0]))
+                .map(|flag| ("Flag", sample(flag)));
+
+            Ok(flags.chain(vec![
+                ("Category", sample(&cat)),
+                ("Question", sample(question)),
+                ("JsonQuestions", sample(JsonQuestions { questions: questions.clone() })),
+                ("QuestionPage", sample(QuestionPage { questions: questions.clone(), page: 1, pages: 1, total: 1 })),
+                ("JsonClusters", sample(JsonClusters { clusters: vec![questions.clone()] })),
+                ("JsonCategories", sample(JsonCategories { categories: vec![cat.clone()] })),
+                ("JsonTags", sample(JsonTags { tags: vec!["schema".into()] })),
+                ("JsonReports", sample(JsonReports {
+                    questions: vec![ReportedQuestion { question: question.clone(), reports: vec![report] }]
+                })),
+                ("Analytics", sample(analytics::load(&conn)?)),
+                ("JsonMedia", sample(JsonMedia { media: vec![media.clone()] })),
+                ("JsonTranslations", sample(JsonTranslations { translations: vec![translation] })),
+                ("NewQuestionTranslation", request::<NewQuestionTranslation>(json!({
+                    "question_id": id,
+                    "locale": "de",
+                    "string": "Schema?",
+                    "correct": "0",
+                    "incorrect": ["1", "2", "3"]
+                }))),
+                ("JsonRevisions", sample(JsonRevisions { revisions: QuestionRevision::of_question(question.id(), &conn)? })),
+                ("JsonDiff", sample(JsonDiff {
+                    changes: vec![FieldChange { field: "string", old: "Schema?".into(), new: "Schemas?".into() }]
+                })),
+                ("JsonTokens", sample(JsonTokens { tokens: vec![token.clone()] })),
+                ("NewToken", request::<NewToken>(json!({ "name": "Schemas", "scopes": ["read", "write"] }))),
+                ("CreatedToken", sample(CreatedToken { token: secret, details: token })),
+                ("Joker", sample(Joker::from(["1", "2"]))),
+                ("DisplayData", sample(DisplayData::new(question, &[media], &cat.name, 30, true, 50, 3))),
+                ("NewGame", request::<v1::NewGame>(json!({
+                    "user": "Schemas",
+                    "categories": [id],
+                    "tags": ["schema"],
+                    "locale": "en",
+                    "practice": false,
+                    "duel": true
+                }))),
+                ("GameView", sample(GameView::from(&state))),
+                ("AnswerRequest", request::<AnswerRequest>(json!({ "answer": "0" }))),
+                ("AnswerResult", sample(AnswerResult {
+                    correct: true,
+                    correct_answer: "0".into(),
+                    points: 30,
+                    weighted_points: 30,
+                    game_over: false,
+                    duel: Some("code".into())
+                })),
+                ("Paused", sample(Paused { categories: vec![cat.clone()] })),
+                ("Resume", request::<Resume>(json!({ "categories": [id] }))),
+                ("Results", sample(Results {
+                    points: 30,
+                    weighted_points: 30,
+                    placement: Some(1),
+                    set_placement: None,
+                    practice_progress: Some(practice::progress(player.id(), &conn)?),
+                    duel: None
+                }))
+            ]).collect::<Vec<_>>())
+        });
+        samples.push(("OpenApi", doc));
+        samples.push(("ApiError", serde_json::from_str(&error).unwrap()));
+
+        let schemas = schemas();
+        let mut used = HashSet::new();
+        for (name, value) in &samples {
+            if let Err(e) = check(value, &schema_ref(name), &schemas, &mut used) {
+                panic!("{} doesn't match its schema: {}", value, e)
+            }
+        }
+
+        let unchecked = schemas
+            .as_object()
+            .unwrap()
+            .keys()
+            .filter(|name| !used.contains(*name))
+            .collect::<Vec<_>>();
+        assert_eq!(unchecked, Vec::<&String>::new())
+    }
+
+    #[test]
+    fn parameters_come_from_the_route() {
+        assert_eq!(openapi_path("/admin/revision_diff/<from>/<to>"), "/admin/revision_diff/{from}/{to}");
+        assert_eq!(openapi_path("/<path..>"), "/{path}");
+        assert_eq!(openapi_path("/"), "/")
+    }
+
+    // a value each documented field is accepted with
+    const FIELD_VALUES: &[(&str, &str)] = &[
+        ("user", "Tom"),
+        ("categories", "1"),
+        ("tags", "history"),
+        ("locale", "en"),
+        ("practice", "true"),
+        ("duel", "false"),
+        ("range", "week"),
+        ("sort", "points"),
+        ("page", "2"),
+        ("player", "Tom"),
+        ("set", "exact"),
+        ("team", "Owls"),
+        ("name", "Ann"),
+        ("voting", "majority")
+    ];
+
+    // all documented fields of a route's form, each with a value from above
+    fn documented_form(method: Method, path: &str) -> String {
+        let fields = match find(method, path).map(|op| op.body) {
+            Some(Form(fields)) => fields,
+            body => panic!("{} isn't documented with a form but {:?}", path, body)
+        };
+
+        fields
+            .iter()
+            .map(|field| FIELD_VALUES
+                .iter()
+                .find(|(name, _)| name == field)
+                .map(|(name, value)| format!("{}={}", name, value))
+                .unwrap_or_else(|| panic!("no value for the field {}", field))
+            )
+            .collect::<Vec<_>>()
+            .join("&")
+    }
+
+    fn accepts<'f, T: FromForm<'f>>(form: &'f str) -> bool {
+        T::from_form(&mut FormItems::from(form), true).is_ok()
+    }
+
+    // the derived forms can't miss a field, but the hand-written ones only know theirs in the parsing code
+    #[test]
+    fn documented_form_fields_are_accepted() {
+        assert!(accepts::<Settings>(&documented_form(Method::Post, "/play/new_game")));
+        assert!(accepts::<NewCategories>(&documented_form(Method::Post, "/play/resume")));
+        assert!(accepts::<NewCategories>(&documented_form(Method::Post, "/rooms/create")));
+        assert!(accepts::<LeaderboardQuery>(&documented_form(Method::Get, "/leaderboard")));
+        assert!(accepts::<NewTeam>(&documented_form(Method::Post, "/teams/create")))
+    }
+}
\ No newline at end of file
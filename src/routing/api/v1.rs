@@ -0,0 +1,508 @@
+//! The gameplay for clients that render it themselves, mounted at `/api/v1`.
+//! Games are tied to the session cookie, just like in the browser.
+
+use {
+    serde::{Serialize, Deserialize},
+    serde_json::Value,
+    rocket::{
+        get,
+        post,
+        delete,
+        State,
+        Request,
+        Response,
+        response::{self, Responder, status::Custom},
+        http::Status
+    },
+    rocket_contrib::json::{Json, JsonError},
+    crate::{
+        routing::pages::{
+            start::Settings,
+            play::{self, DisplayData, Joker, StartError}
+        },
+        models::{
+            self,
+            duels,
+            i18n::Locale,
+            practice::{self, BoxCount},
+            game::{GameState, Answered, AnswerError, JokerError, NextQuestionError, QuestionError, correct_ratio},
+            web::{NewSession, NewGameState, SyncedGameState, SyncedGameStates, EndGame, PlayerSession, Session, Visitor},
+            db::{
+                DbConn,
+                CategoryId,
+                models::{Category, Score}
+            }
+        }
+    }
+};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidRequest,
+    /// The session has no game, it never started one or the game is over.
+    NoGame,
+    NoQuestion,
+    /// The current question needs to be answered before the next one.
+    HasNotAnswered,
+    /// All questions of the selected categories have been asked,
+    /// the game can go on after resuming with other categories.
+    NoneRemaining,
+    JokerAlreadyUsed,
+    /// Practice mode keeps track of progress, so it needs a logged in player.
+    AccountRequired,
+    Internal
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 8] = [
+        ErrorCode::InvalidRequest,
+        ErrorCode::NoGame,
+        ErrorCode::NoQuestion,
+        ErrorCode::HasNotAnswered,
+        ErrorCode::NoneRemaining,
+        ErrorCode::JokerAlreadyUsed,
+        ErrorCode::AccountRequired,
+        ErrorCode::Internal
+    ];
+
+    fn status(self) -> Status {
+        match self {
+            ErrorCode::InvalidRequest => Status::BadRequest,
+            ErrorCode::NoGame | ErrorCode::NoQuestion => Status::NotFound,
+            ErrorCode::HasNotAnswered | ErrorCode::NoneRemaining | ErrorCode::JokerAlreadyUsed => Status::Conflict,
+            ErrorCode::AccountRequired => Status::Unauthorized,
+            ErrorCode::Internal => Status::InternalServerError
+        }
+    }
+
+    fn message(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "the request body is not valid",
+            ErrorCode::NoGame => "there is no game running in this session",
+            ErrorCode::NoQuestion => "there is no current question",
+            ErrorCode::HasNotAnswered => "the current question has not been answered yet",
+            ErrorCode::NoneRemaining => "there are no questions left in the selected categories",
+            ErrorCode::JokerAlreadyUsed => "the joker has already been used",
+            ErrorCode::AccountRequired => "practice mode requires a logged in player",
+            ErrorCode::Internal => "internal server error"
+        }
+    }
+}
+
+/// Every error is answered with `{"error": {"code": ..., "message": ...}}`,
+/// clients should only rely on the code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiError(pub ErrorCode);
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: ErrorDetails
+}
+
+#[derive(Serialize)]
+struct ErrorDetails {
+    code: ErrorCode,
+    message: &'static str
+}
+
+impl <'r> Responder<'r> for ApiError {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let body = ErrorBody {
+            error: ErrorDetails {
+                code: self.0,
+                message: self.0.message()
+            }
+        };
+
+        Response::build_from(Json(body).respond_to(request)?)
+            .status(self.0.status())
+            .ok()
+    }
+}
+
+impl From<NextQuestionError> for ApiError {
+    fn from(e: NextQuestionError) -> Self {
+        ApiError(match e {
+            NextQuestionError::HasNotAnswered => ErrorCode::HasNotAnswered,
+            NextQuestionError::NoneRemaining => ErrorCode::NoneRemaining
+        })
+    }
+}
+
+impl From<QuestionError> for ApiError {
+    fn from(e: QuestionError) -> Self {
+        ApiError(match e {
+            QuestionError::Query(_) => ErrorCode::Internal,
+            QuestionError::NoneRemaining => ErrorCode::NoneRemaining
+        })
+    }
+}
+
+impl From<JokerError> for ApiError {
+    fn from(e: JokerError) -> Self {
+        ApiError(match e {
+            JokerError::AlreadyUsed => ErrorCode::JokerAlreadyUsed,
+            JokerError::NoQuestion => ErrorCode::NoQuestion
+        })
+    }
+}
+
+impl From<AnswerError> for ApiError {
+    fn from(e: AnswerError) -> Self {
+        ApiError(match e {
+            AnswerError::Query(_) => ErrorCode::Internal,
+            AnswerError::NoQuestion => ErrorCode::NoQuestion
+        })
+    }
+}
+
+impl From<StartError> for ApiError {
+    fn from(e: StartError) -> Self {
+        ApiError(match e {
+            StartError::NeedsAccount => ErrorCode::AccountRequired,
+            StartError::Query(_) => ErrorCode::Internal
+        })
+    }
+}
+
+impl From<diesel::result::Error> for ApiError {
+    fn from(_: diesel::result::Error) -> Self {
+        ApiError(ErrorCode::Internal)
+    }
+}
+
+impl <'a> From<JsonError<'a>> for ApiError {
+    fn from(_: JsonError<'a>) -> Self {
+        ApiError(ErrorCode::InvalidRequest)
+    }
+}
+
+type ApiResult<T> = Result<Json<T>, ApiError>;
+
+fn running(game_state: Option<SyncedGameState>) -> Result<SyncedGameState, ApiError> {
+    game_state.ok_or(ApiError(ErrorCode::NoGame))
+}
+
+#[derive(Serialize, Debug)]
+pub struct GameView {
+    points: i32,
+    weighted_points: i32,
+    joker: bool,
+    elapsed_secs: u64,
+    practice: bool,
+    seed: u64
+}
+
+impl From<&GameState> for GameView {
+    fn from(game_state: &GameState) -> Self {
+        GameView {
+            points: game_state.points(),
+            weighted_points: game_state.weighted_points(),
+            joker: game_state.joker(),
+            elapsed_secs: game_state.stopwatch.elapsed().as_secs(),
+            practice: game_state.is_practice(),
+            seed: game_state.seed()
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NewGame {
+    user: String,
+    categories: Vec<CategoryId>,
+    #[serde(default)]
+    tags: Vec<String>,
+    locale: Option<Locale>,
+    #[serde(default)]
+    practice: bool,
+    #[serde(default)]
+    duel: bool
+}
+
+/// Replaces whatever game the session had before.
+#[post("/games", data = "<new>")]
+pub fn new_game(
+    new: Result<Json<NewGame>, JsonError>,
+    _sess: NewSession,
+    new_game_state: NewGameState,
+    request_locale: Locale,
+    player: Option<PlayerSession>,
+    visitor: Visitor,
+    conn: DbConn
+) -> Result<Custom<Json<GameView>>, ApiError> {
+    let new = new?.into_inner();
+    let locale = new.locale.unwrap_or(request_locale);
+    let settings = Settings {
+        user: new.user,
+        categories: new.categories,
+        tags: new.tags,
+        locale: Some(locale),
+        practice: new.practice,
+        duel: new.duel
+    };
+
+    let state = play::start_game(settings, locale, player, &visitor, &conn)?;
+    let view = GameView::from(&state);
+    new_game_state.set(state);
+    Ok(Custom(Status::Created, Json(view)))
+}
+
+#[get("/game")]
+pub fn game(game_state: Option<SyncedGameState>) -> ApiResult<GameView> {
+    let game_state = running(game_state)?;
+    Ok(Json(GameView::from(&*game_state)))
+}
+
+#[get("/game/question")]
+pub fn question(game_state: Option<SyncedGameState>, conn: DbConn) -> ApiResult<Value> {
+    let game_state = running(game_state)?;
+    display(&game_state, &conn)
+}
+
+/// The same data the play page is rendered with.
+fn display(game_state: &GameState, conn: &DbConn) -> ApiResult<Value> {
+    let elapsed_secs = game_state.stopwatch.elapsed().as_secs();
+    let (cat, question) = game_state
+        .current_question()
+        .ok_or(ApiError(ErrorCode::NoQuestion))?;
+
+    let display = DisplayData::new(
+        question,
+        &question.media(conn)?,
+        &cat.name,
+        game_state.points(),
+        game_state.joker(),
+        correct_ratio(question, conn)?,
+        elapsed_secs
+    );
+
+    serde_json::to_value(display)
+        .map(Json)
+        .map_err(|_| ApiError(ErrorCode::Internal))
+}
+
+/// Moves on to the next question, loading more from the selected categories if needed.
+#[post("/game/next")]
+pub fn next(game_state: Option<SyncedGameState>, conn: DbConn) -> ApiResult<Value> {
+    let mut game_state = running(game_state)?;
+    match game_state.next_question() {
+        Ok(_) => {},
+        Err(NextQuestionError::NoneRemaining) => {
+            if let Err(e) = game_state.load_more_questions(&conn) {
+                // just like the intermission page, the time doesn't count until resuming
+                if let QuestionError::NoneRemaining = e {
+                    game_state.stopwatch.pause();
+                }
+                return Err(e.into())
+            }
+            game_state.next_question()?;
+        },
+        Err(e) => return Err(e.into())
+    }
+
+    display(&game_state, &conn)
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AnswerRequest {
+    answer: String
+}
+
+#[derive(Serialize, Debug)]
+pub struct AnswerResult {
+    pub(crate) correct: bool,
+    pub(crate) correct_answer: String,
+    pub(crate) points: i32,
+    pub(crate) weighted_points: i32,
+    /// A wrong answer ends the game, except in practice mode.
+    pub(crate) game_over: bool,
+    /// The duel the game challenged others to or answered, once it's over.
+    pub(crate) duel: Option<String>
+}
+
+#[post("/game/answer", data = "<request>")]
+pub fn answer(
+    request: Result<Json<AnswerRequest>, JsonError>,
+    sess: Option<Session>,
+    game_states: State<SyncedGameStates>,
+    conn: DbConn
+) -> ApiResult<AnswerResult> {
+    let request = request?;
+    let sess = sess.ok_or(ApiError(ErrorCode::NoGame))?;
+    let mut game_states = game_states
+        .lock()
+        .map_err(|_| ApiError(ErrorCode::Internal))?;
+    let game_state = game_states
+        .get_mut(&sess)
+        .ok_or(ApiError(ErrorCode::NoGame))?;
+
+    let correct_answer = game_state.current_question
+        .as_ref()
+        .map(|question| question.correct.clone())
+        .ok_or(ApiError(ErrorCode::NoQuestion))?;
+    let correct = match models::game::answer(&request.answer, game_state, &conn)? {
+        Answered::Correctly => true,
+        Answered::Incorrectly => false
+    };
+    let game_over = !correct && !game_state.is_practice();
+    let (points, weighted_points) = (game_state.points(), game_state.weighted_points());
+
+    let ended = if game_over {
+        game_states.remove(&sess)
+    } else {
+        None
+    };
+    let duel = match ended {
+        Some(ended) => duels::conclude(&ended, &conn)?,
+        None => None
+    };
+
+    Ok(Json(AnswerResult {
+        correct,
+        correct_answer,
+        points,
+        weighted_points,
+        game_over,
+        duel
+    }))
+}
+
+#[post("/game/joker")]
+pub fn joker(game_state: Option<SyncedGameState>) -> ApiResult<Joker> {
+    let mut game_state = running(game_state)?;
+    let picks = game_state.use_joker()?;
+    Ok(Json(Joker::from(picks)))
+}
+
+#[derive(Serialize, Debug)]
+pub struct Paused {
+    pub(crate) categories: Vec<Category>
+}
+
+/// Stops the clock, the answer lists the categories to go on with.
+#[post("/game/pause")]
+pub fn pause(game_state: Option<SyncedGameState>, conn: DbConn) -> ApiResult<Paused> {
+    let mut game_state = running(game_state)?;
+    game_state.stopwatch.pause();
+    Ok(Json(Paused {
+        categories: Category::load_all(&conn)?
+    }))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Resume {
+    categories: Vec<CategoryId>
+}
+
+#[post("/game/resume", data = "<resume>")]
+pub fn resume(resume: Result<Json<Resume>, JsonError>, game_state: Option<SyncedGameState>, conn: DbConn) -> ApiResult<GameView> {
+    let resume = resume?;
+    let mut game_state = running(game_state)?;
+    let categories = Category::load_with_ids(&resume.categories, &conn)?;
+    game_state.set_categories(categories);
+    game_state.stopwatch.resume();
+    Ok(Json(GameView::from(&*game_state)))
+}
+
+#[derive(Serialize, Debug)]
+pub struct Results {
+    pub(crate) points: i32,
+    pub(crate) weighted_points: i32,
+    /// None for practice games, they don't make it onto the leaderboard.
+    pub(crate) placement: Option<u64>,
+    pub(crate) set_placement: Option<u64>,
+    pub(crate) practice_progress: Option<Vec<BoxCount>>,
+    pub(crate) duel: Option<String>
+}
+
+#[delete("/game")]
+pub fn end_game(end: Option<EndGame>, conn: DbConn) -> ApiResult<Results> {
+    let EndGame { game_state } = end.ok_or(ApiError(ErrorCode::NoGame))?;
+    let mut results = Results {
+        points: game_state.points(),
+        weighted_points: game_state.weighted_points(),
+        placement: None,
+        set_placement: None,
+        practice_progress: None,
+        duel: None
+    };
+
+    if let (true, Some(player)) = (game_state.is_practice(), game_state.player()) {
+        results.practice_progress = Some(practice::progress(player, &conn)?);
+        return Ok(Json(results))
+    }
+
+    let user_score = Score::insert(&game_state.score(), &conn)?;
+    let scores = models::game::scores(&user_score, &conn)?;
+    results.placement = Some(scores.placement);
+    results.set_placement = Some(scores.set_placement);
+    results.duel = duels::conclude(&game_state, &conn)?;
+    Ok(Json(results))
+}
+
+#[cfg(test)]
+mod test {
+    use {
+        crate::test::rocket,
+        rocket::{
+            local::Client,
+            http::{
+                Status,
+                ContentType
+            }
+        }
+    };
+
+    const NEW_GAME: &str = r#"{"user": "Tom", "categories": [1]}"#;
+
+    #[test]
+    fn missing_game_has_error_code() {
+        let rocket = rocket();
+        let client = Client::new(rocket).unwrap();
+
+        let mut resp = client.get("/api/v1/game")
+            .dispatch();
+
+        assert_eq!(resp.status(), Status::NotFound);
+        assert!(resp.body_string().unwrap().contains(r#""code":"no_game""#))
+    }
+
+    #[test]
+    fn invalid_body_is_rejected() {
+        let rocket = rocket();
+        let client = Client::new(rocket).unwrap();
+
+        let mut resp = client.post("/api/v1/games")
+            .header(ContentType::JSON)
+            .body("user=Tom&categories=1")
+            .dispatch();
+
+        assert_eq!(resp.status(), Status::BadRequest);
+        assert!(resp.body_string().unwrap().contains(r#""code":"invalid_request""#))
+    }
+
+    #[test]
+    fn joker_can_only_be_used_once() {
+        let rocket = rocket();
+        let client = Client::new(rocket).unwrap();
+
+        let status = client.post("/api/v1/games")
+            .header(ContentType::JSON)
+            .body(NEW_GAME)
+            .dispatch()
+            .status();
+        assert_eq!(status, Status::Created);
+
+        client.post("/api/v1/game/next")
+            .dispatch();
+        client.post("/api/v1/game/joker")
+            .dispatch();
+
+        let mut resp = client.post("/api/v1/game/joker")
+            .dispatch();
+
+        assert_eq!(resp.status(), Status::Conflict);
+        assert!(resp.body_string().unwrap().contains(r#""code":"joker_already_used""#))
+    }
+}
\ No newline at end of file
@@ -3,14 +3,27 @@ use {
     std::path::{PathBuf, Path},
     rocket::{
         get,
-        http::Status,
-        response::NamedFile,
+        State,
+        Request,
+        Response,
+        http::{Status, ContentType},
+        response::{NamedFile, Responder},
+    },
+    rocket_contrib::templates::Template,
+    crate::models::{
+        web::Or500,
+        i18n::{self, Locale},
+        media::{MediaDir, MediaType},
+        db::{
+            DbConn,
+            ops::MediaId,
+            models::QuestionMedia
+        }
     }
 };
 
 const ROOT: &str = env!("CARGO_MANIFEST_DIR");
 static STATIC_CONTENT_PATH: Lazy<PathBuf> = Lazy::new(static_content_path);
-static STATIC_PAGE_PATH: Lazy<PathBuf> = Lazy::new(static_page_path);
 
 fn static_content_path() -> PathBuf {
     Path::new(ROOT)
@@ -19,14 +32,6 @@ fn static_content_path() -> PathBuf {
         .join("static")
 }
 
-fn static_page_path() -> PathBuf {
-    Path::new(ROOT)
-        .join("content")
-        .join("public")
-        .join("html")
-        .join("static")
-}
-
 #[get("/favicon.ico")]
 pub fn favicon() -> Result<NamedFile, Status> {
     static_content("favicon.ico".into())
@@ -38,15 +43,33 @@ pub fn static_content(path: PathBuf) -> Result<NamedFile, Status> {
         .map_err(|_| Status::NotFound)
 }
 
-#[get("/<path..>", rank = 99)]
-pub fn static_page(path: PathBuf) -> Result<NamedFile, Status> {
-    let mut path = STATIC_PAGE_PATH.join(path);
-    path.set_extension("html");
-    NamedFile::open(path)
+#[get("/content/media/<id>")]
+pub fn media(id: MediaId, dir: State<MediaDir>, conn: DbConn) -> Result<MediaFile, Status> {
+    let media = QuestionMedia::with_id(id, &conn)
+        .or_500()?
+        .ok_or(Status::NotFound)?;
+    // files stored before uploads were checked may be anything
+    let content_type = MediaType::of_stored(&media.content_type)
+        .map_or(ContentType::Binary, MediaType::content_type);
+
+    NamedFile::open(dir.path(&media))
+        .map(|file| MediaFile(content_type, file))
         .map_err(|_| Status::NotFound)
 }
 
+/// An uploaded file, which browsers may only treat as the type we send.
+pub struct MediaFile(ContentType, NamedFile);
+
+impl <'r> Responder<'r> for MediaFile {
+    fn respond_to(self, request: &Request) -> Result<Response<'r>, Status> {
+        Response::build_from(self.1.respond_to(request)?)
+            .header(self.0)
+            .raw_header("X-Content-Type-Options", "nosniff")
+            .ok()
+    }
+}
+
 #[get("/")]
-pub fn index() -> Result<NamedFile, Status> {
-    static_page("index.html".into())
+pub fn index(locale: Locale) -> Template {
+    i18n::render("index", locale, ())
 }
\ No newline at end of file
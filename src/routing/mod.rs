@@ -1,5 +1,6 @@
 pub mod pages;
 pub mod general;
 pub mod catchers;
+pub mod api;
 
 pub use {pages::*, general::*};
\ No newline at end of file
@@ -1,5 +1,5 @@
 use {
-    serde::Serialize,
+    serde::{Serialize, Deserialize},
     serde_repr::Serialize_repr,
     rocket_contrib::{
         json::Json,
@@ -11,6 +11,8 @@ use {
         put,
         post,
         delete,
+        Data,
+        State,
         FromForm,
         response::Redirect,
         request::{Form, FromFormValue},
@@ -23,19 +25,50 @@ use {
     },
     crate::models::{
         web::{AdminGuard, Login, Or500},
-        account::{self, Credentials},
+        account::{self, Credentials, Scope},
+        media::{self, MediaDir, MediaError},
+        similarity::{self, Duplicates},
+        analytics::{self, Analytics},
+        i18n::{self, Locale, AvailableLocale},
         db::{
             DbConn,
             AdminError,
-            ops::QuestionId,
-            models::{Category, Question, NewQuestion, NewCategory}
+            ops::{
+                QuestionId,
+                CategoryId,
+                MediaId,
+                RevisionId,
+                ApiTokenId,
+                FieldChange,
+                StatusError,
+                PurgeError,
+                QuestionStatus,
+                QuestionFilter,
+                QuestionOrder
+            },
+            models::{
+                Admin,
+                ApiToken,
+                Category,
+                Question,
+                QuestionMedia,
+                QuestionTranslation,
+                QuestionRevision,
+                QuestionReport,
+                NewQuestion,
+                NewCategory,
+                NewQuestionTranslation
+            }
         },
     }
 };
 
 #[derive(Serialize)]
 struct DisplayData {
-    error: Option<RegisterError>
+    error: Option<RegisterError>,
+    duplicate: Option<QuestionId>,
+    similar: Option<QuestionId>,
+    locales: Vec<AvailableLocale>
 }
 
 #[derive(Serialize_repr, Debug, Copy, Clone, PartialEq, Eq)]
@@ -74,23 +107,40 @@ impl UriDisplay<Query> for RegisterError {
 
 impl_from_uri_param_identity!([Query] RegisterError);
 
-#[get("/admin?<error>")]
-pub fn admin(error: Option<RegisterError>, _guard: AdminGuard) -> Template {
-    Template::render(
+/// `duplicate` is set if a question was rejected because of an existing one with the same text,
+/// `similar` if a question was added but looks a lot like an existing one.
+#[get("/admin?<error>&<duplicate>&<similar>")]
+pub fn admin(
+    error: Option<RegisterError>,
+    duplicate: Option<QuestionId>,
+    similar: Option<QuestionId>,
+    locale: Locale,
+    _guard: AdminGuard
+) -> Template {
+    i18n::render(
         if error.is_some() { "admin_error" } else { "admin" },
+        locale,
         DisplayData {
-            error
+            error,
+            duplicate,
+            similar,
+            locales: i18n::available(locale)
         }
     )
 }
 
+#[get("/login")]
+pub fn login(locale: Locale) -> Template {
+    i18n::render("login", locale, ())
+}
+
 #[post("/login/verify", data = "<credentials>")]
 pub fn verify(credentials: Form<Credentials>, conn: DbConn) -> Login<Redirect> {
     let success = account::verify(&credentials, &conn)
         .unwrap_or_default();
 
     if success {
-        Login::Success(Redirect::to("/admin"))
+        Login::Success(credentials.into_inner().name, Redirect::to("/admin"))
     } else {
         Login::Failure(Redirect::to("/"))
     }
@@ -101,10 +151,10 @@ pub fn register(credentials: Form<Credentials>, conn: DbConn) -> Redirect {
     match account::register(&credentials, &conn) {
         Ok(()) => Redirect::to("/admin"),
         Err(e) => match e {
-            account::Error::Hash(_) => Redirect::to(uri!(admin: RegisterError::Other)),
+            account::Error::Hash(_) => Redirect::to(uri!(admin: error = RegisterError::Other, duplicate = _, similar = _)),
             account::Error::Insert(e) => match e {
-                AdminError::Query(_) => Redirect::to(uri!(admin: RegisterError::Other)),
-                AdminError::NameInUse => Redirect::to(uri!(admin: RegisterError::NameInUse))
+                AdminError::Query(_) => Redirect::to(uri!(admin: error = RegisterError::Other, duplicate = _, similar = _)),
+                AdminError::NameInUse => Redirect::to(uri!(admin: error = RegisterError::NameInUse, duplicate = _, similar = _))
             }
         }
     }
@@ -118,23 +168,45 @@ pub struct FormQuestion {
     incorrect2: String,
     incorrect3: String,
     category: i32,
+    tags: String,
+    locale: Locale
+}
+
+// tags are entered as a single comma separated list
+fn parse_tags(tags: &str) -> Vec<String> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|tag| !tag.is_empty())
+        .map(String::from)
+        .collect()
 }
 
 #[post("/admin/add_question", data = "<form>")]
-pub fn add_question(form: Form<FormQuestion>, _guard: AdminGuard, conn: DbConn) -> Result<Redirect, Status> {
+pub fn add_question(form: Form<FormQuestion>, guard: AdminGuard, conn: DbConn) -> Result<Redirect, Status> {
     let FormQuestion {
-        question, correct, incorrect1, incorrect2, incorrect3, category
+        question, correct, incorrect1, incorrect2, incorrect3, category, tags, locale
     } = form.into_inner();
 
     let new = NewQuestion {
         category_id: category,
         string: &question,
         correct: &correct,
-        incorrect: &[incorrect1, incorrect2, incorrect3]
+        incorrect: &[incorrect1, incorrect2, incorrect3],
+        tags: &parse_tags(&tags),
+        locale: locale.code()
     };
 
-    Question::insert(&new, &conn)
-        .map(|_| Redirect::to("/admin"))
+    let similar = match Question::duplicates(&question, None, &conn).or_500()? {
+        Duplicates::Exact(id) => return Ok(Redirect::to(uri!(admin: error = _, duplicate = id, similar = _))),
+        Duplicates::Similar(ids) => ids.first().copied(),
+        Duplicates::None => None
+    };
+
+    Question::insert(&new, guard.name(), &conn)
+        .map(|_| match similar {
+            Some(id) => Redirect::to(uri!(admin: error = _, duplicate = _, similar = id)),
+            None => Redirect::to("/admin")
+        })
         .or_500()
 }
 
@@ -155,7 +227,10 @@ pub fn add_category(form: Form<FormCategory>, _guard: AdminGuard, conn: DbConn)
 }
 
 pub mod api {
-    use super::*;
+    use {
+        super::*,
+        std::collections::HashMap
+    };
 
     #[delete("/admin/delete_question/<id>")]
     pub fn delete_question(id: QuestionId, _guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
@@ -163,24 +238,145 @@ pub mod api {
             .or_500()
     }
 
+    #[get("/admin/trashed_questions")]
+    pub fn trashed_questions(_guard: AdminGuard, conn: DbConn) -> Result<Json<JsonQuestions>, Status> {
+        Question::load_trashed(&conn)
+            .map(|questions| JsonQuestions { questions })
+            .map(Json)
+            .or_500()
+    }
+
+    #[put("/admin/restore_question/<id>")]
+    pub fn restore_question(id: QuestionId, _guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
+        if Question::restore(id, &conn).or_500()? {
+            Ok(())
+        } else {
+            Err(Status::NotFound)
+        }
+    }
+
+    /// Only questions that have been moved to the trash before and were never answered can be purged.
+    #[delete("/admin/purge_question/<id>")]
+    pub fn purge_question(id: QuestionId, dir: State<MediaDir>, _guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
+        let question = Question::load(id, &conn)
+            .or_500()?
+            .ok_or(Status::NotFound)?;
+
+        if question.deleted_at.is_none() {
+            return Err(Status::Conflict)
+        }
+
+        let media = QuestionMedia::of_question(id, &conn)
+            .or_500()?;
+
+        Question::purge(id, &conn)
+            .map_err(|e| match e {
+                PurgeError::Answered => Status::Conflict,
+                PurgeError::Query(_) => Status::InternalServerError
+            })?;
+
+        media
+            .iter()
+            .map(|media| media::remove(media, &dir))
+            .collect::<Result<_, _>>()
+            .or_500()
+    }
+
+    /// Edits that turn a question into an exact duplicate of another are refused.
     #[put("/admin/edit_question", data = "<question>")]
-    pub fn edit_question(question: Json<Question>, _guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
+    pub fn edit_question(question: Json<Question>, guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
         let question = question.into_inner();
         let new = NewQuestion {
             category_id: *question.category_id(),
             string: &question.string,
             correct: &question.correct,
-            incorrect: &question.incorrect
+            incorrect: &question.incorrect,
+            tags: &question.tags,
+            locale: &question.locale
         };
 
-        Question::update(question.id(), new, &conn)
+        if let Duplicates::Exact(_) = Question::duplicates(new.string, Some(question.id()), &conn).or_500()? {
+            return Err(Status::Conflict)
+        }
+
+        Question::update(question.id(), new, guard.name(), &conn)
             .map(drop)
             .or_500()
     }
 
     #[derive(Serialize)]
     pub struct JsonQuestions {
-        questions: Vec<Question>
+        pub(crate) questions: Vec<Question>
+    }
+
+    #[get("/admin/analytics")]
+    pub fn question_analytics(_guard: AdminGuard, conn: DbConn) -> Result<Json<Analytics>, Status> {
+        analytics::load(&conn)
+            .map(Json)
+            .or_500()
+    }
+
+    #[derive(Serialize)]
+    pub struct ReportedQuestion {
+        pub(crate) question: Question,
+        pub(crate) reports: Vec<QuestionReport>
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonReports {
+        pub(crate) questions: Vec<ReportedQuestion>
+    }
+
+    #[get("/admin/open_reports")]
+    pub fn open_reports(_guard: AdminGuard, conn: DbConn) -> Result<Json<JsonReports>, Status> {
+        QuestionReport::open(&conn)
+            .map(|open| open
+                .into_iter()
+                .map(|(question, reports)| ReportedQuestion { question, reports })
+                .collect()
+            )
+            .map(|questions| JsonReports { questions })
+            .map(Json)
+            .or_500()
+    }
+
+    /// Resolves all open reports about a question at once.
+    #[post("/admin/resolve_reports/<id>")]
+    pub fn resolve_reports(id: QuestionId, guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
+        QuestionReport::resolve(id, guard.name(), &conn)
+            .map(drop)
+            .or_500()
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonClusters {
+        pub(crate) clusters: Vec<Vec<Question>>
+    }
+
+    /// Groups the whole question bank into clusters of likely duplicates.
+    #[get("/admin/duplicate_questions")]
+    pub fn duplicate_questions(_guard: AdminGuard, conn: DbConn) -> Result<Json<JsonClusters>, Status> {
+        let mut questions = Question::load_all(&conn)
+            .or_500()?
+            .into_iter()
+            .map(|question| (question.id(), question))
+            .collect::<HashMap<_, _>>();
+
+        let texts = questions
+            .values()
+            .map(|question| (question.id(), question.string.clone()))
+            .collect::<Vec<_>>();
+
+        let clusters = similarity::clusters(&texts)
+            .into_iter()
+            .map(|ids| ids
+                .iter()
+                .filter_map(|id| questions.remove(id))
+                .collect()
+            )
+            .collect();
+
+        Ok(Json(JsonClusters { clusters }))
     }
 
     #[get("/admin/all_questions")]
@@ -195,9 +391,84 @@ pub mod api {
             .or_500()
     }
 
+    const PAGE_SIZE: i64 = 50;
+
+    #[derive(FromForm, Debug)]
+    pub struct QuestionQuery {
+        search: Option<String>,
+        category: Option<CategoryId>,
+        status: Option<QuestionStatus>,
+        min_rate: Option<i32>,
+        max_rate: Option<i32>,
+        sort: Option<QuestionOrder>,
+        desc: bool,
+        page: Option<i64>
+    }
+
+    #[derive(Serialize)]
+    pub struct QuestionPage {
+        pub(crate) questions: Vec<Question>,
+        pub(crate) page: i64,
+        pub(crate) pages: i64,
+        pub(crate) total: i64
+    }
+
+    #[get("/admin/questions?<query..>")]
+    pub fn search_questions(query: Form<QuestionQuery>, _guard: AdminGuard, conn: DbConn) -> Result<Json<QuestionPage>, Status> {
+        let query = query.into_inner();
+        let filter = QuestionFilter {
+            search: query.search
+                .map(|search| search.trim().to_owned())
+                .filter(|search| !search.is_empty()),
+            category: query.category,
+            status: query.status,
+            min_correct_rate: query.min_rate,
+            max_correct_rate: query.max_rate
+        };
+
+        let total = Question::count(&filter, &conn)
+            .or_500()?;
+        let pages = (total + PAGE_SIZE - 1) / PAGE_SIZE;
+        let page = query.page
+            .unwrap_or(1)
+            .max(1)
+            .min(pages.max(1));
+
+        let sort = query.sort.unwrap_or_default();
+        let questions = Question::search(&filter, sort, query.desc, (page - 1) * PAGE_SIZE, PAGE_SIZE, &conn)
+            .or_500()?;
+
+        Ok(Json(QuestionPage { questions, page, pages, total }))
+    }
+
+    #[get("/admin/tagged_questions?<tag>")]
+    pub fn tagged_questions(tag: String, _guard: AdminGuard, conn: DbConn) -> Result<Json<JsonQuestions>, Status> {
+        Question::load_tagged(&tag, &conn)
+            .map(|mut questions| {
+                questions.sort_unstable_by_key(Question::id);
+                questions
+            })
+            .map(|questions| JsonQuestions { questions })
+            .map(Json)
+            .or_500()
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonTags {
+        pub(crate) tags: Vec<String>
+    }
+
+    #[get("/admin/all_tags")]
+    pub fn all_tags(_guard: AdminGuard, conn: DbConn) -> Result<Json<JsonTags>, Status> {
+        Question::all_tags(&conn)
+            .map(|tags| JsonTags { tags })
+            .map(Json)
+            .or_500()
+    }
+
     #[derive(Serialize)]
     pub struct JsonCategories {
-        categories: Vec<Category>
+        pub(crate) categories: Vec<Category>
     }
 
     #[get("/admin/all_categories")]
@@ -207,4 +478,239 @@ pub mod api {
             .map(Json)
             .or_500()
     }
+
+    #[post("/admin/upload_media/<id>?<answer>", data = "<data>")]
+    pub fn upload_media(
+        id: QuestionId,
+        answer: Option<i16>,
+        data: Data,
+        dir: State<MediaDir>,
+        _guard: AdminGuard,
+        conn: DbConn
+    ) -> Result<Json<QuestionMedia>, Status> {
+        Question::load(id, &conn)
+            .or_500()?
+            .ok_or(Status::NotFound)?;
+
+        media::store(id, answer, data, &dir, &conn)
+            .map(Json)
+            .map_err(|e| match e {
+                MediaError::UnsupportedType => Status::UnsupportedMediaType,
+                MediaError::InvalidAnswer => Status::BadRequest,
+                MediaError::TooLarge => Status::PayloadTooLarge,
+                MediaError::Io(_) | MediaError::Query(_) => Status::InternalServerError
+            })
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonMedia {
+        pub(crate) media: Vec<QuestionMedia>
+    }
+
+    #[get("/admin/question_media/<id>")]
+    pub fn question_media(id: QuestionId, _guard: AdminGuard, conn: DbConn) -> Result<Json<JsonMedia>, Status> {
+        QuestionMedia::of_question(id, &conn)
+            .map(|media| JsonMedia { media })
+            .map(Json)
+            .or_500()
+    }
+
+    #[delete("/admin/delete_media/<id>")]
+    pub fn delete_media(id: MediaId, dir: State<MediaDir>, _guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
+        QuestionMedia::delete(id, &conn)
+            .or_500()?
+            .ok_or(Status::NotFound)
+            .and_then(|media| media::remove(&media, &dir)
+                .or_500()
+            )
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonTranslations {
+        pub(crate) translations: Vec<QuestionTranslation>
+    }
+
+    #[get("/admin/question_translations/<id>")]
+    pub fn question_translations(id: QuestionId, _guard: AdminGuard, conn: DbConn) -> Result<Json<JsonTranslations>, Status> {
+        QuestionTranslation::of_question(id, &conn)
+            .map(|translations| JsonTranslations { translations })
+            .map(Json)
+            .or_500()
+    }
+
+    #[put("/admin/translate_question", data = "<translation>")]
+    pub fn translate_question(
+        translation: Json<NewQuestionTranslation>,
+        _guard: AdminGuard,
+        conn: DbConn
+    ) -> Result<Json<QuestionTranslation>, Status> {
+        if Locale::from_code(&translation.locale).is_none() || translation.incorrect.len() != 3 {
+            return Err(Status::BadRequest)
+        }
+
+        Question::load(translation.question_id(), &conn)
+            .or_500()?
+            .ok_or(Status::NotFound)?;
+
+        QuestionTranslation::upsert(&translation, &conn)
+            .map(Json)
+            .or_500()
+    }
+
+    #[delete("/admin/delete_translation/<id>/<locale>")]
+    pub fn delete_translation(id: QuestionId, locale: String, _guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
+        let locale = Locale::from_code(&locale)
+            .ok_or(Status::BadRequest)?;
+
+        QuestionTranslation::delete(id, locale, &conn)
+            .or_500()
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonRevisions {
+        pub(crate) revisions: Vec<QuestionRevision>
+    }
+
+    #[get("/admin/question_revisions/<id>")]
+    pub fn question_revisions(id: QuestionId, _guard: AdminGuard, conn: DbConn) -> Result<Json<JsonRevisions>, Status> {
+        QuestionRevision::of_question(id, &conn)
+            .map(|revisions| JsonRevisions { revisions })
+            .map(Json)
+            .or_500()
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonDiff {
+        pub(crate) changes: Vec<FieldChange>
+    }
+
+    #[get("/admin/revision_diff/<from>/<to>")]
+    pub fn revision_diff(from: RevisionId, to: RevisionId, _guard: AdminGuard, conn: DbConn) -> Result<Json<JsonDiff>, Status> {
+        let load = |id| QuestionRevision::with_id(id, &conn)
+            .or_500()?
+            .ok_or(Status::NotFound);
+        let (from, to) = (load(from)?, load(to)?);
+
+        if from.question_id() != to.question_id() {
+            return Err(Status::BadRequest)
+        }
+
+        Ok(Json(JsonDiff {
+            changes: from.diff(&to)
+        }))
+    }
+
+    /// Like editing, a revision can't be restored over an exact duplicate.
+    #[post("/admin/restore_revision/<id>")]
+    pub fn restore_revision(id: RevisionId, guard: AdminGuard, conn: DbConn) -> Result<Json<Question>, Status> {
+        let revision = QuestionRevision::with_id(id, &conn)
+            .or_500()?
+            .ok_or(Status::NotFound)?;
+
+        if let Duplicates::Exact(_) = Question::duplicates(&revision.string, Some(revision.question_id()), &conn).or_500()? {
+            return Err(Status::Conflict)
+        }
+
+        revision
+            .restore(guard.name(), &conn)
+            .map(Json)
+            .or_500()
+    }
+
+    fn status_result(result: Result<Question, StatusError>) -> Result<Json<Question>, Status> {
+        result
+            .map(Json)
+            .map_err(|e| match e {
+                StatusError::Query(_) => Status::InternalServerError,
+                StatusError::NotFound => Status::NotFound,
+                StatusError::InvalidTransition => Status::Conflict,
+                StatusError::SameReviewer => Status::Forbidden
+            })
+    }
+
+    #[post("/admin/submit_question/<id>")]
+    pub fn submit_question(id: QuestionId, guard: AdminGuard, conn: DbConn) -> Result<Json<Question>, Status> {
+        status_result(Question::submit_for_review(id, guard.name(), &conn))
+    }
+
+    #[post("/admin/approve_question/<id>")]
+    pub fn approve_question(id: QuestionId, guard: AdminGuard, conn: DbConn) -> Result<Json<Question>, Status> {
+        status_result(Question::approve(id, guard.name(), &conn))
+    }
+
+    #[post("/admin/reject_question/<id>")]
+    pub fn reject_question(id: QuestionId, _guard: AdminGuard, conn: DbConn) -> Result<Json<Question>, Status> {
+        status_result(Question::reject(id, &conn))
+    }
+
+    #[post("/admin/retire_question/<id>")]
+    pub fn retire_question(id: QuestionId, _guard: AdminGuard, conn: DbConn) -> Result<Json<Question>, Status> {
+        status_result(Question::retire(id, &conn))
+    }
+
+    #[post("/admin/reopen_question/<id>")]
+    pub fn reopen_question(id: QuestionId, _guard: AdminGuard, conn: DbConn) -> Result<Json<Question>, Status> {
+        status_result(Question::reopen(id, &conn))
+    }
+
+    /// Tokens can only be managed when logged in, so a leaked one can't make more.
+    fn logged_in_admin(guard: &AdminGuard, conn: &DbConn) -> Result<Admin, Status> {
+        if guard.is_token() {
+            return Err(Status::Forbidden)
+        }
+
+        Admin::named(guard.name(), conn)
+            .or_500()?
+            .ok_or(Status::Unauthorized)
+    }
+
+    #[derive(Serialize)]
+    pub struct JsonTokens {
+        pub(crate) tokens: Vec<ApiToken>
+    }
+
+    #[get("/admin/api_tokens")]
+    pub fn api_tokens(guard: AdminGuard, conn: DbConn) -> Result<Json<JsonTokens>, Status> {
+        let admin = logged_in_admin(&guard, &conn)?;
+        ApiToken::of_admin(admin.id(), &conn)
+            .map(|tokens| JsonTokens { tokens })
+            .map(Json)
+            .or_500()
+    }
+
+    #[derive(Deserialize, Debug)]
+    pub struct NewToken {
+        name: String,
+        scopes: Vec<Scope>
+    }
+
+    #[derive(Serialize)]
+    pub struct CreatedToken {
+        /// Only ever shown in this response.
+        pub(crate) token: String,
+        pub(crate) details: ApiToken
+    }
+
+    #[post("/admin/api_tokens", data = "<new>")]
+    pub fn create_api_token(new: Json<NewToken>, guard: AdminGuard, conn: DbConn) -> Result<Json<CreatedToken>, Status> {
+        let admin = logged_in_admin(&guard, &conn)?;
+        if new.name.trim().is_empty() || new.scopes.is_empty() {
+            return Err(Status::BadRequest)
+        }
+
+        account::create_token(&admin, new.name.trim(), &new.scopes, &conn)
+            .map(|(details, token)| CreatedToken { token, details })
+            .map(Json)
+            .or_500()
+    }
+
+    #[delete("/admin/api_tokens/<id>")]
+    pub fn revoke_api_token(id: ApiTokenId, guard: AdminGuard, conn: DbConn) -> Result<(), Status> {
+        let admin = logged_in_admin(&guard, &conn)?;
+        if ApiToken::revoke(id, admin.id(), &conn).or_500()? {
+            Ok(())
+        } else {
+            Err(Status::NotFound)
+        }
+    }
 }
\ No newline at end of file
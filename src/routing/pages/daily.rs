@@ -0,0 +1,116 @@
+use {
+    serde::Serialize,
+    super::play::{DisplayScore, display_scores},
+    rocket_contrib::templates::Template,
+    rocket::{
+        get,
+        post,
+        State,
+        FromForm,
+        response::Redirect,
+        request::Form,
+        http::{Status, Cookies}
+    },
+    crate::models::{
+        self,
+        daily::{self, DailyOffset, DailyCategories},
+        web::{Or500, NewSession, NewGameState, PlayerSession, Visitor},
+        i18n::{self, Locale},
+        db::{
+            DbConn,
+            Returning,
+            ScoreFilter,
+            ScoreOrder,
+            models::{DailyPlay, NewDailyPlay, Score}
+        }
+    }
+};
+
+const LEADERBOARD_SIZE: i64 = 25;
+
+#[derive(Serialize)]
+struct Daily {
+    available: bool,
+    played_today: bool,
+    player: Option<String>,
+    scores: Vec<DisplayScore>
+}
+
+#[get("/daily")]
+pub fn daily(
+    offset: State<DailyOffset>,
+    categories: State<DailyCategories>,
+    player: Option<PlayerSession>,
+    visitor: Visitor,
+    locale: Locale,
+    conn: DbConn
+) -> Result<Template, Status> {
+    let today = offset.today();
+    let player = PlayerSession::player(player, &conn).or_500()?;
+    let challenger = daily::challenger(player
+        .as_ref()
+        .map_or(Returning::Visitor(&visitor), |player| Returning::Player(player.id()))
+    );
+
+    let filter = ScoreFilter {
+        daily: Some(today),
+        ..<_>::default()
+    };
+    let scores = Score::leaderboard(&filter, ScoreOrder::WeightedPoints, 0, LEADERBOARD_SIZE, &conn)
+        .or_500()?;
+
+    Ok(i18n::render("daily", locale, Daily {
+        available: !daily::question_ids(today, &categories, &conn).or_500()?.is_empty(),
+        played_today: DailyPlay::exists(today, &challenger, &conn).or_500()?,
+        player: player.map(|player| player.name),
+        scores: display_scores(scores, &conn).or_500()?
+    }))
+}
+
+#[derive(FromForm, Debug)]
+pub struct DailyStart {
+    user: Option<String>
+}
+
+#[post("/daily/play", data = "<start>")]
+pub fn play(
+    start: Form<DailyStart>,
+    _sess: NewSession,
+    new_game_state: NewGameState,
+    offset: State<DailyOffset>,
+    categories: State<DailyCategories>,
+    player: Option<PlayerSession>,
+    visitor: Visitor,
+    locale: Locale,
+    mut cookies: Cookies,
+    conn: DbConn
+) -> Result<Redirect, Status> {
+    locale.remember(&mut cookies);
+    let today = offset.today();
+
+    let player = PlayerSession::player(player, &conn).or_500()?;
+    let user = match (&player, start.into_inner().user) {
+        (Some(player), _) => player.name.clone(),
+        (None, Some(user)) if !user.trim().is_empty() => user,
+        (None, _) => return Ok(Redirect::to("/daily"))
+    };
+    let player = player.map(|player| player.id());
+
+    let questions = daily::questions(today, &categories, locale, &conn)
+        .or_500()?;
+    if questions.is_empty() {
+        return Err(Status::NotFound)
+    }
+
+    let challenger = daily::challenger(player.map_or(Returning::Visitor(&visitor), Returning::Player));
+    let first_play = DailyPlay::claim(&NewDailyPlay { day: today, challenger: &challenger }, &conn)
+        .or_500()?;
+    if !first_play {
+        return Ok(Redirect::to("/daily"))
+    }
+
+    models::game::new_daily_state(user, player, &visitor, today, questions, locale, &conn)
+        .map(|state| new_game_state.set(state))
+        .map(|_| Redirect::to("/play"))
+        .or_500()
+}
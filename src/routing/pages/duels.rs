@@ -0,0 +1,93 @@
+use {
+    serde::Serialize,
+    std::time::SystemTime,
+    rocket_contrib::templates::Template,
+    rocket::{
+        get,
+        post,
+        FromForm,
+        response::Redirect,
+        request::Form,
+        http::{Status, Cookies}
+    },
+    crate::models::{
+        self,
+        duels::{self, Comparison},
+        web::{Or500, NewSession, NewGameState, PlayerSession, Visitor},
+        i18n::{self, Locale},
+        db::{
+            DbConn,
+            models::Duel
+        }
+    }
+};
+
+#[derive(Serialize)]
+struct DuelPage<'a> {
+    code: &'a str,
+    challenger: String,
+    questions: usize,
+    // someone is playing it right now, unless they gave up on it
+    accepted: bool,
+    player: Option<String>,
+    comparison: Option<Comparison>
+}
+
+#[get("/duels/<code>")]
+pub fn duel(code: String, player: Option<PlayerSession>, locale: Locale, conn: DbConn) -> Result<Template, Status> {
+    let duel = Duel::with_code(&code, &conn)
+        .or_500()?
+        .ok_or(Status::NotFound)?;
+    let player = PlayerSession::player(player, &conn).or_500()?;
+
+    Ok(i18n::render("duel", locale, DuelPage {
+        code: &duel.code,
+        challenger: duels::challenger_name(&duel, &conn).or_500()?,
+        questions: duel.questions().len(),
+        accepted: duel.is_taken(SystemTime::now()),
+        player: player.map(|player| player.name),
+        comparison: duels::compare(&duel, locale, &conn).or_500()?
+    }))
+}
+
+#[derive(FromForm, Debug)]
+pub struct DuelStart {
+    user: Option<String>
+}
+
+#[post("/duels/<code>/accept", data = "<start>")]
+pub fn accept(
+    code: String,
+    start: Form<DuelStart>,
+    _sess: NewSession,
+    new_game_state: NewGameState,
+    player: Option<PlayerSession>,
+    visitor: Visitor,
+    locale: Locale,
+    mut cookies: Cookies,
+    conn: DbConn
+) -> Result<Redirect, Status> {
+    locale.remember(&mut cookies);
+    let back = Redirect::to(format!("/duels/{}", code));
+    let duel = Duel::with_code(&code, &conn)
+        .or_500()?
+        .ok_or(Status::NotFound)?;
+    if duel.is_taken(SystemTime::now()) {
+        return Ok(back)
+    }
+
+    let player = PlayerSession::player(player, &conn).or_500()?;
+    let user = match (&player, start.into_inner().user) {
+        (Some(player), _) => player.name.clone(),
+        (None, Some(user)) if !user.trim().is_empty() => user,
+        (None, _) => return Ok(back)
+    };
+
+    match models::game::new_duel_state(user, player.map(|player| player.id()), &visitor, &duel, locale, &conn).or_500()? {
+        Some(state) => {
+            new_game_state.set(state);
+            Ok(Redirect::to("/play"))
+        },
+        None => Ok(back)
+    }
+}
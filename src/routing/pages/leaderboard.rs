@@ -0,0 +1,307 @@
+use {
+    serde::Serialize,
+    diesel::QueryResult,
+    super::play::{DisplayScore, display_scores},
+    std::time::{Duration, SystemTime},
+    chrono::{Datelike, NaiveDate, NaiveDateTime, Utc},
+    rocket_contrib::templates::Template,
+    rocket::{
+        get,
+        http::{Status, RawStr},
+        request::{Form, FromForm, FormItems, FromFormValue}
+    },
+    crate::models::{
+        web::Or500,
+        i18n::{self, Locale},
+        db::{
+            DbConn,
+            CategoryId,
+            CategorySet,
+            ScoreFilter,
+            ScoreOrder,
+            models::{Category, Score}
+        }
+    }
+};
+
+const PAGE_SIZE: i64 = 25;
+
+#[derive(Serialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeRange {
+    Day,
+    Week,
+    Month,
+    All
+}
+
+impl Default for TimeRange {
+    fn default() -> Self {
+        TimeRange::All
+    }
+}
+
+impl TimeRange {
+    // ranges follow the calendar in UTC, so the weekly board starts fresh every monday
+    fn since(self, now: NaiveDateTime) -> Option<SystemTime> {
+        let today = now.date();
+        let start = match self {
+            TimeRange::Day => today,
+            TimeRange::Week => today - chrono::Duration::days(today.weekday().num_days_from_monday() as _),
+            TimeRange::Month => NaiveDate::from_ymd(today.year(), today.month(), 1),
+            TimeRange::All => return None
+        };
+
+        Some(SystemTime::UNIX_EPOCH + Duration::from_secs(start.and_hms(0, 0, 0).timestamp() as _))
+    }
+}
+
+impl <'v> FromFormValue<'v> for TimeRange {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "day" => Ok(TimeRange::Day),
+            "week" => Ok(TimeRange::Week),
+            "month" => Ok(TimeRange::Month),
+            "all" => Ok(TimeRange::All),
+            _ => Err(form_value)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LeaderboardQuery {
+    range: TimeRange,
+    sort: ScoreOrder,
+    page: Option<i64>,
+    player: Option<String>,
+    categories: Vec<CategoryId>,
+    // whether scores need to have been played with exactly the selected
+    // categories or just with all of them among others
+    exact: bool
+}
+
+// like the settings form, the selected categories are sent as repeated keys
+impl <'f> FromForm<'f> for LeaderboardQuery {
+    type Error = &'f RawStr;
+
+    fn from_form(it: &mut FormItems<'f>, strict: bool) -> Result<Self, Self::Error> {
+        let mut query = LeaderboardQuery::default();
+
+        for (key, val) in it.map(|fi| fi.key_value()) {
+            match key.url_decode_lossy().as_str() {
+                "range" => query.range = TimeRange::from_form_value(val)?,
+                "sort" => query.sort = ScoreOrder::from_form_value(val)?,
+                "page" => query.page = Some(i64::from_form_value(val).map_err(|_| val)?),
+                "player" => query.player = Some(String::from_form_value(val)?),
+                "categories" => query.categories.push(CategoryId::from_form_value(val)?),
+                "set" => query.exact = match val.as_str() {
+                    "exact" => true,
+                    "contains" => false,
+                    _ => return Err(val)
+                },
+                _ if strict => return Err(val),
+                _ => {}
+            }
+        }
+
+        Ok(query)
+    }
+}
+
+impl LeaderboardQuery {
+    fn category_set(&self) -> CategorySet {
+        match (self.categories.is_empty(), self.exact) {
+            (true, _) => CategorySet::Any,
+            (false, true) => CategorySet::Exactly(self.categories.clone()),
+            (false, false) => CategorySet::Containing(self.categories.clone())
+        }
+    }
+
+    // the category part of the query, so links to other ranges and pages keep it
+    fn filter_query(&self) -> String {
+        if self.categories.is_empty() {
+            String::new()
+        } else {
+            format!("&{}", categories_query(&self.categories, self.exact))
+        }
+    }
+}
+
+fn categories_query(categories: &[CategoryId], exact: bool) -> String {
+    categories
+        .iter()
+        .map(|cat| format!("categories={}", **cat))
+        .chain(std::iter::once(format!("set={}", if exact { "exact" } else { "contains" })))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// The query for a leaderboard of scores played with exactly the given categories.
+/// Without any categories there is no such leaderboard, the query would show all scores.
+pub fn set_query(categories: &[CategoryId]) -> Option<String> {
+    if categories.is_empty() {
+        None
+    } else {
+        Some(categories_query(categories, true))
+    }
+}
+
+#[derive(Serialize)]
+struct SelectableCategory {
+    category: Category,
+    selected: bool
+}
+
+// only the first score on a page needs to be ranked by the database,
+// below it a score either ties with the one above or is ranked by its position
+fn page_ranks(first: u64, offset: i64, keys: &[i32]) -> Vec<u64> {
+    let mut ranks = Vec::with_capacity(keys.len());
+    for (i, key) in keys.iter().enumerate() {
+        let rank = match i {
+            0 => first,
+            _ if keys[i - 1] == *key => ranks[i - 1],
+            _ => (offset + i as i64 + 1) as u64
+        };
+        ranks.push(rank);
+    }
+    ranks
+}
+
+#[derive(Serialize)]
+struct RankedScore {
+    rank: u64,
+    #[serde(flatten)]
+    score: DisplayScore
+}
+
+#[derive(Serialize)]
+struct PlayerRank {
+    name: String,
+    rank: Option<u64>,
+    page: Option<i64>
+}
+
+#[derive(Serialize)]
+struct Leaderboard {
+    scores: Vec<RankedScore>,
+    range: TimeRange,
+    sort: ScoreOrder,
+    page: i64,
+    pages: i64,
+    player: Option<PlayerRank>,
+    categories: Vec<SelectableCategory>,
+    exact: bool,
+    filter_query: String
+}
+
+#[get("/leaderboard?<query..>")]
+pub fn leaderboard(query: Form<LeaderboardQuery>, locale: Locale, conn: DbConn) -> Result<Template, Status> {
+    let query = query.into_inner();
+    let sort = query.sort;
+    let filter = ScoreFilter {
+        since: query.range.since(Utc::now().naive_utc()),
+        categories: query.category_set(),
+        daily: None
+    };
+
+    let total = Score::count(&filter, &conn)
+        .or_500()?;
+    let pages = (total + PAGE_SIZE - 1) / PAGE_SIZE;
+    let page = query.page
+        .unwrap_or(1)
+        .max(1)
+        .min(pages.max(1));
+
+    let offset = (page - 1) * PAGE_SIZE;
+    let scores = Score::leaderboard(&filter, sort, offset, PAGE_SIZE, &conn)
+        .or_500()?;
+    let first = scores
+        .first()
+        .map(|score| score.rank(&filter, sort, &conn))
+        .transpose()
+        .or_500()?
+        .unwrap_or_default();
+    let keys = scores
+        .iter()
+        .map(|score| match sort {
+            ScoreOrder::Points => score.points,
+            ScoreOrder::WeightedPoints => score.weighted_points
+        })
+        .collect::<Vec<_>>();
+    let ranks = page_ranks(first, offset, &keys);
+    let scores = display_scores(scores, &conn)
+        .or_500()?
+        .into_iter()
+        .zip(ranks)
+        .map(|(score, rank)| RankedScore { rank, score })
+        .collect();
+
+    let player = query.player
+        .clone()
+        .filter(|name| !name.trim().is_empty())
+        .map(|name| -> QueryResult<PlayerRank> {
+            let rank = Score::best_of(name.trim(), &filter, sort, &conn)?
+                .map(|best| best.rank(&filter, sort, &conn))
+                .transpose()?;
+
+            Ok(PlayerRank {
+                name,
+                rank,
+                page: rank.map(|rank| (rank as i64 - 1) / PAGE_SIZE + 1)
+            })
+        })
+        .transpose()
+        .or_500()?;
+
+    let categories = Category::load_all(&conn)
+        .or_500()?
+        .into_iter()
+        .map(|category| SelectableCategory {
+            selected: query.categories.contains(&category.id()),
+            category
+        })
+        .collect();
+
+    Ok(i18n::render("leaderboard", locale, Leaderboard {
+        scores,
+        range: query.range,
+        sort,
+        page,
+        pages,
+        player,
+        categories,
+        exact: query.exact,
+        filter_query: query.filter_query()
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ranges_start_at_calendar_boundaries() {
+        let at = |y, m, d| SystemTime::UNIX_EPOCH + Duration::from_secs(
+            NaiveDate::from_ymd(y, m, d).and_hms(0, 0, 0).timestamp() as _
+        );
+        // a thursday
+        let now = NaiveDate::from_ymd(2020, 2, 13).and_hms(18, 30, 0);
+
+        assert_eq!(TimeRange::Day.since(now), Some(at(2020, 2, 13)));
+        assert_eq!(TimeRange::Week.since(now), Some(at(2020, 2, 10)));
+        assert_eq!(TimeRange::Month.since(now), Some(at(2020, 2, 1)));
+        assert_eq!(TimeRange::All.since(now), None)
+    }
+
+    #[test]
+    fn ties_share_a_rank() {
+        // the first score ties with the last one of the page before
+        assert_eq!(
+            page_ranks(24, 25, &[70, 70, 60, 50, 50, 50, 40]),
+            vec![24, 24, 28, 29, 29, 29, 32]
+        );
+        assert_eq!(page_ranks(1, 0, &[]), Vec::<u64>::new())
+    }
+}
@@ -1,3 +1,9 @@
 pub mod start;
 pub mod play;
-pub mod admin;
\ No newline at end of file
+pub mod admin;
+pub mod leaderboard;
+pub mod daily;
+pub mod duels;
+pub mod rooms;
+pub mod teams;
+pub mod player;
\ No newline at end of file
@@ -13,25 +13,32 @@ use {
         post,
         FromForm,
         response::Redirect,
-        http::{Status, RawStr},
+        http::{Status, RawStr, Cookies},
         request::{Form, FormItems, FromFormValue}
     },
     crate::models::{
         self,
-        web::{NewSession, NewGameState, SyncedGameState, Or500, EndGame},
-        game::{QuestionError, JokerError, Answered, NextQuestionError, pseudo_shuffle, correct_ratio},
+        i18n::{self, Locale},
+        web::{Session, NewSession, NewGameState, SyncedGameState, Or500, EndGame, ReportAllowance, PlayerSession, Visitor, EventStream},
+        game::{GameState, QuestionError, JokerError, Answered, NextQuestionError, pseudo_shuffle, correct_ratio},
+        practice::{self, BoxCount},
+        duels,
         db::{
             DbConn,
             CategoryId,
-            models::{Category, Question, Score}
+            QuestionId,
+            ReportReason,
+            models::{Category, Question, QuestionMedia, QuestionReport, NewQuestionReport, Score}
         }
     }
 };
 
 #[derive(Serialize)]
-struct DisplayData<'a> {
+pub struct DisplayData<'a> {
+    id: QuestionId,
     question: &'a str,
-    answers: Vec<&'a str>,
+    media: Option<DisplayMedia>,
+    answers: Vec<DisplayAnswer<'a>>,
     category: &'a str,
     points: i32,
     joker: bool,
@@ -39,8 +46,37 @@ struct DisplayData<'a> {
     elapsed_secs: u64
 }
 
+#[derive(Serialize)]
+pub struct DisplayAnswer<'a> {
+    text: &'a str,
+    media: Option<DisplayMedia>
+}
+
+#[derive(Serialize)]
+pub struct DisplayMedia {
+    url: String,
+    audio: bool
+}
+
+impl From<&QuestionMedia> for DisplayMedia {
+    fn from(media: &QuestionMedia) -> Self {
+        DisplayMedia {
+            url: format!("/content/media/{}", *media.id()),
+            audio: media.content_type.starts_with("audio/")
+        }
+    }
+}
+
 impl <'a> DisplayData<'a> {
-    pub fn new(question: &'a Question, category: &'a str, points: i32, joker: bool, ratio: u8, elapsed_secs: u64) -> DisplayData<'a> {
+    pub fn new(
+        question: &'a Question,
+        media: &[QuestionMedia],
+        category: &'a str,
+        points: i32,
+        joker: bool,
+        ratio: u8,
+        elapsed_secs: u64
+    ) -> DisplayData<'a> {
         let mut answers = question
             .incorrect
             .iter()
@@ -49,9 +85,22 @@ impl <'a> DisplayData<'a> {
             .collect::<Vec<_>>();
         pseudo_shuffle(&mut answers);
 
+        let media_for = |answer: Option<i16>| media
+            .iter()
+            .find(|media| media.answer == answer)
+            .map(DisplayMedia::from);
+
         DisplayData {
+            id: question.id(),
             question: &question.string,
-            answers,
+            media: media_for(None),
+            answers: answers
+                .into_iter()
+                .map(|text| DisplayAnswer {
+                    text,
+                    media: media_for(question.answer_index(text))
+                })
+                .collect(),
             category,
             points,
             joker,
@@ -62,18 +111,70 @@ impl <'a> DisplayData<'a> {
 }
 
 #[post("/play/new_game", data = "<settings>")]
-pub fn new_game(settings: Form<Settings>, _sess: NewSession, new_game_state: NewGameState, conn: DbConn) -> Result<Redirect, Status> {
+pub fn new_game(
+    settings: Form<Settings>,
+    _sess: NewSession,
+    new_game_state: NewGameState,
+    request_locale: Locale,
+    player: Option<PlayerSession>,
+    visitor: Visitor,
+    mut cookies: Cookies,
+    conn: DbConn
+) -> Result<Redirect, Status> {
     let settings = settings.into_inner();
+    let locale = settings.locale.unwrap_or(request_locale);
+    locale.remember(&mut cookies);
 
-    models::game::new_game_state(settings.user, &settings.categories, &conn)
-        .map(|state| new_game_state.set(state))
-        .map(|_| Redirect::to("/play"))
-        .or_500()
+    match start_game(settings, locale, player, &visitor, &conn) {
+        Ok(state) => {
+            new_game_state.set(state);
+            Ok(Redirect::to("/play"))
+        },
+        Err(StartError::NeedsAccount) => Ok(Redirect::to("/player")),
+        Err(StartError::Query(_)) => Err(Status::InternalServerError)
+    }
+}
+
+#[derive(Debug)]
+pub enum StartError {
+    /// Practising without an account would lose all progress.
+    NeedsAccount,
+    Query(diesel::result::Error)
+}
+
+/// Sets up a game the way the settings say, shared with the API.
+pub fn start_game(
+    settings: Settings,
+    locale: Locale,
+    player: Option<PlayerSession>,
+    visitor: &str,
+    conn: &DbConn
+) -> Result<GameState, StartError> {
+    // logged in players always play under their own name
+    let player = PlayerSession::player(player, conn).map_err(StartError::Query)?;
+    let user = player
+        .as_ref()
+        .map_or(settings.user, |player| player.name.clone());
+
+    let seed = rand::random();
+    match (settings.practice, player) {
+        (true, Some(player)) =>
+            models::game::new_practice_state(user, player.id(), visitor, seed, &settings.categories, settings.tags, locale, conn),
+        (true, None) => return Err(StartError::NeedsAccount),
+        (false, player) =>
+            models::game::new_game_state(user, player.map(|player| player.id()), visitor, seed, &settings.categories, settings.tags, locale, conn)
+                .map(|mut state| {
+                    if settings.duel {
+                        state.challenge_others();
+                    }
+                    state
+                })
+    }.map_err(StartError::Query)
 }
 
 #[get("/play")]
 pub fn continue_game(mut game_state: SyncedGameState, conn: DbConn) -> Result<Template, Status> {
-    let (points, joker) = (game_state.points(), game_state.joker());
+    let (points, joker, locale) = (game_state.points(), game_state.joker(), game_state.locale());
     let elapsed_secs = game_state
         .stopwatch
         .elapsed()
@@ -81,11 +182,11 @@ pub fn continue_game(mut game_state: SyncedGameState, conn: DbConn) -> Result<Te
 
     match game_state.next_question() {
         Ok((cat, next_q)) =>
-            next_question(points, joker, elapsed_secs, cat, next_q, &conn),
+            next_question(points, joker, elapsed_secs, locale, cat, next_q, &conn),
         Err(NextQuestionError::HasNotAnswered) => game_state
             .current_question()
             .or_500()
-            .and_then(|(cat, cq)| stay(points, joker, elapsed_secs, &conn, cat, cq)),
+            .and_then(|(cat, cq)| stay(points, joker, elapsed_secs, locale, &conn, cat, cq)),
         Err(NextQuestionError::NoneRemaining) => load_more_questions(game_state, conn)
     }
 }
@@ -94,15 +195,19 @@ fn next_question(
     points: i32,
     joker: bool,
     elapsed_secs: u64,
+    locale: Locale,
     cat: &Category,
     next_q: &Question,
     conn: &DbConn
 ) -> Result<Template, Status> {
     let ratio = correct_ratio(next_q, &conn)
         .or_500()?;
+    let media = next_q.media(&conn)
+        .or_500()?;
 
-    Ok(Template::render("play", DisplayData::new(
+    Ok(i18n::render("play", locale, DisplayData::new(
         next_q,
+        &media,
         &cat.name,
         points,
         joker,
@@ -117,6 +222,20 @@ fn load_more_questions(mut game_state: SyncedGameState, conn: DbConn) -> Result<
         Err(e) => match e {
             QuestionError::Query(_) =>
                 Err(Status::InternalServerError),
+            // there's nothing to choose from in the daily challenge
+            QuestionError::NoneRemaining if game_state.daily().is_some() =>
+                Ok(i18n::render("daily_done", game_state.locale(), Intermission {
+                    categories: &[],
+                    points: game_state.points(),
+                    joker: game_state.joker()
+                })),
+            // neither in a duel, it's the challenger's questions
+            QuestionError::NoneRemaining if game_state.is_duel_opponent() =>
+                Ok(i18n::render("duel_done", game_state.locale(), Intermission {
+                    categories: &[],
+                    points: game_state.points(),
+                    joker: game_state.joker()
+                })),
             QuestionError::NoneRemaining =>
                 intermission(&mut game_state, &conn)
                     .or_500()
@@ -139,7 +258,7 @@ fn intermission(game_state: &mut SyncedGameState, conn: &DbConn) -> QueryResult<
 }
 
 fn render_intermission(game_state: &SyncedGameState, categories: &[Category]) -> Template {
-    Template::render("play_error", Intermission {
+    i18n::render("play_error", game_state.locale(), Intermission {
         categories,
         points: game_state.points(),
         joker: game_state.joker()
@@ -150,15 +269,19 @@ fn stay(
     points: i32,
     joker: bool,
     elapsed_secs: u64,
+    locale: Locale,
     conn: &DbConn,
     cat: &Category,
     cq: &Question
 ) -> Result<Template, Status> {
     let ratio = correct_ratio(cq, &conn)
         .or_500()?;
+    let media = cq.media(&conn)
+        .or_500()?;
 
-    Ok(Template::render("play", DisplayData::new(
+    Ok(i18n::render("play", locale, DisplayData::new(
         cq,
+        &media,
         &cat.name,
         points,
         joker,
@@ -169,7 +292,7 @@ fn stay(
 
 #[derive(Debug)]
 pub struct NewCategories {
-    categories: Vec<CategoryId>
+    pub(super) categories: Vec<CategoryId>
 }
 
 impl <'f> rocket::request::FromForm<'f> for NewCategories {
@@ -206,25 +329,63 @@ pub struct Response {
 
 #[post("/play/answer", data = "<response>")]
 pub fn answer(response: Form<Response>, mut game_state: SyncedGameState, conn: DbConn) -> Result<Redirect, Status> {
+    let practice = game_state.is_practice();
     models::game::answer(&response.answer, &mut *game_state, &conn)
         .map(|ans| match ans {
             Answered::Correctly => Redirect::to("/play"),
+            // practice goes on after a mistake, it's about learning the answer
+            Answered::Incorrectly if practice => Redirect::to("/play/missed"),
             Answered::Incorrectly => Redirect::to("/play/failed")
         })
         .or_500()
 }
 
+#[derive(Serialize)]
+struct Missed<'a> {
+    id: QuestionId,
+    question: &'a str,
+    correct: &'a str
+}
+
+#[get("/play/missed")]
+pub fn missed(game_state: SyncedGameState) -> Result<Template, Status> {
+    let question = game_state.current_question
+        .as_ref()
+        .ok_or(Status::NotFound)?;
+
+    Ok(i18n::render("missed", game_state.locale(), Missed {
+        id: question.id(),
+        question: &question.string,
+        correct: &question.correct
+    }))
+}
+
+/// Pushes the game's questions, answers and score as they happen,
+/// until the game ends. Responds with 503 once too many streams are open.
+#[get("/play/events")]
+pub fn events(sess: Session, mut game_state: SyncedGameState) -> Result<EventStream, Status> {
+    game_state
+        .subscribe(sess)
+        .ok_or(Status::ServiceUnavailable)
+}
+
 #[derive(Serialize, Debug)]
 pub struct Joker {
     incorrect: [String; 2]
 }
 
+impl From<[&str; 2]> for Joker {
+    fn from([ans1, ans2]: [&str; 2]) -> Self {
+        Joker {
+            incorrect: [ans1.into(), ans2.into()]
+        }
+    }
+}
+
 #[get("/play/use_joker")]
 pub fn use_joker(mut game_state: SyncedGameState) -> Result<Json<Joker>, Status> {
     game_state.use_joker()
-        .map(|[ans1, ans2]| Joker {
-            incorrect: [ans1.into(), ans2.into()]
-        })
+        .map(Joker::from)
         .map(Json)
         .map_err(|e| match e {
             JokerError::AlreadyUsed => Status::NotAcceptable,
@@ -236,13 +397,16 @@ pub fn use_joker(mut game_state: SyncedGameState) -> Result<Json<Joker>, Status>
 struct Results {
     user_score: DisplayScore,
     placement: u64,
+    set_placement: u64,
+    set_query: Option<String>,
     higher: Vec<DisplayScore>,
     lower: Vec<DisplayScore>,
-    top_three: Vec<DisplayScore>
+    top_three: Vec<DisplayScore>,
+    duel: Option<String>
 }
 
 #[derive(Serialize)]
-struct DisplayScore {
+pub struct DisplayScore {
     name: String,
     points: i32,
     weighted_points: i32,
@@ -313,30 +477,47 @@ impl From<Duration> for Hms {
     }
 }
 
+#[derive(Serialize)]
+struct PracticeResults {
+    progress: Vec<BoxCount>
+}
+
 #[get("/play/end")]
 pub fn end_game(end: EndGame, conn: DbConn) -> Result<Template, Status> {
+    // practice games don't make it onto the leaderboard
+    if let (true, Some(player)) = (end.game_state.is_practice(), end.game_state.player()) {
+        return Ok(i18n::render("practice_end", end.game_state.locale(), PracticeResults {
+            progress: practice::progress(player, &conn).or_500()?
+        }))
+    }
+
     let user_score = Score::insert(
         &end.game_state.score(),
         &conn
     ).or_500()?;
+    let duel = duels::conclude(&end.game_state, &conn).or_500()?;
 
     let scores = models::game::scores(&user_score, &conn).or_500()?;
+    let set_query = super::leaderboard::set_query(&user_score.categories);
 
     let user_score = DisplayScore::from_score(user_score, &conn).or_500()?;
     let higher = display_scores(scores.higher, &conn).or_500()?;
     let lower = display_scores(scores.lower, &conn).or_500()?;
     let top_three = display_scores(scores.top_three, &conn).or_500()?;
 
-    Ok(Template::render("end", Results {
+    Ok(i18n::render("end", end.game_state.locale(), Results {
         user_score,
         placement: scores.placement,
+        set_placement: scores.set_placement,
+        set_query,
         higher,
         lower,
-        top_three
+        top_three,
+        duel
     }))
 }
 
-fn display_scores(scores: Vec<Score>, conn: &DbConn) -> QueryResult<Vec<DisplayScore>> {
+pub fn display_scores(scores: Vec<Score>, conn: &DbConn) -> QueryResult<Vec<DisplayScore>> {
     scores
         .into_iter()
         .map(|score| DisplayScore::from_score(score, &conn))
@@ -347,14 +528,57 @@ fn display_scores(scores: Vec<Score>, conn: &DbConn) -> QueryResult<Vec<DisplayS
 struct Failed {
     points: i32,
     weighted_points: i32,
+    question: Option<QuestionId>,
+    duel: Option<String>
 }
 
 #[get("/play/failed")]
-pub fn failed(end: EndGame) -> Template {
-    Template::render("failed", Failed {
+pub fn failed(end: EndGame, conn: DbConn) -> Result<Template, Status> {
+    Ok(i18n::render("failed", end.game_state.locale(), Failed {
         points: end.game_state.points(),
-        weighted_points: end.game_state.weighted_points()
-    })
+        weighted_points: end.game_state.weighted_points(),
+        question: end.game_state.current_question
+            .as_ref()
+            .map(Question::id),
+        duel: duels::conclude(&end.game_state, &conn).or_500()?
+    }))
+}
+
+const MAX_COMMENT_LEN: usize = 1000;
+
+#[derive(FromForm, Debug)]
+pub struct Report {
+    question: QuestionId,
+    reason: ReportReason,
+    comment: Option<String>
+}
+
+#[post("/play/report", data = "<report>")]
+pub fn report(report: Form<Report>, allowance: ReportAllowance, conn: DbConn) -> Result<Status, Status> {
+    let comment = report.comment
+        .as_ref()
+        .map(|comment| comment.trim())
+        .filter(|comment| !comment.is_empty());
+
+    if comment.map_or(false, |comment| comment.chars().count() > MAX_COMMENT_LEN) {
+        return Err(Status::UnprocessableEntity)
+    }
+
+    let question = Question::load_published(report.question, &conn)
+        .or_500()?
+        .ok_or(Status::NotFound)?;
+
+    allowance.spend()?;
+
+    QuestionReport::insert(
+        &NewQuestionReport {
+            question_id: *question.id(),
+            reason: report.reason,
+            comment
+        },
+        &conn
+    ).map(|_| Status::Created)
+    .or_500()
 }
 
 #[cfg(test)]
@@ -459,4 +683,30 @@ mod test {
 
         assert_eq!(status, Status::NotAcceptable)
     }
+
+    #[test]
+    fn rejected_reports_are_free() {
+        let rocket = rocket();
+        let client = Client::new(rocket).unwrap();
+
+        client.post("/play/new_game")
+            .header(ContentType::Form)
+            .body(SETTINGS_FORM)
+            .dispatch();
+
+        // there is no question with id 0, so none of these use up the allowance
+        let statuses = (0..6)
+            .map(|_| client.post("/play/report")
+                .header(ContentType::Form)
+                .body("question=0&reason=1")
+                .dispatch()
+                .status()
+            )
+            .collect::<Vec<_>>();
+
+        assert!(statuses
+            .iter()
+            .all(|status| *status == Status::NotFound)
+        )
+    }
 }
\ No newline at end of file
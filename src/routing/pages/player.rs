@@ -0,0 +1,200 @@
+use {
+    serde::Serialize,
+    serde_repr::Serialize_repr,
+    super::play::{DisplayScore, display_scores},
+    rocket_contrib::templates::Template,
+    rocket::{
+        uri,
+        get,
+        post,
+        response::Redirect,
+        request::{Form, FromFormValue},
+        http::{
+            Status,
+            RawStr,
+            Cookies,
+            impl_from_uri_param_identity,
+            uri::{Query, UriDisplay, Formatter}
+        }
+    },
+    crate::models::{
+        web::{Or500, PlayerSession},
+        account::{self, Credentials},
+        i18n::{self, Locale},
+        db::{
+            DbConn,
+            PlayerId,
+            CategoryId,
+            PlayerError,
+            ops::CategoryAccuracy,
+            models::{Category, Player}
+        }
+    }
+};
+
+const BEST_SCORES: i64 = 10;
+
+#[derive(Serialize_repr, Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PlayerLoginError {
+    NameInUse = 1,
+    WrongCredentials = 2,
+    Other = 3
+}
+
+impl <'v> FromFormValue<'v> for PlayerLoginError {
+    type Error = &'v RawStr;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "1" => Ok(PlayerLoginError::NameInUse),
+            "2" => Ok(PlayerLoginError::WrongCredentials),
+            "3" => Ok(PlayerLoginError::Other),
+            _ => Err(form_value)
+        }
+    }
+}
+
+impl UriDisplay<Query> for PlayerLoginError {
+    fn fmt(&self, f: &mut Formatter<Query>) -> std::fmt::Result {
+        f.write_value((*self as u8).to_string())
+    }
+}
+
+impl_from_uri_param_identity!([Query] PlayerLoginError);
+
+#[derive(Serialize)]
+struct LoginData {
+    error: Option<PlayerLoginError>
+}
+
+/// Logged in players go straight to their profile.
+#[get("/player?<error>")]
+pub fn player(error: Option<PlayerLoginError>, session: Option<PlayerSession>, locale: Locale) -> Result<Template, Redirect> {
+    match session {
+        Some(_) => Err(Redirect::to(uri!(profile))),
+        None => Ok(i18n::render("player", locale, LoginData { error }))
+    }
+}
+
+#[post("/player/register", data = "<credentials>")]
+pub fn register(credentials: Form<Credentials>, mut cookies: Cookies, conn: DbConn) -> Redirect {
+    if credentials.name.trim().is_empty() || credentials.password.is_empty() {
+        return Redirect::to(uri!(player: error = PlayerLoginError::WrongCredentials))
+    }
+
+    match account::register_player(&credentials, &conn) {
+        Ok(player) => {
+            PlayerSession::log_in(player.id(), &mut cookies);
+            Redirect::to(uri!(profile))
+        },
+        Err(account::Error::InsertPlayer(PlayerError::NameInUse)) => Redirect::to(uri!(player: error = PlayerLoginError::NameInUse)),
+        Err(_) => Redirect::to(uri!(player: error = PlayerLoginError::Other))
+    }
+}
+
+#[post("/player/login", data = "<credentials>")]
+pub fn login(credentials: Form<Credentials>, mut cookies: Cookies, conn: DbConn) -> Redirect {
+    match account::verify_player(&credentials, &conn) {
+        Ok(Some(player)) => {
+            PlayerSession::log_in(player.id(), &mut cookies);
+            Redirect::to(uri!(profile))
+        },
+        Ok(None) => Redirect::to(uri!(player: error = PlayerLoginError::WrongCredentials)),
+        Err(_) => Redirect::to(uri!(player: error = PlayerLoginError::Other))
+    }
+}
+
+#[post("/player/logout")]
+pub fn logout(mut cookies: Cookies) -> Redirect {
+    PlayerSession::log_out(&mut cookies);
+    Redirect::to("/")
+}
+
+#[derive(Serialize)]
+struct FavouriteCategory {
+    category: Category,
+    picked: i64
+}
+
+#[derive(Serialize)]
+struct CategoryStats {
+    category: Category,
+    answers: i64,
+    correct: i64,
+    /// Percentage of correct answers.
+    accuracy: i64
+}
+
+#[derive(Serialize)]
+struct Profile {
+    id: PlayerId,
+    name: String,
+    /// Whether the player is looking at their own profile.
+    own: bool,
+    games_played: i64,
+    best_scores: Vec<DisplayScore>,
+    favourites: Vec<FavouriteCategory>,
+    accuracy: Vec<CategoryStats>
+}
+
+fn find_category(categories: &[Category], id: CategoryId) -> Option<Category> {
+    categories
+        .iter()
+        .find(|cat| cat.id() == id)
+        .cloned()
+}
+
+fn render_profile(player: Player, own: bool, locale: Locale, conn: &DbConn) -> Result<Template, Status> {
+    let categories = Category::load_all(conn)
+        .or_500()?;
+
+    let favourites = player.favourite_categories(conn)
+        .or_500()?
+        .into_iter()
+        .filter_map(|(id, picked)| find_category(&categories, id)
+            .map(|category| FavouriteCategory { category, picked })
+        )
+        .collect();
+
+    let accuracy = player.accuracy(conn)
+        .or_500()?
+        .into_iter()
+        .filter_map(|CategoryAccuracy { category, answers, correct }| find_category(&categories, category)
+            .map(|category| CategoryStats {
+                category,
+                answers,
+                correct,
+                accuracy: correct * 100 / answers.max(1)
+            })
+        )
+        .collect();
+
+    let best_scores = display_scores(player.best_scores(BEST_SCORES, conn).or_500()?, conn)
+        .or_500()?;
+
+    Ok(i18n::render("profile", locale, Profile {
+        id: player.id(),
+        games_played: player.games_played(conn).or_500()?,
+        name: player.name,
+        own,
+        best_scores,
+        favourites,
+        accuracy
+    }))
+}
+
+/// The own profile is the public one, there is just nothing to hide.
+#[get("/player/profile")]
+pub fn profile(session: PlayerSession) -> Redirect {
+    Redirect::to(format!("/players/{}", **session))
+}
+
+#[get("/players/<id>")]
+pub fn public_profile(id: PlayerId, session: Option<PlayerSession>, locale: Locale, conn: DbConn) -> Result<Template, Status> {
+    let player = Player::with_id(id, &conn)
+        .or_500()?
+        .ok_or(Status::NotFound)?;
+
+    render_profile(player, session.map_or(false, |sess| *sess == id), locale, &conn)
+}
@@ -0,0 +1,193 @@
+use {
+    serde::Serialize,
+    std::time::Instant,
+    super::play::NewCategories,
+    rocket_contrib::templates::Template,
+    rocket::{
+        get,
+        post,
+        State,
+        FromForm,
+        response::Redirect,
+        request::Form,
+        http::Status
+    },
+    crate::models::{
+        rooms::{self, Room, RoomCode, RoomError},
+        web::{Or500, Session, NewSession, SyncedRooms, EventStream, event},
+        i18n::{self, Locale},
+        db::{
+            DbConn,
+            models::Category
+        }
+    }
+};
+
+fn status(e: RoomError) -> Status {
+    match e {
+        RoomError::NotHost | RoomError::NotInRoom => Status::Forbidden,
+        RoomError::NameTaken | RoomError::AlreadyJoined | RoomError::AlreadyAnswered => Status::Conflict,
+        RoomError::WrongPhase | RoomError::TooLate => Status::UnprocessableEntity
+    }
+}
+
+#[derive(Serialize)]
+struct Rooms {
+    categories: Vec<Category>
+}
+
+#[get("/rooms")]
+pub fn rooms(locale: Locale, conn: DbConn) -> Result<Template, Status> {
+    Ok(i18n::render("rooms", locale, Rooms {
+        categories: Category::load_all(&conn).or_500()?
+    }))
+}
+
+#[post("/rooms/create", data = "<new>")]
+pub fn create(
+    new: Form<NewCategories>,
+    sess: NewSession,
+    rooms: State<SyncedRooms>,
+    locale: Locale,
+    conn: DbConn
+) -> Result<Redirect, Status> {
+    let categories = Category::load_with_ids(&new.categories, &conn)
+        .or_500()?;
+    let questions = rooms::load_questions(&categories, locale, &conn)
+        .or_500()?;
+    if questions.is_empty() {
+        return Ok(Redirect::to("/rooms"))
+    }
+
+    let code = rooms::insert_with_new_code(
+        &mut *rooms.lock().or_500()?,
+        Room::new(sess.into(), questions),
+        Instant::now()
+    );
+
+    Ok(Redirect::to(format!("/rooms/{}/host", code.as_str())))
+}
+
+#[derive(Serialize)]
+struct RoomPage<'a> {
+    code: &'a str,
+    host: bool
+}
+
+#[get("/rooms/<code>/host")]
+pub fn host(code: RoomCode, sess: Session, rooms: State<SyncedRooms>, locale: Locale) -> Result<Template, Status> {
+    let rooms = rooms
+        .lock()
+        .or_500()?;
+    let room = rooms
+        .get(&code)
+        .ok_or(Status::NotFound)?;
+
+    if room.is_host(&sess) {
+        Ok(i18n::render("room", locale, RoomPage { code: code.as_str(), host: true }))
+    } else {
+        Err(Status::Forbidden)
+    }
+}
+
+/// Rooms and teams are joined the same way.
+#[derive(FromForm, Debug)]
+pub struct Join {
+    code: String,
+    name: String
+}
+
+impl Join {
+    pub(super) fn parse(self) -> Result<(RoomCode, String), Status> {
+        let code = RoomCode::parse(&self.code)
+            .ok_or(Status::NotFound)?;
+        let name = self.name.trim();
+        if name.is_empty() {
+            return Err(Status::BadRequest)
+        }
+
+        Ok((code, name.to_string()))
+    }
+}
+
+#[post("/rooms/join", data = "<join>")]
+pub fn join(join: Form<Join>, sess: NewSession, rooms: State<SyncedRooms>) -> Result<Redirect, Status> {
+    let (code, name) = join.into_inner().parse()?;
+
+    rooms
+        .lock()
+        .or_500()?
+        .get_mut(&code)
+        .ok_or(Status::NotFound)?
+        .join(sess.into(), name)
+        .map_err(status)?;
+
+    Ok(Redirect::to(format!("/rooms/{}", code.as_str())))
+}
+
+#[get("/rooms/<code>")]
+pub fn room(code: RoomCode, sess: Session, rooms: State<SyncedRooms>, locale: Locale) -> Result<Template, Status> {
+    let rooms = rooms
+        .lock()
+        .or_500()?;
+    let room = rooms
+        .get(&code)
+        .ok_or(Status::NotFound)?;
+
+    if room.is_member(&sess) {
+        Ok(i18n::render("room", locale, RoomPage { code: code.as_str(), host: room.is_host(&sess) }))
+    } else {
+        Err(Status::Forbidden)
+    }
+}
+
+#[post("/rooms/<code>/advance")]
+pub fn advance(code: RoomCode, sess: Session, rooms: State<SyncedRooms>) -> Result<Status, Status> {
+    rooms
+        .lock()
+        .or_500()?
+        .get_mut(&code)
+        .ok_or(Status::NotFound)?
+        .advance(&sess, Instant::now())
+        .map(|_| Status::NoContent)
+        .map_err(status)
+}
+
+#[derive(FromForm, Debug)]
+pub struct RoomAnswer {
+    answer: String
+}
+
+#[post("/rooms/<code>/answer", data = "<answer>")]
+pub fn answer(code: RoomCode, answer: Form<RoomAnswer>, sess: Session, rooms: State<SyncedRooms>) -> Result<Status, Status> {
+    rooms
+        .lock()
+        .or_500()?
+        .get_mut(&code)
+        .ok_or(Status::NotFound)?
+        .answer(&sess, &answer.answer, Instant::now())
+        .map(|_| Status::NoContent)
+        .map_err(status)
+}
+
+/// Every change to the room is pushed as a `state` event with the whole room view,
+/// alongside the more specific events for front-ends that only care about some of it.
+/// Responds with 503 once too many streams are open.
+#[get("/rooms/<code>/events")]
+pub fn events(code: RoomCode, sess: Session, rooms: State<SyncedRooms>) -> Result<EventStream, Status> {
+    let mut rooms = rooms
+        .lock()
+        .or_500()?;
+    let room = rooms
+        .get_mut(&code)
+        .ok_or(Status::NotFound)?;
+
+    if !room.is_member(&sess) {
+        return Err(Status::Forbidden)
+    }
+
+    let view = room.view(Instant::now());
+    room.events
+        .subscribe(sess, event::STATE, &view)
+        .ok_or(Status::ServiceUnavailable)
+}
@@ -1,7 +1,11 @@
 use {
     serde::Serialize,
     rocket_contrib::templates::Template,
-    crate::models::db,
+    crate::models::{
+        db,
+        web::{Or500, PlayerSession},
+        i18n::{self, Locale, AvailableLocale}
+    },
     rocket::{
         post,
         FromForm,
@@ -18,18 +22,33 @@ pub struct User {
 #[derive(Serialize)]
 struct AvailableSettings {
     user: String,
-    categories: Vec<db::models::Category>
+    categories: Vec<db::models::Category>,
+    tags: Vec<String>,
+    locales: Vec<AvailableLocale>,
+    // practice mode needs an account to keep track of progress
+    practice_available: bool
 }
 
 #[post("/settings", data="<user>")]
-pub fn settings(user: Form<User>, conn: db::DbConn) -> Result<Template, Status> {
+pub fn settings(user: Form<User>, locale: Locale, player: Option<PlayerSession>, conn: db::DbConn) -> Result<Template, Status> {
+    let player = PlayerSession::player(player, &conn).or_500()?;
+    let practice_available = player.is_some();
+    let user = player.map_or(user.into_inner().name, |player| player.name);
+
+    let tags = db::models::Question::all_tags(&conn)
+        .map_err(|_| Status::InternalServerError)?;
+
     db::models::Category::load_all(&conn)
         .map_err(|_| Status::InternalServerError)
-        .map(|categories| Template::render(
+        .map(|categories| i18n::render(
             "settings",
-            &AvailableSettings {
-                user: user.0.name,
-                categories
+            locale,
+            AvailableSettings {
+                user,
+                categories,
+                tags,
+                locales: i18n::available(locale),
+                practice_available
             }
         ))
 }
@@ -38,7 +57,12 @@ pub fn settings(user: Form<User>, conn: db::DbConn) -> Result<Template, Status>
 #[derive(Debug)]
 pub struct Settings {
     pub user: String,
-    pub categories: Vec<db::CategoryId>
+    pub categories: Vec<db::CategoryId>,
+    pub tags: Vec<String>,
+    pub locale: Option<Locale>,
+    pub practice: bool,
+    /// Whether others get to play the same questions afterwards.
+    pub duel: bool
 }
 
 // rocket 4.x doesn't have support for multi-select forms yet,
@@ -49,6 +73,10 @@ impl <'f> rocket::request::FromForm<'f> for Settings {
     fn from_form(it: &mut FormItems<'f>, strict: bool) -> Result<Self, Self::Error> {
         let mut user = None;
         let mut categories = Vec::new();
+        let mut tags = Vec::new();
+        let mut locale = None;
+        let mut practice = false;
+        let mut duel = false;
 
         for (key, val) in it.map(|fi| fi.key_value()) {
             match key.url_decode_lossy().as_str() {
@@ -56,6 +84,12 @@ impl <'f> rocket::request::FromForm<'f> for Settings {
                 "categories" => categories.push(
                     db::CategoryId::from_form_value(val)?
                 ),
+                "tags" => tags.push(
+                    String::from_form_value(val)?
+                ),
+                "locale" => locale = Some(Locale::from_form_value(val)?),
+                "practice" => practice = bool::from_form_value(val)?,
+                "duel" => duel = bool::from_form_value(val)?,
                 _ if strict => return Err(val),
                 _ => {}
             }
@@ -65,7 +99,11 @@ impl <'f> rocket::request::FromForm<'f> for Settings {
 
         Ok(Settings {
             user,
-            categories
+            categories,
+            tags,
+            locale,
+            practice,
+            duel
         })
     }
 }
\ No newline at end of file
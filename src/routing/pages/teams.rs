@@ -0,0 +1,317 @@
+use {
+    serde::Serialize,
+    std::{
+        time::Instant,
+        sync::{Arc, Mutex}
+    },
+    super::rooms::Join,
+    rocket_contrib::templates::Template,
+    rocket::{
+        get,
+        post,
+        State,
+        FromForm,
+        response::Redirect,
+        request::{Form, FormItems, FromFormValue},
+        http::{Status, RawStr}
+    },
+    crate::models::{
+        self,
+        rooms::{self, RoomCode},
+        teams::{Team, TeamError, TeamResult, Voting},
+        game::{Answered, pseudo_shuffle},
+        web::{Or500, Session, NewSession, SyncedTeams, EventStream, Visitor},
+        i18n::{self, Locale},
+        db::{
+            DbConn,
+            CategoryId,
+            QuestionId,
+            models::{Category, TeamScore, NewTeamScore}
+        }
+    }
+};
+
+const LEADERBOARD_SIZE: i64 = 25;
+
+fn status(e: TeamError) -> Status {
+    match e {
+        TeamError::NotInTeam => Status::Forbidden,
+        TeamError::NameTaken | TeamError::AlreadyVoted | TeamError::WrongQuestion => Status::Conflict,
+        TeamError::Finished => Status::UnprocessableEntity
+    }
+}
+
+// the lock on all teams is only held to look one up,
+// the team's own lock is what's held while it's played
+fn find(code: &RoomCode, teams: &SyncedTeams) -> Result<Arc<Mutex<Team>>, Status> {
+    teams
+        .lock()
+        .or_500()?
+        .get(code)
+        .cloned()
+        .ok_or(Status::NotFound)
+}
+
+#[derive(Serialize)]
+struct Teams {
+    categories: Vec<Category>
+}
+
+#[get("/teams")]
+pub fn teams(locale: Locale, conn: DbConn) -> Result<Template, Status> {
+    Ok(i18n::render("teams", locale, Teams {
+        categories: Category::load_all(&conn).or_500()?
+    }))
+}
+
+#[derive(Debug)]
+pub struct NewTeam {
+    team: String,
+    name: String,
+    voting: Voting,
+    categories: Vec<CategoryId>
+}
+
+impl <'f> rocket::request::FromForm<'f> for NewTeam {
+    type Error = &'f RawStr;
+
+    fn from_form(it: &mut FormItems<'f>, strict: bool) -> Result<Self, Self::Error> {
+        let mut team = None;
+        let mut name = None;
+        let mut voting = Voting::First;
+        let mut categories = Vec::new();
+
+        for (key, val) in it.map(|fi| fi.key_value()) {
+            match key.url_decode_lossy().as_str() {
+                "team" => team = Some(String::from_form_value(val)?),
+                "name" => name = Some(String::from_form_value(val)?),
+                "voting" => voting = Voting::from_form_value(val)?,
+                "categories" => categories.push(
+                    CategoryId::from_form_value(val)?
+                ),
+                _ if strict => return Err(val),
+                _ => {}
+            }
+        }
+
+        Ok(NewTeam {
+            team: team.ok_or(RawStr::from_str("team not specified"))?,
+            name: name.ok_or(RawStr::from_str("name not specified"))?,
+            voting,
+            categories
+        })
+    }
+}
+
+#[post("/teams/create", data = "<new>")]
+pub fn create(
+    new: Form<NewTeam>,
+    sess: NewSession,
+    teams: State<SyncedTeams>,
+    visitor: Visitor,
+    locale: Locale,
+    conn: DbConn
+) -> Result<Redirect, Status> {
+    let NewTeam { team, name, voting, categories } = new.into_inner();
+    let (team, name) = (team.trim(), name.trim());
+    if team.is_empty() || name.is_empty() {
+        return Err(Status::BadRequest)
+    }
+
+    // the team plays under its own name, so its games show up like anyone else's
+    let game = models::game::new_game_state(team.to_string(), None, &visitor, rand::random(), &categories, Vec::new(), locale, &conn)
+        .or_500()?;
+    let mut team = Team::new(team.to_string(), voting, game);
+    team.join(sess.into(), name.to_string())
+        .map_err(status)?;
+    if !team.next_question(&conn, Instant::now()).or_500()? {
+        finish(&mut team, &conn).or_500()?;
+    }
+
+    let team = Arc::new(Mutex::new(team));
+    let code = rooms::insert_with_new_code(&mut *teams.lock().or_500()?, team, Instant::now());
+
+    Ok(Redirect::to(format!("/teams/{}", code.as_str())))
+}
+
+#[post("/teams/join", data = "<join>")]
+pub fn join(join: Form<Join>, sess: NewSession, teams: State<SyncedTeams>) -> Result<Redirect, Status> {
+    let (code, name) = join.into_inner().parse()?;
+
+    find(&code, &teams)?
+        .lock()
+        .or_500()?
+        .join(sess.into(), name)
+        .map_err(status)?;
+
+    Ok(Redirect::to(format!("/teams/{}", code.as_str())))
+}
+
+#[derive(Serialize)]
+struct TeamPage<'a> {
+    code: &'a str,
+    team: &'a str,
+    members: Vec<String>,
+    voting: Voting,
+    id: QuestionId,
+    question: &'a str,
+    category: &'a str,
+    answers: Vec<&'a str>,
+    points: i32,
+    votes: usize,
+    voted: bool,
+    remaining_secs: u64
+}
+
+#[derive(Serialize)]
+struct TeamEnd<'a> {
+    team: &'a str,
+    members: Vec<String>,
+    result: &'a TeamResult
+}
+
+#[get("/teams/<code>")]
+pub fn team(code: RoomCode, sess: Session, teams: State<SyncedTeams>) -> Result<Template, Status> {
+    let team = find(&code, &teams)?;
+    let team = team
+        .lock()
+        .or_500()?;
+
+    if !team.is_member(&sess) {
+        return Err(Status::Forbidden)
+    }
+
+    let locale = team.game.locale();
+    if let Some(result) = team.result() {
+        return Ok(i18n::render("team_end", locale, TeamEnd {
+            team: team.name(),
+            members: team.members(),
+            result
+        }))
+    }
+
+    let (cat, question) = team.game
+        .current_question()
+        .or_500()?;
+    let mut answers = question
+        .incorrect
+        .iter()
+        .map(String::as_str)
+        .chain(std::iter::once(question.correct.as_str()))
+        .collect::<Vec<_>>();
+    pseudo_shuffle(&mut answers);
+
+    Ok(i18n::render("team", locale, TeamPage {
+        code: code.as_str(),
+        team: team.name(),
+        members: team.members(),
+        voting: team.voting(),
+        id: question.id(),
+        question: &question.string,
+        category: &cat.name,
+        answers,
+        points: team.game.points(),
+        votes: team.votes(),
+        voted: team.has_voted(&sess),
+        remaining_secs: team.remaining(Instant::now()).as_secs()
+    }))
+}
+
+fn finish(team: &mut Team, conn: &DbConn) -> diesel::QueryResult<()> {
+    let members = team.members();
+    let score = TeamScore::insert(&NewTeamScore::of(team.name(), &members, team.game.score()), conn)?;
+
+    team.finish(TeamResult {
+        points: score.points,
+        weighted_points: score.weighted_points,
+        placement: score.placement(conn)?
+    });
+    Ok(())
+}
+
+// answers for the whole team and moves it on to the next question
+fn settle(team: &mut Team, answer: &str, conn: &DbConn) -> Result<(), Status> {
+    let answered = models::game::answer(answer, &mut team.game, conn)
+        .or_500()?;
+    let next = match answered {
+        Answered::Correctly => team.next_question(conn, Instant::now()).or_500()?,
+        // like in a single game, one wrong answer ends it
+        Answered::Incorrectly => false
+    };
+
+    if !next {
+        finish(team, conn).or_500()?;
+    }
+    Ok(())
+}
+
+#[derive(FromForm, Debug)]
+pub struct TeamVote {
+    question: QuestionId,
+    answer: String
+}
+
+#[post("/teams/<code>/answer", data = "<vote>")]
+pub fn answer(code: RoomCode, vote: Form<TeamVote>, sess: Session, teams: State<SyncedTeams>, conn: DbConn) -> Result<Redirect, Status> {
+    let TeamVote { question, answer } = vote.into_inner();
+    let team = find(&code, &teams)?;
+    let mut team = team
+        .lock()
+        .or_500()?;
+
+    let settled = team.vote(&sess, question, answer, Instant::now())
+        .map_err(status)?;
+    if let Some(answer) = settled {
+        settle(&mut team, &answer, &conn)?;
+    }
+
+    Ok(Redirect::to(format!("/teams/{}", code.as_str())))
+}
+
+/// Settles a majority vote with the votes cast so far once the answer window is over,
+/// so members who left don't hold up the others.
+#[post("/teams/<code>/settle")]
+pub fn settle_overdue(code: RoomCode, sess: Session, teams: State<SyncedTeams>, conn: DbConn) -> Result<Redirect, Status> {
+    let team = find(&code, &teams)?;
+    let mut team = team
+        .lock()
+        .or_500()?;
+
+    if !team.is_member(&sess) {
+        return Err(Status::Forbidden)
+    }
+    let answer = team.settle_overdue(Instant::now())
+        .ok_or(Status::Conflict)?;
+    settle(&mut team, &answer, &conn)?;
+
+    Ok(Redirect::to(format!("/teams/{}", code.as_str())))
+}
+
+/// The same events as in a single game, so members can follow what the others answered.
+#[get("/teams/<code>/events")]
+pub fn events(code: RoomCode, sess: Session, teams: State<SyncedTeams>) -> Result<EventStream, Status> {
+    let team = find(&code, &teams)?;
+    let mut team = team
+        .lock()
+        .or_500()?;
+
+    if team.is_member(&sess) {
+        team.game
+            .subscribe(sess)
+            .ok_or(Status::ServiceUnavailable)
+    } else {
+        Err(Status::Forbidden)
+    }
+}
+
+#[derive(Serialize)]
+struct TeamLeaderboard {
+    scores: Vec<TeamScore>
+}
+
+#[get("/leaderboard/teams")]
+pub fn leaderboard(locale: Locale, conn: DbConn) -> Result<Template, Status> {
+    Ok(i18n::render("team_leaderboard", locale, TeamLeaderboard {
+        scores: TeamScore::leaderboard(0, LEADERBOARD_SIZE, &conn).or_500()?
+    }))
+}